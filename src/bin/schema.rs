@@ -1,10 +1,19 @@
 use cosmwasm_schema::{export_schema, remove_schemas, schema_for};
-use dd_registry_cw::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use dd_registry_cw::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, SudoMsg};
 use dd_registry_cw::msg::{
     UserInfoResponse, ReferrerResponse, DirectReferralsResponse, ReferralChainResponse,
     UserPointsResponse, LeaderboardResponse, PointsHistoryResponse, ConfigResponse,
     PointsRulesResponse, LevelStatsResponse, ValidationResponse, RewardAllocation,
+    TransferConfigResponse, TransferUsageResponse, HooksResponse, UsersSummaryResponse,
+    VoucherKeyResponse, VoucherStatusResponse, ReferralCodeResponse, ReferralCodesResponse,
+    BindReferrerConfigResponse, DeactivationConfigResponse, DownlineResponse, TeamSummaryResponse,
+    SimulateRegisterResponse, SimulateAllocationResponse, BudgetStatusResponse,
+    CampaignResponse, CampaignsResponse, SeasonResponse, SeasonLeaderboardResponse,
+    UserSeasonPointsResponse, UserPointsAtResponse, TotalPointsAtResponse,
+    DistributionResponse, DistributionClaimResponse, ReferralLeaderboardResponse, SupplyResponse,
 };
+use dd_registry_cw::voucher::VoucherSignDoc;
+use dd_registry_cw::hooks::RegistryHookExecuteMsg;
 
 fn main() {
     let out_dir = std::env::var("OUT_DIR").unwrap();
@@ -21,6 +30,8 @@ fn main() {
     export_schema(&schema_for!(InstantiateMsg), &schema_dir);
     export_schema(&schema_for!(ExecuteMsg), &schema_dir);
     export_schema(&schema_for!(QueryMsg), &schema_dir);
+    export_schema(&schema_for!(SudoMsg), &schema_dir);
+    export_schema(&schema_for!(MigrateMsg), &schema_dir);
     
     // 响应类型
     export_schema(&schema_for!(UserInfoResponse), &schema_dir);
@@ -35,6 +46,38 @@ fn main() {
     export_schema(&schema_for!(LevelStatsResponse), &schema_dir);
     export_schema(&schema_for!(ValidationResponse), &schema_dir);
     export_schema(&schema_for!(RewardAllocation), &schema_dir);
+    export_schema(&schema_for!(TransferConfigResponse), &schema_dir);
+    export_schema(&schema_for!(TransferUsageResponse), &schema_dir);
+    export_schema(&schema_for!(HooksResponse), &schema_dir);
+    export_schema(&schema_for!(UsersSummaryResponse), &schema_dir);
+    export_schema(&schema_for!(VoucherKeyResponse), &schema_dir);
+    export_schema(&schema_for!(VoucherStatusResponse), &schema_dir);
+    export_schema(&schema_for!(ReferralCodeResponse), &schema_dir);
+    export_schema(&schema_for!(ReferralCodesResponse), &schema_dir);
+    export_schema(&schema_for!(BindReferrerConfigResponse), &schema_dir);
+    export_schema(&schema_for!(DeactivationConfigResponse), &schema_dir);
+    export_schema(&schema_for!(DownlineResponse), &schema_dir);
+    export_schema(&schema_for!(TeamSummaryResponse), &schema_dir);
+    export_schema(&schema_for!(SimulateRegisterResponse), &schema_dir);
+    export_schema(&schema_for!(SimulateAllocationResponse), &schema_dir);
+    export_schema(&schema_for!(BudgetStatusResponse), &schema_dir);
+    export_schema(&schema_for!(CampaignResponse), &schema_dir);
+    export_schema(&schema_for!(CampaignsResponse), &schema_dir);
+    export_schema(&schema_for!(SeasonResponse), &schema_dir);
+    export_schema(&schema_for!(SeasonLeaderboardResponse), &schema_dir);
+    export_schema(&schema_for!(UserSeasonPointsResponse), &schema_dir);
+    export_schema(&schema_for!(UserPointsAtResponse), &schema_dir);
+    export_schema(&schema_for!(TotalPointsAtResponse), &schema_dir);
+    export_schema(&schema_for!(DistributionResponse), &schema_dir);
+    export_schema(&schema_for!(DistributionClaimResponse), &schema_dir);
+    export_schema(&schema_for!(ReferralLeaderboardResponse), &schema_dir);
+    export_schema(&schema_for!(SupplyResponse), &schema_dir);
+    
+    // 离线签名格式
+    export_schema(&schema_for!(VoucherSignDoc), &schema_dir);
+    
+    // 钩子消息
+    export_schema(&schema_for!(RegistryHookExecuteMsg), &schema_dir);
 
     println!("Schema files generated in: {:?}", schema_dir);
 }
@@ -0,0 +1,217 @@
+use cosmwasm_std::{Decimal, Deps, DepsMut, Env, Order, StdResult, Storage, Uint128};
+use cw_storage_plus::Bound;
+use crate::error::ContractError;
+use crate::state::{Campaign, PointsReason, RateOverrides, CAMPAIGNS, LIVE_CAMPAIGNS, NEXT_CAMPAIGN_ID};
+
+/// 同时生效（未结束）的活动数量上限
+pub const MAX_LIVE_CAMPAIGNS: usize = 20;
+
+/// 活动名称长度上限
+pub const MAX_CAMPAIGN_NAME_LENGTH: usize = 64;
+
+/// 活动列表分页默认与最大条数
+pub const DEFAULT_CAMPAIGN_LIMIT: u32 = 10;
+pub const MAX_CAMPAIGN_LIMIT: u32 = 50;
+
+fn invalid(parameter: &str, value: impl ToString) -> ContractError {
+    ContractError::InvalidParameter {
+        parameter: parameter.to_string(),
+        value: value.to_string(),
+    }
+}
+
+/// 按比例调整积分
+pub fn apply_multiplier(points: Uint128, multiplier: Decimal) -> Uint128 {
+    points.multiply_ratio(
+        multiplier.atomics(),
+        Uint128::from(10_u128.pow(multiplier.decimal_places()))
+    )
+}
+
+/// 剩余预算，未设预算时为 None
+pub fn remaining_budget(campaign: &Campaign) -> Option<Uint128> {
+    campaign.budget.map(|budget| budget.saturating_sub(campaign.spent))
+}
+
+/// 活动在当前时间是否生效
+pub fn is_running(campaign: &Campaign, now: u64) -> bool {
+    !campaign.cancelled
+        && campaign.start_time <= now
+        && now <= campaign.end_time
+        && remaining_budget(campaign) != Some(Uint128::zero())
+}
+
+/// 活动是否已无法再生效
+fn is_finished(campaign: &Campaign, now: u64) -> bool {
+    campaign.cancelled || now > campaign.end_time || remaining_budget(campaign) == Some(Uint128::zero())
+}
+
+/// 校验费率覆盖
+fn validate_rate_overrides(overrides: &RateOverrides) -> Result<(), ContractError> {
+    for rate in [overrides.direct_referral_rate, overrides.level_2_rate, overrides.level_3_rate]
+        .into_iter()
+        .flatten()
+    {
+        if rate > Decimal::one() {
+            return Err(invalid("rate_overrides", rate));
+        }
+    }
+    Ok(())
+}
+
+/// 创建活动
+#[allow(clippy::too_many_arguments)]
+pub fn create_campaign(
+    deps: DepsMut,
+    env: &Env,
+    name: String,
+    start_time: u64,
+    end_time: u64,
+    budget: Option<Uint128>,
+    multiplier: Option<Decimal>,
+    rate_overrides: Option<RateOverrides>,
+    eligible_reasons: Vec<PointsReason>,
+) -> Result<Campaign, ContractError> {
+    let now = env.block.time.seconds();
+    
+    if name.is_empty() || name.len() > MAX_CAMPAIGN_NAME_LENGTH {
+        return Err(invalid("name", format!("length {}", name.len())));
+    }
+    if end_time <= start_time || end_time <= now {
+        return Err(invalid("end_time", end_time));
+    }
+    if budget == Some(Uint128::zero()) {
+        return Err(invalid("budget", 0));
+    }
+    
+    let multiplier = multiplier.unwrap_or_else(Decimal::one);
+    if multiplier.is_zero() {
+        return Err(invalid("multiplier", multiplier));
+    }
+    if multiplier == Decimal::one() && rate_overrides.is_none() {
+        return Err(invalid("multiplier", "campaign changes nothing"));
+    }
+    if let Some(ref overrides) = rate_overrides {
+        validate_rate_overrides(overrides)?;
+    }
+    
+    // 转账类积分不参与活动
+    if eligible_reasons.is_empty() {
+        return Err(invalid("eligible_reasons", "empty"));
+    }
+    if let Some(reason) = eligible_reasons
+        .iter()
+        .find(|reason| matches!(reason, PointsReason::TransferOut | PointsReason::TransferIn | PointsReason::TransferFee))
+    {
+        return Err(invalid("eligible_reasons", format!("{:?}", reason)));
+    }
+    
+    prune_finished(deps.storage, now)?;
+    let live = LIVE_CAMPAIGNS.keys(deps.storage, None, None, Order::Ascending).count();
+    if live >= MAX_LIVE_CAMPAIGNS {
+        return Err(ContractError::LimitExceeded {
+            limit_type: "live_campaigns".to_string(),
+            limit_value: MAX_LIVE_CAMPAIGNS as u32,
+            actual_value: (live + 1) as u32,
+        });
+    }
+    
+    let id = NEXT_CAMPAIGN_ID.may_load(deps.storage)?.unwrap_or(1);
+    NEXT_CAMPAIGN_ID.save(deps.storage, &(id + 1))?;
+    
+    let campaign = Campaign {
+        id,
+        name,
+        start_time,
+        end_time,
+        budget,
+        spent: Uint128::zero(),
+        multiplier,
+        rate_overrides,
+        eligible_reasons,
+        cancelled: false,
+        created_at: now,
+    };
+    CAMPAIGNS.save(deps.storage, id, &campaign)?;
+    LIVE_CAMPAIGNS.save(deps.storage, id, &true)?;
+    
+    Ok(campaign)
+}
+
+/// 取消活动
+pub fn cancel_campaign(deps: DepsMut, id: u64) -> Result<Campaign, ContractError> {
+    let mut campaign = CAMPAIGNS
+        .may_load(deps.storage, id)?
+        .ok_or_else(|| invalid("campaign_id", id))?;
+    if campaign.cancelled {
+        return Err(invalid("campaign_id", id));
+    }
+    
+    campaign.cancelled = true;
+    CAMPAIGNS.save(deps.storage, id, &campaign)?;
+    LIVE_CAMPAIGNS.remove(deps.storage, id);
+    
+    Ok(campaign)
+}
+
+/// 移出已结束、已取消或预算用尽的活动
+fn prune_finished(storage: &mut dyn Storage, now: u64) -> StdResult<()> {
+    let ids = LIVE_CAMPAIGNS
+        .keys(storage, None, None, Order::Ascending)
+        .collect::<StdResult<Vec<_>>>()?;
+    for id in ids {
+        if is_finished(&CAMPAIGNS.load(storage, id)?, now) {
+            LIVE_CAMPAIGNS.remove(storage, id);
+        }
+    }
+    Ok(())
+}
+
+/// 查找对该积分原因生效的活动，多个活动重叠时取最早创建的一个
+pub fn find_campaign(
+    storage: &dyn Storage,
+    env: &Env,
+    reason: &PointsReason,
+) -> StdResult<Option<Campaign>> {
+    let now = env.block.time.seconds();
+    for id in LIVE_CAMPAIGNS.keys(storage, None, None, Order::Ascending) {
+        let campaign = CAMPAIGNS.load(storage, id?)?;
+        if is_running(&campaign, now) && campaign.eligible_reasons.contains(reason) {
+            return Ok(Some(campaign));
+        }
+    }
+    Ok(None)
+}
+
+/// 记录活动已发放的积分，预算用尽后活动停止
+pub fn charge_campaign(
+    storage: &mut dyn Storage,
+    env: &Env,
+    id: u64,
+    points: Uint128,
+) -> StdResult<()> {
+    let mut campaign = CAMPAIGNS.load(storage, id)?;
+    campaign.spent += points;
+    CAMPAIGNS.save(storage, id, &campaign)?;
+    
+    if is_finished(&campaign, env.block.time.seconds()) {
+        LIVE_CAMPAIGNS.remove(storage, id);
+    }
+    Ok(())
+}
+
+/// 分页列出活动
+pub fn list_campaigns(
+    deps: Deps,
+    start_after: Option<u64>,
+    limit: Option<u32>,
+) -> StdResult<Vec<Campaign>> {
+    let limit = limit.unwrap_or(DEFAULT_CAMPAIGN_LIMIT).min(MAX_CAMPAIGN_LIMIT) as usize;
+    let min = start_after.map(Bound::exclusive);
+    
+    CAMPAIGNS
+        .range(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .map(|item| item.map(|(_, campaign)| campaign))
+        .collect()
+}
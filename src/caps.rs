@@ -0,0 +1,171 @@
+use cosmwasm_std::{Addr, Deps, Env, StdResult, Storage, Uint128};
+use crate::error::ContractError;
+use crate::msg::{BudgetHeadroom, BudgetStatusResponse, ReasonBudgetStatus};
+use crate::state::{
+    CapPolicy, EarningCapsConfig, EarningUsage, PeriodUsage, PointsReason, EARNING_CAPS,
+    GLOBAL_BUDGET_USAGE, REASON_BUDGET_USAGE, USER_EARNINGS,
+};
+use crate::transfer::SECONDS_PER_DAY;
+
+/// 积分原因的存储键
+fn reason_key(reason: &PointsReason) -> String {
+    format!("{:?}", reason)
+}
+
+/// 转账类积分只是流动，不计入上限与预算
+fn is_minting(reason: &PointsReason) -> bool {
+    !matches!(reason, PointsReason::TransferIn | PointsReason::TransferFee)
+}
+
+/// 校验上限配置
+pub fn validate_earning_caps(config: &EarningCapsConfig) -> Result<(), ContractError> {
+    if config.period == 0 {
+        return Err(ContractError::InvalidParameter {
+            parameter: "period".to_string(),
+            value: "0".to_string(),
+        });
+    }
+    
+    for (index, cap) in config.caps.iter().enumerate() {
+        if !is_minting(&cap.reason) || config.caps[..index].iter().any(|other| other.reason == cap.reason) {
+            return Err(ContractError::InvalidParameter {
+                parameter: "caps.reason".to_string(),
+                value: reason_key(&cap.reason),
+            });
+        }
+    }
+    
+    Ok(())
+}
+
+fn load_config(storage: &dyn Storage) -> StdResult<EarningCapsConfig> {
+    Ok(EARNING_CAPS.may_load(storage)?.unwrap_or_default())
+}
+
+/// 读取用户某原因的获得量，跨日后重置当日用量
+fn load_user_usage(storage: &dyn Storage, env: &Env, user: &Addr, key: &str) -> StdResult<EarningUsage> {
+    let today = env.block.time.seconds() / SECONDS_PER_DAY;
+    let mut usage = USER_EARNINGS.may_load(storage, (user, key))?.unwrap_or_default();
+    if usage.day != today {
+        usage.day = today;
+        usage.daily = Uint128::zero();
+    }
+    Ok(usage)
+}
+
+/// 读取周期用量，进入新周期后重置
+fn current_period_usage(usage: Option<PeriodUsage>, env: &Env, period: u64) -> PeriodUsage {
+    let current = env.block.time.seconds() / period.max(1);
+    match usage {
+        Some(usage) if usage.period == current => usage,
+        _ => PeriodUsage { period: current, amount: Uint128::zero() },
+    }
+}
+
+/// 按上限与预算调整本次发放的积分
+///
+/// 超出时按策略截断或拒绝，返回实际可发放的积分并记录用量。
+/// 用量仅在对应上限配置后开始累计，终身上限即自配置起的累计上限。
+pub fn apply_earning_caps(
+    storage: &mut dyn Storage,
+    env: &Env,
+    user: &Addr,
+    points: Uint128,
+    reason: &PointsReason,
+) -> Result<Uint128, ContractError> {
+    if points.is_zero() || !is_minting(reason) {
+        return Ok(points);
+    }
+    
+    let config = load_config(storage)?;
+    let cap = config.caps.iter().find(|cap| cap.reason == *reason);
+    if cap.is_none() && config.global_budget.is_none() {
+        return Ok(points);
+    }
+    
+    let key = reason_key(reason);
+    let mut user_usage = load_user_usage(storage, env, user, &key)?;
+    let mut reason_usage = current_period_usage(REASON_BUDGET_USAGE.may_load(storage, &key)?, env, config.period);
+    let mut global_usage = current_period_usage(GLOBAL_BUDGET_USAGE.may_load(storage)?, env, config.period);
+    
+    let mut limits = Vec::new();
+    if let Some(cap) = cap {
+        if let Some(limit) = cap.user_daily {
+            limits.push(("user_daily", limit, user_usage.daily));
+        }
+        if let Some(limit) = cap.user_lifetime {
+            limits.push(("user_lifetime", limit, user_usage.lifetime));
+        }
+        if let Some(limit) = cap.global_per_period {
+            limits.push(("reason_budget", limit, reason_usage.amount));
+        }
+    }
+    if let Some(limit) = config.global_budget {
+        limits.push(("global_budget", limit, global_usage.amount));
+    }
+    
+    let mut allowed = points;
+    for (limit_type, limit, used) in limits {
+        let headroom = limit.saturating_sub(used);
+        if headroom < points && config.policy == CapPolicy::Reject {
+            return Err(ContractError::AmountLimitExceeded {
+                limit_type: limit_type.to_string(),
+                limit_value: limit,
+                actual_value: used + points,
+            });
+        }
+        allowed = allowed.min(headroom);
+    }
+    
+    if cap.is_some() {
+        user_usage.daily += allowed;
+        user_usage.lifetime += allowed;
+        reason_usage.amount += allowed;
+        USER_EARNINGS.save(storage, (user, &key), &user_usage)?;
+        REASON_BUDGET_USAGE.save(storage, &key, &reason_usage)?;
+    }
+    if config.global_budget.is_some() {
+        global_usage.amount += allowed;
+        GLOBAL_BUDGET_USAGE.save(storage, &global_usage)?;
+    }
+    
+    Ok(allowed)
+}
+
+fn headroom(limit: Option<Uint128>, used: Uint128) -> Option<BudgetHeadroom> {
+    limit.map(|limit| BudgetHeadroom {
+        limit,
+        used,
+        remaining: limit.saturating_sub(used),
+    })
+}
+
+/// 查询上限与预算余量
+pub fn get_budget_status(deps: Deps, env: &Env, user: Option<&Addr>) -> StdResult<BudgetStatusResponse> {
+    let config = load_config(deps.storage)?;
+    let global_usage = current_period_usage(GLOBAL_BUDGET_USAGE.may_load(deps.storage)?, env, config.period);
+    
+    let mut reasons = Vec::new();
+    for cap in &config.caps {
+        let key = reason_key(&cap.reason);
+        let reason_usage = current_period_usage(REASON_BUDGET_USAGE.may_load(deps.storage, &key)?, env, config.period);
+        let user_usage = user
+            .map(|user| load_user_usage(deps.storage, env, user, &key))
+            .transpose()?;
+        
+        reasons.push(ReasonBudgetStatus {
+            reason: cap.reason.clone(),
+            budget: headroom(cap.global_per_period, reason_usage.amount),
+            user_daily: user_usage.as_ref().and_then(|usage| headroom(cap.user_daily, usage.daily)),
+            user_lifetime: user_usage.as_ref().and_then(|usage| headroom(cap.user_lifetime, usage.lifetime)),
+        });
+    }
+    
+    Ok(BudgetStatusResponse {
+        policy: config.policy,
+        period: config.period,
+        period_index: global_usage.period,
+        global: headroom(config.global_budget, global_usage.amount),
+        reasons,
+    })
+}
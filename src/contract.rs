@@ -1,8 +1,8 @@
 use cosmwasm_std::{
-    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Response, StdResult,
+    entry_point, to_json_binary, Binary, Deps, DepsMut, Env, MessageInfo, Reply, Response, StdResult,
 };
 use crate::error::ContractError;
-use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+use crate::msg::{ExecuteMsg, InstantiateMsg, MigrateMsg, QueryMsg, SudoMsg};
 
 #[entry_point]
 pub fn instantiate(
@@ -32,9 +32,12 @@ pub fn execute(
     info: MessageInfo,
     msg: ExecuteMsg,
 ) -> Result<Response, ContractError> {
+    // 外部调用未完成前拒绝重入
+    crate::security::check_reentrancy_lock(deps.as_ref())?;
+    
     match msg {
-        ExecuteMsg::Register { referrer } => {
-            crate::execute::execute_register(deps, env, info, referrer)
+        ExecuteMsg::Register { referrer, referral_code } => {
+            crate::execute::execute_register(deps, env, info, referrer, referral_code)
         }
         ExecuteMsg::AllocateRewards {
             user,
@@ -62,11 +65,119 @@ pub fn execute(
         ExecuteMsg::EmergencyPause { paused } => {
             crate::execute::execute_emergency_pause(deps, env, info, paused)
         }
+        ExecuteMsg::TransferPoints { to, amount, memo } => {
+            crate::execute::execute_transfer_points(deps, env, info, to, amount, memo)
+        }
+        ExecuteMsg::UpdateTransferConfig { config } => {
+            crate::execute::execute_update_transfer_config(deps, env, info, config)
+        }
+        ExecuteMsg::AddHook { addr, events } => {
+            crate::execute::execute_add_hook(deps, env, info, addr, events)
+        }
+        ExecuteMsg::RemoveHook { addr } => {
+            crate::execute::execute_remove_hook(deps, env, info, addr)
+        }
+        ExecuteMsg::RegisterVoucherKey { public_key } => {
+            crate::execute::execute_register_voucher_key(deps, env, info, public_key)
+        }
+        ExecuteMsg::RegisterWithVoucher { voucher, signature } => {
+            crate::execute::execute_register_with_voucher(deps, env, info, voucher, signature)
+        }
+        ExecuteMsg::CreateReferralCode { code, max_uses, expires_at } => {
+            crate::execute::execute_create_referral_code(deps, env, info, code, max_uses, expires_at)
+        }
+        ExecuteMsg::ImportUsers { users } => {
+            crate::execute::execute_import_users(deps, env, info, users)
+        }
+        ExecuteMsg::ReassignReferrer { user, new_referrer, reason, reward_action } => {
+            crate::execute::execute_reassign_referrer(deps, env, info, user, new_referrer, reason, reward_action)
+        }
+        ExecuteMsg::BindReferrer { referrer } => {
+            crate::execute::execute_bind_referrer(deps, env, info, referrer)
+        }
+        ExecuteMsg::UpdateBindReferrerConfig { config } => {
+            crate::execute::execute_update_bind_referrer_config(deps, env, info, config)
+        }
+        ExecuteMsg::Deactivate {} => {
+            crate::execute::execute_deactivate(deps, env, info)
+        }
+        ExecuteMsg::AdminDeactivate { user, reason } => {
+            crate::execute::execute_admin_deactivate(deps, env, info, user, reason)
+        }
+        ExecuteMsg::UpdateDeactivationConfig { config } => {
+            crate::execute::execute_update_deactivation_config(deps, env, info, config)
+        }
+        ExecuteMsg::UpdateEarningCaps { config } => {
+            crate::execute::execute_update_earning_caps(deps, env, info, config)
+        }
+        ExecuteMsg::CreateCampaign {
+            name,
+            start_time,
+            end_time,
+            budget,
+            multiplier,
+            rate_overrides,
+            eligible_reasons,
+        } => crate::execute::execute_create_campaign(
+            deps,
+            env,
+            info,
+            name,
+            start_time,
+            end_time,
+            budget,
+            multiplier,
+            rate_overrides,
+            eligible_reasons,
+        ),
+        ExecuteMsg::CancelCampaign { id } => {
+            crate::execute::execute_cancel_campaign(deps, env, info, id)
+        }
+        ExecuteMsg::StartSeason { name } => {
+            crate::execute::execute_start_season(deps, env, info, name)
+        }
+        ExecuteMsg::CloseSeason {} => {
+            crate::execute::execute_close_season(deps, env, info)
+        }
+        ExecuteMsg::CreateDistribution { merkle_root, total, expires_at } => {
+            crate::execute::execute_create_distribution(deps, env, info, merkle_root, total, expires_at)
+        }
+        ExecuteMsg::ClaimDistribution { id, amount, proof } => {
+            crate::execute::execute_claim_distribution(deps, env, info, id, amount, proof)
+        }
+        ExecuteMsg::ReclaimDistribution { id, recipient } => {
+            crate::execute::execute_reclaim_distribution(deps, env, info, id, recipient)
+        }
     }
 }
 
 #[entry_point]
-pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
+pub fn sudo(deps: DepsMut, env: Env, msg: SudoMsg) -> Result<Response, ContractError> {
+    match msg {
+        SudoMsg::ForcePause { paused } => {
+            crate::execute::sudo_force_pause(deps, env, paused)
+        }
+        SudoMsg::ReplaceAdmin { admin } => {
+            crate::execute::sudo_replace_admin(deps, env, admin)
+        }
+        SudoMsg::FreezeUsers { users, frozen } => {
+            crate::execute::sudo_freeze_users(deps, env, users, frozen)
+        }
+    }
+}
+
+#[entry_point]
+pub fn migrate(deps: DepsMut, env: Env, _msg: MigrateMsg) -> Result<Response, ContractError> {
+    crate::migration::migrate(deps, env)
+}
+
+#[entry_point]
+pub fn reply(deps: DepsMut, env: Env, msg: Reply) -> Result<Response, ContractError> {
+    crate::guard::handle_reply(deps, env, msg)
+}
+
+#[entry_point]
+pub fn query(deps: Deps, env: Env, msg: QueryMsg) -> StdResult<Binary> {
     match msg {
         QueryMsg::GetUserInfo { user } => {
             to_json_binary(&crate::query::query_user_info(deps, user)?)
@@ -87,15 +198,21 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
         QueryMsg::GetUserPoints { user } => {
             to_json_binary(&crate::query::query_user_points(deps, user)?)
         }
-        QueryMsg::GetPointsLeaderboard { limit, start_after } => {
-            to_json_binary(&crate::query::query_points_leaderboard(deps, limit, start_after)?)
+        QueryMsg::GetPointsLeaderboard { limit, start_after, filter } => {
+            to_json_binary(&crate::query::query_points_leaderboard(deps, limit, start_after, filter)?)
+        }
+        QueryMsg::GetReferralLeaderboard { limit, start_after, filter } => {
+            to_json_binary(&crate::query::query_referral_leaderboard(deps, limit, start_after, filter)?)
         }
         QueryMsg::GetPointsHistory {
             user,
             limit,
             start_after,
+            filter,
+            order,
+            cursor,
         } => {
-            to_json_binary(&crate::query::query_points_history(deps, user, limit, start_after)?)
+            to_json_binary(&crate::query::query_points_history(deps, user, limit, start_after, filter, order, cursor)?)
         }
         QueryMsg::GetConfig {} => {
             to_json_binary(&crate::query::query_config(deps)?)
@@ -107,7 +224,82 @@ pub fn query(deps: Deps, _env: Env, msg: QueryMsg) -> StdResult<Binary> {
             to_json_binary(&crate::query::query_level_stats(deps)?)
         }
         QueryMsg::ValidateReferral { referrer, referee } => {
-            to_json_binary(&crate::query::query_validate_referral(deps, referrer, referee)?)
+            to_json_binary(&crate::query::query_validate_referral(deps, env, referrer, referee)?)
+        }
+        QueryMsg::GetTransferConfig {} => {
+            to_json_binary(&crate::query::query_transfer_config(deps)?)
+        }
+        QueryMsg::GetTransferUsage { user } => {
+            to_json_binary(&crate::query::query_transfer_usage(deps, env, user)?)
+        }
+        QueryMsg::ListHooks {} => {
+            to_json_binary(&crate::query::query_hooks(deps)?)
+        }
+        QueryMsg::GetUsersSummary { users } => {
+            to_json_binary(&crate::query::query_users_summary(deps, users)?)
+        }
+        QueryMsg::GetVoucherKey { user } => {
+            to_json_binary(&crate::query::query_voucher_key(deps, user)?)
+        }
+        QueryMsg::GetVoucherStatus { referrer, nonce } => {
+            to_json_binary(&crate::query::query_voucher_status(deps, referrer, nonce)?)
+        }
+        QueryMsg::ResolveReferralCode { code } => {
+            to_json_binary(&crate::query::query_resolve_referral_code(deps, env, code)?)
+        }
+        QueryMsg::ListMyCodes { user, start_after, limit } => {
+            to_json_binary(&crate::query::query_user_codes(deps, user, start_after, limit)?)
+        }
+        QueryMsg::GetBindReferrerConfig {} => {
+            to_json_binary(&crate::query::query_bind_referrer_config(deps)?)
+        }
+        QueryMsg::GetDeactivationConfig {} => {
+            to_json_binary(&crate::query::query_deactivation_config(deps)?)
+        }
+        QueryMsg::GetDownline { user, max_depth, start_after, limit } => {
+            to_json_binary(&crate::query::query_downline(deps, user, max_depth, start_after, limit)?)
+        }
+        QueryMsg::GetTeamSummary { user } => {
+            to_json_binary(&crate::query::query_team_summary(deps, user)?)
+        }
+        QueryMsg::SimulateRegister { user, referrer } => {
+            to_json_binary(&crate::query::query_simulate_register(deps, env, user, referrer)?)
+        }
+        QueryMsg::SimulateAllocation { user, base_points, reason } => {
+            to_json_binary(&crate::query::query_simulate_allocation(deps, env, user, base_points, reason)?)
+        }
+        QueryMsg::GetBudgetStatus { user } => {
+            to_json_binary(&crate::query::query_budget_status(deps, env, user)?)
+        }
+        QueryMsg::GetCampaign { id } => {
+            to_json_binary(&crate::query::query_campaign(deps, env, id)?)
+        }
+        QueryMsg::ListCampaigns { start_after, limit } => {
+            to_json_binary(&crate::query::query_campaigns(deps, start_after, limit)?)
+        }
+        QueryMsg::GetSeason { season_id } => {
+            to_json_binary(&crate::query::query_season(deps, season_id)?)
+        }
+        QueryMsg::GetSeasonLeaderboard { season_id, limit } => {
+            to_json_binary(&crate::query::query_season_leaderboard(deps, season_id, limit)?)
+        }
+        QueryMsg::GetUserSeasonPoints { season_id, user } => {
+            to_json_binary(&crate::query::query_user_season_points(deps, season_id, user)?)
+        }
+        QueryMsg::GetUserPointsAt { user, height } => {
+            to_json_binary(&crate::query::query_user_points_at(deps, user, height)?)
+        }
+        QueryMsg::GetTotalPointsAt { height } => {
+            to_json_binary(&crate::query::query_total_points_at(deps, height)?)
+        }
+        QueryMsg::GetDistribution { id } => {
+            to_json_binary(&crate::query::query_distribution(deps, env, id)?)
+        }
+        QueryMsg::GetDistributionClaim { id, user } => {
+            to_json_binary(&crate::query::query_distribution_claim(deps, id, user)?)
+        }
+        QueryMsg::GetSupply {} => {
+            to_json_binary(&crate::query::query_supply(deps)?)
         }
     }
 }
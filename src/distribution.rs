@@ -0,0 +1,181 @@
+use cosmwasm_std::{Addr, Binary, DepsMut, Env, Response, Uint128};
+use sha2::{Digest, Sha256};
+use crate::error::ContractError;
+use crate::state::{Distribution, PointsReason, DISTRIBUTIONS, DISTRIBUTION_CLAIMS, NEXT_DISTRIBUTION_ID};
+
+/// sha256 哈希长度
+pub const HASH_LENGTH: usize = 32;
+
+/// 默克尔证明的最大层数
+pub const MAX_PROOF_LENGTH: usize = 32;
+
+fn invalid(parameter: &str, value: impl ToString) -> ContractError {
+    ContractError::InvalidParameter {
+        parameter: parameter.to_string(),
+        value: value.to_string(),
+    }
+}
+
+fn load_distribution(deps: &DepsMut, id: u64) -> Result<Distribution, ContractError> {
+    DISTRIBUTIONS
+        .may_load(deps.storage, id)?
+        .ok_or_else(|| invalid("distribution_id", id))
+}
+
+/// 叶子节点：sha256("{地址}{数量}")
+pub fn distribution_leaf(user: &Addr, amount: Uint128) -> [u8; HASH_LENGTH] {
+    Sha256::digest(format!("{}{}", user, amount).as_bytes()).into()
+}
+
+/// 沿证明逐层计算根，每层按字节序排列两个节点后拼接哈希
+pub fn compute_merkle_root(leaf: [u8; HASH_LENGTH], proof: &[Binary]) -> Result<[u8; HASH_LENGTH], ContractError> {
+    if proof.len() > MAX_PROOF_LENGTH {
+        return Err(ContractError::InvalidMerkleProof);
+    }
+    
+    proof.iter().try_fold(leaf, |node, sibling| {
+        let sibling: [u8; HASH_LENGTH] = sibling
+            .as_slice()
+            .try_into()
+            .map_err(|_| ContractError::InvalidMerkleProof)?;
+        let (left, right) = if node <= sibling { (node, sibling) } else { (sibling, node) };
+        let mut hasher = Sha256::new();
+        hasher.update(left);
+        hasher.update(right);
+        Ok(hasher.finalize().into())
+    })
+}
+
+/// 发布分发
+pub fn create_distribution(
+    deps: DepsMut,
+    env: &Env,
+    merkle_root: Binary,
+    total: Uint128,
+    expires_at: u64,
+) -> Result<Distribution, ContractError> {
+    if merkle_root.len() != HASH_LENGTH {
+        return Err(invalid("merkle_root", format!("length {}", merkle_root.len())));
+    }
+    if total.is_zero() {
+        return Err(invalid("total", total));
+    }
+    if expires_at <= env.block.time.seconds() {
+        return Err(invalid("expires_at", expires_at));
+    }
+    
+    let id = NEXT_DISTRIBUTION_ID.may_load(deps.storage)?.unwrap_or(1);
+    NEXT_DISTRIBUTION_ID.save(deps.storage, &(id + 1))?;
+    
+    let distribution = Distribution {
+        id,
+        merkle_root,
+        total,
+        claimed: Uint128::zero(),
+        expires_at,
+        created_at: env.block.time.seconds(),
+        reclaimed: None,
+    };
+    DISTRIBUTIONS.save(deps.storage, id, &distribution)?;
+    
+    Ok(distribution)
+}
+
+/// 用户凭默克尔证明领取分发积分，每个地址只能领取一次
+pub fn claim_distribution(
+    mut deps: DepsMut,
+    env: &Env,
+    user: &Addr,
+    id: u64,
+    amount: Uint128,
+    proof: Vec<Binary>,
+) -> Result<Response, ContractError> {
+    let mut distribution = load_distribution(&deps, id)?;
+    
+    if distribution.reclaimed.is_some() || env.block.time.seconds() > distribution.expires_at {
+        return Err(ContractError::DistributionExpired {
+            id,
+            expires_at: distribution.expires_at,
+        });
+    }
+    // 已冻结、封禁或注销的用户不能领取
+    crate::security::check_user_active(deps.as_ref(), user)?;
+    if DISTRIBUTION_CLAIMS.has(deps.storage, (id, user)) {
+        return Err(ContractError::DistributionAlreadyClaimed {
+            id,
+            user: user.to_string(),
+        });
+    }
+    if amount.is_zero() {
+        return Err(invalid("amount", amount));
+    }
+    
+    let root = compute_merkle_root(distribution_leaf(user, amount), &proof)?;
+    if root.as_slice() != distribution.merkle_root.as_slice() {
+        return Err(ContractError::InvalidMerkleProof);
+    }
+    
+    // 默克尔树与总量由管理员分别提交，领取总额不得超过公布的总量
+    let claimed = distribution.claimed + amount;
+    if claimed > distribution.total {
+        return Err(ContractError::AmountLimitExceeded {
+            limit_type: "distribution_total".to_string(),
+            limit_value: distribution.total,
+            actual_value: claimed,
+        });
+    }
+    distribution.claimed = claimed;
+    DISTRIBUTIONS.save(deps.storage, id, &distribution)?;
+    DISTRIBUTION_CLAIMS.save(deps.storage, (id, user), &amount)?;
+    
+    crate::points::credit_points_exact(
+        &mut deps,
+        env,
+        user,
+        amount,
+        PointsReason::Distribution,
+        None,
+        Some(format!("distribution:{}", id)),
+    )
+}
+
+/// 过期后回收未领取的积分，可选择记入指定用户
+///
+/// 返回回收数量与发放响应。
+pub fn reclaim_distribution(
+    mut deps: DepsMut,
+    env: &Env,
+    id: u64,
+    recipient: Option<Addr>,
+) -> Result<(Uint128, Response), ContractError> {
+    let mut distribution = load_distribution(&deps, id)?;
+    
+    if distribution.reclaimed.is_some() {
+        return Err(invalid("distribution_id", format!("{} already reclaimed", id)));
+    }
+    if env.block.time.seconds() <= distribution.expires_at {
+        return Err(invalid("distribution_id", format!("{} not expired", id)));
+    }
+    
+    let unclaimed = distribution.total.saturating_sub(distribution.claimed);
+    distribution.reclaimed = Some(unclaimed);
+    DISTRIBUTIONS.save(deps.storage, id, &distribution)?;
+    
+    let response = match recipient {
+        Some(ref recipient) if !unclaimed.is_zero() => {
+            crate::security::check_user_active(deps.as_ref(), recipient)?;
+            crate::points::credit_points_exact(
+                &mut deps,
+                env,
+                recipient,
+                unclaimed,
+                PointsReason::Distribution,
+                None,
+                Some(format!("distribution_reclaim:{}", id)),
+            )?
+        }
+        _ => Response::new(),
+    };
+    
+    Ok((unclaimed, response))
+}
@@ -36,6 +36,39 @@ pub enum ContractError {
     #[error("Cooldown not reached: {cooldown_type}, remaining {remaining_time}s")]
     CooldownNotReached { cooldown_type: String, remaining_time: u64 },
 
+    #[error("Feature disabled: {feature}")]
+    FeatureDisabled { feature: String },
+
+    #[error("Amount limit exceeded: {limit_type} limit {limit_value}, actual {actual_value}")]
+    AmountLimitExceeded { limit_type: String, limit_value: Uint128, actual_value: Uint128 },
+
+    #[error("Invalid user status: {user} is {status}")]
+    InvalidUserStatus { user: String, status: String },
+
+    #[error("Invalid signature")]
+    InvalidSignature,
+
+    #[error("Voucher expired at {expires_at}")]
+    VoucherExpired { expires_at: u64 },
+
+    #[error("Voucher already used: {referrer} nonce {nonce}")]
+    VoucherAlreadyUsed { referrer: String, nonce: u64 },
+
+    #[error("Invalid referral code {code}: {reason}")]
+    InvalidReferralCode { code: String, reason: String },
+
+    #[error("Referral code already taken: {code}")]
+    ReferralCodeTaken { code: String },
+
+    #[error("Invalid merkle proof")]
+    InvalidMerkleProof,
+
+    #[error("Distribution {id} already claimed by {user}")]
+    DistributionAlreadyClaimed { id: u64, user: String },
+
+    #[error("Distribution {id} expired at {expires_at}")]
+    DistributionExpired { id: u64, expires_at: u64 },
+
     #[error("System error: {message}")]
     SystemError { message: String },
 }
@@ -0,0 +1,85 @@
+use cosmwasm_std::{Addr, Event, Uint128};
+use crate::state::{PointsReason, UserLevel};
+
+/// 事件类型前缀
+pub const EVENT_NAMESPACE: &str = "dd_registry";
+
+/// 事件属性结构版本，属性增删或含义变化时递增
+pub const EVENT_VERSION: &str = "1";
+
+/// 供索引器解析的事件，每个受影响的用户对应一个事件
+///
+/// 各事件的属性集合固定，可选值以空字符串表示。
+#[derive(Clone, Debug, PartialEq)]
+pub enum RegistryEvent {
+    UserRegistered {
+        user: Addr,
+        referrer: Option<Addr>,
+        registered_at: u64,
+    },
+    ReferralEstablished {
+        referrer: Addr,
+        referee: Addr,
+        timestamp: u64,
+    },
+    PointsChanged {
+        user: Addr,
+        reason: PointsReason,
+        old_points: Uint128,
+        new_points: Uint128,
+        event_id: Option<String>,
+    },
+    LevelChanged {
+        user: Addr,
+        old_level: UserLevel,
+        new_level: UserLevel,
+    },
+}
+
+impl RegistryEvent {
+    pub fn name(&self) -> &'static str {
+        match self {
+            RegistryEvent::UserRegistered { .. } => "user_registered",
+            RegistryEvent::ReferralEstablished { .. } => "referral_established",
+            RegistryEvent::PointsChanged { .. } => "points_changed",
+            RegistryEvent::LevelChanged { .. } => "level_changed",
+        }
+    }
+}
+
+impl From<RegistryEvent> for Event {
+    fn from(registry_event: RegistryEvent) -> Self {
+        let event = Event::new(format!("{}.{}", EVENT_NAMESPACE, registry_event.name()))
+            .add_attribute("version", EVENT_VERSION);
+        
+        match registry_event {
+            RegistryEvent::UserRegistered { user, referrer, registered_at } => event
+                .add_attribute("user", user)
+                .add_attribute("referrer", referrer.map(String::from).unwrap_or_default())
+                .add_attribute("registered_at", registered_at.to_string()),
+            RegistryEvent::ReferralEstablished { referrer, referee, timestamp } => event
+                .add_attribute("referrer", referrer)
+                .add_attribute("referee", referee)
+                .add_attribute("timestamp", timestamp.to_string()),
+            RegistryEvent::PointsChanged { user, reason, old_points, new_points, event_id } => {
+                let (direction, amount) = if new_points >= old_points {
+                    ("credit", new_points - old_points)
+                } else {
+                    ("debit", old_points - new_points)
+                };
+                event
+                    .add_attribute("user", user)
+                    .add_attribute("reason", format!("{:?}", reason))
+                    .add_attribute("direction", direction)
+                    .add_attribute("amount", amount.to_string())
+                    .add_attribute("old_points", old_points.to_string())
+                    .add_attribute("new_points", new_points.to_string())
+                    .add_attribute("event_id", event_id.unwrap_or_default())
+            }
+            RegistryEvent::LevelChanged { user, old_level, new_level } => event
+                .add_attribute("user", user)
+                .add_attribute("old_level", format!("{:?}", old_level))
+                .add_attribute("new_level", format!("{:?}", new_level)),
+        }
+    }
+}
@@ -1,85 +1,35 @@
-use cosmwasm_std::{Uint128, DepsMut, Env, MessageInfo, Response};
+use cosmwasm_std::{Binary, Decimal, Uint128, DepsMut, Env, MessageInfo, Response, Order};
 use crate::error::ContractError;
-use crate::msg::RewardAllocation;
-use crate::state::{CONFIG, POINTS_RULES, USER_MAP, UserInfo, UserLevel, ReferralStats, UserStatus, SystemConfig, PointsRules, PointsReason};
+use crate::msg::{ImportedUser, RewardAllocation};
+use crate::voucher::InvitationVoucher;
+use crate::state::{CONFIG, POINTS_RULES, UserStatus, ReferralRewardAction, SystemConfig, PointsRules, PointsReason, TransferConfig, HookEventType, BindReferrerConfig, DeactivationConfig, EarningCapsConfig, RateOverrides, TRANSFER_CONFIG, BIND_REFERRER_CONFIG, DEACTIVATION_CONFIG, EARNING_CAPS, USER_MAP};
 
 /// 执行用户注册
 pub fn execute_register(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: Env,
     info: MessageInfo,
     referrer: Option<String>,
+    referral_code: Option<String>,
 ) -> Result<Response, ContractError> {
-    let config = CONFIG.load(deps.storage)?;
-    
-    // 检查系统是否启用
-    if !config.enabled {
-        return Err(ContractError::SystemPaused);
-    }
-    
-    let user = info.sender;
-    
-    // 检查用户是否已注册
-    if USER_MAP.has(deps.storage, &user) {
-        return Err(ContractError::UserAlreadyRegistered {
-            user: user.to_string(),
-        });
-    }
-    
     // 处理推荐人
-    let referrer_addr = if let Some(ref ref_addr) = referrer {
-        let addr = deps.api.addr_validate(&ref_addr)?;
-        
-        // 检查推荐人是否存在
-        if !USER_MAP.has(deps.storage, &addr) {
-            return Err(ContractError::InvalidReferrer {
-                referrer: ref_addr.to_string(),
+    let referrer_addr = match (referrer, referral_code) {
+        (Some(_), Some(code)) => {
+            return Err(ContractError::InvalidParameter {
+                parameter: "referral_code".to_string(),
+                value: code,
             });
         }
-        
-        // 检查循环推荐
-        crate::referral::check_circular_referral(deps.as_ref(), &addr, &user)?;
-        
-        Some(addr)
-    } else {
-        None
+        (Some(ref_addr), None) => Some(deps.api.addr_validate(&ref_addr)?),
+        (None, Some(code)) => Some(crate::referral_code::consume_referral_code(deps.branch(), &env, &code)?),
+        (None, None) => None,
     };
     
-    // 创建用户信息
-    let user_info = UserInfo {
-        recommender: referrer_addr.clone(),
-        direct_referrals: Vec::new(),
-        reward_points: Uint128::zero(),
-        registered_at: env.block.time.seconds(),
-        last_active_at: env.block.time.seconds(),
-        user_level: UserLevel::Bronze,
-        referral_stats: ReferralStats {
-            total_referrals: 0,
-            active_referrals: 0,
-            monthly_referrals: 0,
-            last_referral_time: None,
-            success_rate: cosmwasm_std::Decimal::zero(),
-        },
-        points_history: Vec::new(),
-        status: UserStatus::Active,
-    };
-    
-    // 保存用户信息
-    USER_MAP.save(deps.storage, &user, &user_info)?;
-    
-    // 如果有推荐人，建立推荐关系
-    if let Some(ref_addr) = referrer_addr {
-        crate::referral::establish_referral_relation(deps, &env, &ref_addr, &user)?;
-    }
-    
-    Ok(Response::new()
-        .add_attribute("action", "user_registered")
-        .add_attribute("user", user.to_string())
-        .add_attribute("referrer", referrer.unwrap_or_default())
-        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+    crate::user::register_user(deps, &env, &info.sender, referrer_addr)
 }
 
 /// 执行积分分配
+#[allow(clippy::too_many_arguments)]
 pub fn execute_allocate_rewards(
     mut deps: DepsMut,
     env: Env,
@@ -100,6 +50,9 @@ pub fn execute_allocate_rewards(
         None
     };
     
+    // 只向正常状态的用户发放
+    crate::security::check_user_active(deps.as_ref(), &user_addr)?;
+    
     // 分配积分
     crate::points::allocate_points_to_user(
         &mut deps,
@@ -132,6 +85,7 @@ pub fn execute_batch_allocate_rewards(
         } else {
             None
         };
+        crate::security::check_user_active(deps.as_ref(), &user_addr)?;
         
         let allocation_response = crate::points::allocate_points_to_user(
             &mut deps,
@@ -143,7 +97,10 @@ pub fn execute_batch_allocate_rewards(
             reward.event_id,
         )?;
         
-        response = response.add_attributes(allocation_response.attributes);
+        // 逐项结果以事件区分，不再合并各项属性
+        response = response
+            .add_submessages(allocation_response.messages)
+            .add_events(allocation_response.events);
         processed_count += 1;
     }
     
@@ -164,6 +121,9 @@ pub fn execute_withdraw_points(
     // 检查系统是否暂停
     crate::security::check_system_paused(deps.as_ref())?;
     
+    // 冻结用户不可提取
+    crate::security::check_user_active(deps.as_ref(), &user)?;
+    
     // 提取积分
     crate::points::withdraw_points(deps, &env, &user, amount)
 }
@@ -178,6 +138,16 @@ pub fn execute_update_config(
     // 检查管理员权限
     crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
     
+    // 下级索引与团队数据按推荐层级上限维护，已有用户后不可再修改
+    let current = CONFIG.load(deps.storage)?;
+    let has_users = USER_MAP.keys(deps.storage, None, None, Order::Ascending).next().is_some();
+    if config.max_referral_depth != current.max_referral_depth && has_users {
+        return Err(ContractError::InvalidParameter {
+            parameter: "max_referral_depth".to_string(),
+            value: config.max_referral_depth.to_string(),
+        });
+    }
+    
     // 保存新配置
     CONFIG.save(deps.storage, &config)?;
     
@@ -214,12 +184,512 @@ pub fn execute_emergency_pause(
     // 检查管理员权限
     crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
     
-    let mut config = CONFIG.load(deps.storage)?;
-    config.emergency_paused = paused;
-    CONFIG.save(deps.storage, &config)?;
+    crate::security::set_emergency_pause(deps.storage, paused)?;
     
     Ok(Response::new()
         .add_attribute("action", "emergency_pause")
         .add_attribute("paused", paused.to_string())
         .add_attribute("timestamp", env.block.time.seconds().to_string()))
 }
+
+/// 执行积分转账
+pub fn execute_transfer_points(
+    mut deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    to: String,
+    amount: Uint128,
+    memo: Option<String>,
+) -> Result<Response, ContractError> {
+    // 检查系统是否暂停
+    crate::security::check_system_paused(deps.as_ref())?;
+    
+    let recipient = deps.api.addr_validate(&to)?;
+    
+    crate::transfer::transfer_points(&mut deps, &env, &info.sender, &recipient, amount, memo)
+}
+
+/// 执行更新转账配置
+pub fn execute_update_transfer_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    config: TransferConfig,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    crate::transfer::validate_transfer_config(deps.as_ref(), &config)?;
+    TRANSFER_CONFIG.save(deps.storage, &config)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "update_transfer_config")
+        .add_attribute("enabled", config.enabled.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行添加钩子
+pub fn execute_add_hook(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    addr: String,
+    events: Vec<HookEventType>,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    let hook_addr = deps.api.addr_validate(&addr)?;
+    crate::hooks::add_hook(deps, &hook_addr, events)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "add_hook")
+        .add_attribute("hook", hook_addr.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行移除钩子
+pub fn execute_remove_hook(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    addr: String,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    let hook_addr = deps.api.addr_validate(&addr)?;
+    crate::hooks::remove_hook(deps, &hook_addr)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "remove_hook")
+        .add_attribute("hook", hook_addr.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行登记凭证公钥
+pub fn execute_register_voucher_key(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    public_key: Binary,
+) -> Result<Response, ContractError> {
+    crate::voucher::register_voucher_key(deps, &info.sender, public_key)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "register_voucher_key")
+        .add_attribute("user", info.sender.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行凭证注册
+pub fn execute_register_with_voucher(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    voucher: InvitationVoucher,
+    signature: Binary,
+) -> Result<Response, ContractError> {
+    crate::voucher::register_with_voucher(deps, &env, &info.sender, voucher, signature)
+}
+
+/// 执行创建推荐码
+pub fn execute_create_referral_code(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    code: String,
+    max_uses: Option<u32>,
+    expires_at: Option<u64>,
+) -> Result<Response, ContractError> {
+    let referral_code = crate::referral_code::create_referral_code(
+        deps,
+        &env,
+        &info.sender,
+        &code,
+        max_uses,
+        expires_at,
+    )?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "create_referral_code")
+        .add_attribute("owner", info.sender.to_string())
+        .add_attribute("code", referral_code.code)
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 治理强制暂停
+pub fn sudo_force_pause(
+    deps: DepsMut,
+    env: Env,
+    paused: bool,
+) -> Result<Response, ContractError> {
+    crate::security::set_emergency_pause(deps.storage, paused)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "sudo_force_pause")
+        .add_attribute("paused", paused.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 治理更换管理员
+pub fn sudo_replace_admin(
+    deps: DepsMut,
+    env: Env,
+    admin: String,
+) -> Result<Response, ContractError> {
+    let admin = crate::security::validate_address(deps.as_ref(), &admin)?;
+    crate::security::set_admin(deps.storage, admin.clone())?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "sudo_replace_admin")
+        .add_attribute("admin", admin.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 治理冻结/解冻用户
+pub fn sudo_freeze_users(
+    deps: DepsMut,
+    env: Env,
+    users: Vec<String>,
+    frozen: bool,
+) -> Result<Response, ContractError> {
+    let (from, to) = if frozen {
+        (UserStatus::Active, UserStatus::Suspended)
+    } else {
+        (UserStatus::Suspended, UserStatus::Active)
+    };
+    
+    // 只在正常与冻结之间切换，封禁、已注销或已处于目标状态的用户跳过
+    let mut count = 0u32;
+    let mut skipped = 0u32;
+    for user in &users {
+        let user_addr = crate::security::validate_address(deps.as_ref(), user)?;
+        let user_info = crate::state::USER_MAP
+            .may_load(deps.storage, &user_addr)?
+            .ok_or_else(|| ContractError::UserNotRegistered { user: user_addr.to_string() })?;
+        if user_info.status != from {
+            skipped += 1;
+            continue;
+        }
+        crate::security::set_user_status(deps.storage, &user_addr, to.clone())?;
+        count += 1;
+    }
+    
+    Ok(Response::new()
+        .add_attribute("action", "sudo_freeze_users")
+        .add_attribute("frozen", frozen.to_string())
+        .add_attribute("count", count.to_string())
+        .add_attribute("skipped", skipped.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行批量导入用户
+pub fn execute_import_users(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    users: Vec<ImportedUser>,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    crate::user::import_users(deps, &env, users)
+}
+
+/// 执行更正推荐人
+pub fn execute_reassign_referrer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    user: String,
+    new_referrer: String,
+    reason: String,
+    reward_action: Option<ReferralRewardAction>,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    let user_addr = deps.api.addr_validate(&user)?;
+    let new_referrer_addr = deps.api.addr_validate(&new_referrer)?;
+    
+    let response = crate::referral::reassign_referrer(
+        deps,
+        &env,
+        &user_addr,
+        &new_referrer_addr,
+        reward_action.unwrap_or(ReferralRewardAction::Keep),
+    )?;
+    
+    Ok(response
+        .add_attribute("reason", reason)
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行补绑推荐人
+pub fn execute_bind_referrer(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    referrer: String,
+) -> Result<Response, ContractError> {
+    // 检查系统是否暂停
+    crate::security::check_system_paused(deps.as_ref())?;
+    
+    let referrer_addr = deps.api.addr_validate(&referrer)?;
+    crate::referral::bind_referrer(deps, &env, &info.sender, &referrer_addr)
+}
+
+/// 执行更新补绑推荐人配置
+pub fn execute_update_bind_referrer_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    config: BindReferrerConfig,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    BIND_REFERRER_CONFIG.save(deps.storage, &config)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "update_bind_referrer_config")
+        .add_attribute("window", config.window.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行注销自己的账户
+pub fn execute_deactivate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    // 检查系统是否暂停
+    crate::security::check_system_paused(deps.as_ref())?;
+    
+    // 冻结用户不可自行注销
+    crate::security::check_user_active(deps.as_ref(), &info.sender)?;
+    
+    crate::user::deactivate_user(deps, &env, &info.sender)
+}
+
+/// 执行注销指定用户（管理员）
+pub fn execute_admin_deactivate(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    user: String,
+    reason: String,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    let user_addr = deps.api.addr_validate(&user)?;
+    let response = crate::user::deactivate_user(deps, &env, &user_addr)?;
+    
+    Ok(response.add_attribute("reason", reason))
+}
+
+/// 执行更新注销配置
+pub fn execute_update_deactivation_config(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    config: DeactivationConfig,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    DEACTIVATION_CONFIG.save(deps.storage, &config)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "update_deactivation_config")
+        .add_attribute("downline_policy", format!("{:?}", config.downline_policy))
+        .add_attribute("points_settlement", format!("{:?}", config.points_settlement))
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行更新积分获得上限与预算
+pub fn execute_update_earning_caps(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    config: EarningCapsConfig,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    crate::caps::validate_earning_caps(&config)?;
+    EARNING_CAPS.save(deps.storage, &config)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "update_earning_caps")
+        .add_attribute("policy", format!("{:?}", config.policy))
+        .add_attribute("caps", config.caps.len().to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行创建限时活动
+#[allow(clippy::too_many_arguments)]
+pub fn execute_create_campaign(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+    start_time: u64,
+    end_time: u64,
+    budget: Option<Uint128>,
+    multiplier: Option<Decimal>,
+    rate_overrides: Option<RateOverrides>,
+    eligible_reasons: Vec<PointsReason>,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    let campaign = crate::campaign::create_campaign(
+        deps,
+        &env,
+        name,
+        start_time,
+        end_time,
+        budget,
+        multiplier,
+        rate_overrides,
+        eligible_reasons,
+    )?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "create_campaign")
+        .add_attribute("campaign_id", campaign.id.to_string())
+        .add_attribute("start_time", campaign.start_time.to_string())
+        .add_attribute("end_time", campaign.end_time.to_string())
+        .add_attribute("multiplier", campaign.multiplier.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行取消活动
+pub fn execute_cancel_campaign(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    let campaign = crate::campaign::cancel_campaign(deps, id)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "cancel_campaign")
+        .add_attribute("campaign_id", campaign.id.to_string())
+        .add_attribute("spent", campaign.spent.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行开始新赛季
+pub fn execute_start_season(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    name: String,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    let season = crate::season::start_season(deps, &env, name)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "start_season")
+        .add_attribute("season_id", season.id.to_string())
+        .add_attribute("name", season.name)
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行结束当前赛季
+pub fn execute_close_season(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    let season = crate::season::close_season(deps, &env)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "close_season")
+        .add_attribute("season_id", season.id.to_string())
+        .add_attribute("participants", season.participants.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行发布默克尔分发
+pub fn execute_create_distribution(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    merkle_root: Binary,
+    total: Uint128,
+    expires_at: u64,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    let distribution = crate::distribution::create_distribution(deps, &env, merkle_root, total, expires_at)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "create_distribution")
+        .add_attribute("distribution_id", distribution.id.to_string())
+        .add_attribute("merkle_root", distribution.merkle_root.to_base64())
+        .add_attribute("total", distribution.total.to_string())
+        .add_attribute("expires_at", distribution.expires_at.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行领取分发积分
+pub fn execute_claim_distribution(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    amount: Uint128,
+    proof: Vec<Binary>,
+) -> Result<Response, ContractError> {
+    // 检查系统是否暂停
+    crate::security::check_system_paused(deps.as_ref())?;
+    
+    let claim_response = crate::distribution::claim_distribution(deps, &env, &info.sender, id, amount, proof)?;
+    
+    Ok(Response::new()
+        .add_submessages(claim_response.messages)
+        .add_events(claim_response.events)
+        .add_attribute("action", "claim_distribution")
+        .add_attribute("distribution_id", id.to_string())
+        .add_attribute("user", info.sender.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 执行回收过期分发
+pub fn execute_reclaim_distribution(
+    deps: DepsMut,
+    env: Env,
+    info: MessageInfo,
+    id: u64,
+    recipient: Option<String>,
+) -> Result<Response, ContractError> {
+    // 检查管理员权限
+    crate::security::check_admin_permission(deps.as_ref(), &info.sender)?;
+    
+    let recipient = recipient.map(|recipient| deps.api.addr_validate(&recipient)).transpose()?;
+    let (unclaimed, reclaim_response) = crate::distribution::reclaim_distribution(deps, &env, id, recipient.clone())?;
+    
+    Ok(Response::new()
+        .add_submessages(reclaim_response.messages)
+        .add_events(reclaim_response.events)
+        .add_attribute("action", "reclaim_distribution")
+        .add_attribute("distribution_id", id.to_string())
+        .add_attribute("unclaimed", unclaimed.to_string())
+        .add_attribute("recipient", recipient.map(|recipient| recipient.to_string()).unwrap_or_default())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
@@ -0,0 +1,123 @@
+use cosmwasm_std::{Addr, CosmosMsg, DepsMut, Env, Order, Reply, Response, Storage, SubMsg, SubMsgResult};
+use crate::error::ContractError;
+use crate::state::{Compensation, PendingCall, NEXT_CALL_ID, PENDING_CALLS};
+use crate::security::set_reentrancy_lock;
+
+/// 包装外部调用：登记待回调记录并持有重入锁，直到所有 reply 返回
+///
+/// 未提供补偿时仅在成功时回调，失败将回滚整个交易；
+/// 提供补偿时无论成败都会回调，失败时退还扣除的积分。
+/// 钩子通知使用 `guard_hook_msg`。
+pub fn guard_sub_msg(
+    storage: &mut dyn Storage,
+    msg: CosmosMsg,
+    compensation: Option<Compensation>,
+) -> Result<SubMsg, ContractError> {
+    let reply_always = compensation.is_some();
+    register_call(storage, msg, PendingCall { compensation, hook: None }, reply_always)
+}
+
+/// 包装钩子通知：无论成败都会回调，失败时记录到该钩子名下而不回滚交易，
+/// 避免单个订阅合约出错阻塞注册与积分变动
+pub fn guard_hook_msg(
+    storage: &mut dyn Storage,
+    msg: CosmosMsg,
+    hook: &Addr,
+) -> Result<SubMsg, ContractError> {
+    register_call(storage, msg, PendingCall { compensation: None, hook: Some(hook.clone()) }, true)
+}
+
+fn register_call(
+    storage: &mut dyn Storage,
+    msg: CosmosMsg,
+    call: PendingCall,
+    reply_always: bool,
+) -> Result<SubMsg, ContractError> {
+    let id = NEXT_CALL_ID.may_load(storage)?.unwrap_or_default();
+    NEXT_CALL_ID.save(storage, &(id + 1))?;
+    
+    let sub_msg = if reply_always {
+        SubMsg::reply_always(msg, id)
+    } else {
+        SubMsg::reply_on_success(msg, id)
+    };
+    
+    PENDING_CALLS.save(storage, id, &call)?;
+    set_reentrancy_lock(storage, true)?;
+    
+    Ok(sub_msg)
+}
+
+/// 处理受保护调用的回调
+pub fn handle_reply(
+    mut deps: DepsMut,
+    env: Env,
+    msg: Reply,
+) -> Result<Response, ContractError> {
+    let call = PENDING_CALLS.may_load(deps.storage, msg.id)?.ok_or_else(|| {
+        ContractError::InvalidParameter {
+            parameter: "reply_id".to_string(),
+            value: msg.id.to_string(),
+        }
+    })?;
+    PENDING_CALLS.remove(deps.storage, msg.id);
+    
+    let mut response = Response::new()
+        .add_attribute("action", "guarded_call_reply")
+        .add_attribute("call_id", msg.id.to_string());
+    
+    match (msg.result, call.compensation, call.hook) {
+        (SubMsgResult::Ok(_), _, _) => {
+            response = response.add_attribute("result", "success");
+        }
+        (SubMsgResult::Err(err), Some(compensation), _) => {
+            // 外部调用失败，退还积分
+            let compensation_response = crate::points::credit_points_exact(
+                &mut deps,
+                &env,
+                &compensation.user,
+                compensation.points,
+                compensation.reason,
+                None,
+                Some(format!("compensation:{}", msg.id)),
+            )?;
+            response = response
+                .add_submessages(compensation_response.messages)
+                .add_events(compensation_response.events)
+                .add_attribute("result", "compensated")
+                .add_attribute("user", compensation.user.to_string())
+                .add_attribute("points", compensation.points.to_string())
+                .add_attribute("error", err);
+        }
+        (SubMsgResult::Err(err), None, Some(hook)) => {
+            // 钩子失败只记录，订阅合约的状态变更已随子消息回滚
+            crate::hooks::record_hook_failure(deps.storage, &env, &hook, &err)?;
+            response = response
+                .add_attribute("result", "hook_failed")
+                .add_attribute("hook", hook.to_string())
+                .add_attribute("error", err);
+        }
+        (SubMsgResult::Err(err), None, None) => {
+            return Err(ContractError::SystemError { message: err });
+        }
+    }
+    
+    // 所有外部调用完成后释放重入锁
+    release_lock_if_idle(deps.storage)?;
+    
+    Ok(response)
+}
+
+/// 没有待回调记录时释放重入锁
+fn release_lock_if_idle(storage: &mut dyn Storage) -> Result<(), ContractError> {
+    let idle = PENDING_CALLS
+        .keys(storage, None, None, Order::Ascending)
+        .next()
+        .is_none();
+    
+    if idle {
+        set_reentrancy_lock(storage, false)?;
+    }
+    
+    Ok(())
+}
@@ -0,0 +1,222 @@
+use cosmwasm_std::{Addr, Deps, Order, StdError, StdResult, Storage};
+use cw_storage_plus::Bound;
+use crate::msg::PointsHistoryFilter;
+use crate::state::{
+    PointsReason, PointsRecord, UserInfo, HISTORY_COUNTS, HISTORY_INDEX, NEXT_RECORD_SEQ, POINTS_RECORDS, USER_MAP,
+};
+
+/// 带时间范围计数时最多遍历的索引条数
+pub const MAX_HISTORY_COUNT_SCAN: usize = 1000;
+
+/// 索引键的排序部分：(时间戳, 顺序号)
+type TimeKey = (u64, u64);
+
+fn reason_key(reason: &PointsReason) -> String {
+    format!("{:?}", reason)
+}
+
+/// 事件、关联用户、原因组合成的筛选键，未设置的条件记为 "-"，其余带长度前缀避免歧义
+fn filter_key(event_id: Option<&str>, related_user: Option<&Addr>, reason: Option<&str>) -> String {
+    [event_id, related_user.map(Addr::as_str), reason]
+        .iter()
+        .map(|part| part.map_or("-".to_string(), |part| format!("{}:{}", part.len(), part)))
+        .collect::<Vec<_>>()
+        .join("/")
+}
+
+/// 一条流水可被命中的全部筛选键：已设置的每个字段都可作为条件或留空
+fn filter_keys(record: &PointsRecord) -> Vec<String> {
+    let reason = reason_key(&record.reason);
+    let mut keys = Vec::new();
+    for event_id in [None].into_iter().chain(record.event_id.as_deref().map(Some)) {
+        for related_user in [None].into_iter().chain(record.related_user.as_ref().map(Some)) {
+            for reason in [None, Some(reason.as_str())] {
+                keys.push(filter_key(event_id, related_user, reason));
+            }
+        }
+    }
+    keys
+}
+
+fn index_record(storage: &mut dyn Storage, user: &Addr, seq: u64, record: &PointsRecord) -> StdResult<()> {
+    let key = (record.timestamp, seq);
+    POINTS_RECORDS.save(storage, (user, seq), record)?;
+    for filter in filter_keys(record) {
+        HISTORY_INDEX.save(storage, (user, &filter, key), &true)?;
+        HISTORY_COUNTS.update(storage, (user, &filter), |count| -> StdResult<u32> {
+            Ok(count.unwrap_or_default() + 1)
+        })?;
+    }
+    Ok(())
+}
+
+/// 将旧版本保存在 UserInfo 中的流水迁入索引并清空列表
+///
+/// 旧版本没有流水索引，按列表位置编号，与迁移前按列表分页返回的游标一致。
+fn migrate_legacy(storage: &mut dyn Storage, user: &Addr, legacy: &mut Vec<PointsRecord>) -> StdResult<()> {
+    if legacy.is_empty() {
+        return Ok(());
+    }
+    
+    for (seq, record) in legacy.iter().enumerate() {
+        index_record(storage, user, seq as u64, record)?;
+    }
+    NEXT_RECORD_SEQ.save(storage, user, &(legacy.len() as u64))?;
+    legacy.clear();
+    
+    Ok(())
+}
+
+/// 将所有用户旧版本保存的流水迁入索引，返回迁移的用户数
+pub fn migrate_legacy_histories(storage: &mut dyn Storage) -> StdResult<u32> {
+    let users: Vec<(Addr, UserInfo)> = USER_MAP
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| item.as_ref().map_or(true, |(_, info)| !info.points_history.is_empty()))
+        .collect::<StdResult<_>>()?;
+    
+    for (user, mut info) in users.iter().cloned() {
+        migrate_legacy(storage, &user, &mut info.points_history)?;
+        USER_MAP.save(storage, &user, &info)?;
+    }
+    
+    Ok(users.len() as u32)
+}
+
+/// 写入一条积分流水并更新各索引，返回该用户下的顺序号
+///
+/// `legacy` 为用户信息中旧版本保存的流水，首次写入时迁入索引。
+pub fn record_history(
+    storage: &mut dyn Storage,
+    user: &Addr,
+    legacy: &mut Vec<PointsRecord>,
+    record: &PointsRecord,
+) -> StdResult<u64> {
+    migrate_legacy(storage, user, legacy)?;
+    
+    let seq = NEXT_RECORD_SEQ.may_load(storage, user)?.unwrap_or_default();
+    NEXT_RECORD_SEQ.save(storage, user, &(seq + 1))?;
+    index_record(storage, user, seq, record)?;
+    
+    Ok(seq)
+}
+
+/// 游标须落在查询的时间范围内
+fn check_cursor(cursor: Option<TimeKey>, (low, high): (TimeKey, TimeKey)) -> StdResult<()> {
+    match cursor {
+        Some(cursor) if cursor < low || cursor > high => Err(StdError::generic_err("cursor out of range")),
+        _ => Ok(()),
+    }
+}
+
+/// 按条件分页读取积分流水
+///
+/// 事件、关联用户、原因的组合直接对应一个索引前缀，每页只读取返回的记录；
+/// 时间范围与游标作为索引边界。返回记录、符合条件的流水数与下一页游标。
+/// 尚未迁入索引的用户直接在旧列表上筛选。
+pub fn points_history(
+    deps: Deps,
+    user: &Addr,
+    filter: &PointsHistoryFilter,
+    related_user: Option<&Addr>,
+    order: Order,
+    cursor: Option<u64>,
+    limit: usize,
+) -> StdResult<(Vec<PointsRecord>, u32, Option<u64>)> {
+    let low: TimeKey = (filter.from_time.unwrap_or(0), 0);
+    let high: TimeKey = (filter.to_time.unwrap_or(u64::MAX), u64::MAX);
+    
+    let legacy = USER_MAP.load(deps.storage, user)?.points_history;
+    if !legacy.is_empty() {
+        let matches = |record: &PointsRecord| {
+            filter.event_id.as_ref().is_none_or(|event_id| record.event_id.as_ref() == Some(event_id))
+                && related_user.is_none_or(|related_user| record.related_user.as_ref() == Some(related_user))
+                && filter.reason.as_ref().is_none_or(|reason| record.reason == *reason)
+        };
+        return legacy_history(&legacy, matches, (low, high), order, cursor, limit);
+    }
+    
+    let reason = filter.reason.as_ref().map(reason_key);
+    let key = filter_key(filter.event_id.as_deref(), related_user, reason.as_deref());
+    let index = HISTORY_INDEX.prefix((user, key.as_str()));
+    
+    // 不限时间时直接取计数，否则在索引上计数
+    let total = if filter.from_time.is_none() && filter.to_time.is_none() {
+        HISTORY_COUNTS.may_load(deps.storage, (user, &key))?.unwrap_or_default()
+    } else {
+        let count = index
+            .keys_raw(deps.storage, Some(Bound::inclusive(low)), Some(Bound::inclusive(high)), Order::Ascending)
+            .take(MAX_HISTORY_COUNT_SCAN + 1)
+            .count();
+        if count > MAX_HISTORY_COUNT_SCAN {
+            return Err(StdError::generic_err("time range too wide, narrow from_time/to_time"));
+        }
+        count as u32
+    };
+    
+    let cursor = cursor
+        .map(|seq| -> StdResult<TimeKey> { Ok((POINTS_RECORDS.load(deps.storage, (user, seq))?.timestamp, seq)) })
+        .transpose()?;
+    check_cursor(cursor, (low, high))?;
+    let (min, max) = match (order, cursor) {
+        (Order::Ascending, Some(cursor)) => (Bound::exclusive(cursor), Bound::inclusive(high)),
+        (Order::Descending, Some(cursor)) => (Bound::inclusive(low), Bound::exclusive(cursor)),
+        _ => (Bound::inclusive(low), Bound::inclusive(high)),
+    };
+    
+    let mut records = Vec::new();
+    let mut last_seq = None;
+    for key in index.keys(deps.storage, Some(min), Some(max), order).take(limit) {
+        let (_, seq) = key?;
+        records.push(POINTS_RECORDS.load(deps.storage, (user, seq))?);
+        last_seq = Some(seq);
+    }
+    
+    let next_cursor = if records.len() >= limit { last_seq } else { None };
+    Ok((records, total, next_cursor))
+}
+
+/// 在旧版本保存的流水列表上分页，列表位置即顺序号
+fn legacy_history(
+    legacy: &[PointsRecord],
+    matches: impl Fn(&PointsRecord) -> bool,
+    (low, high): (TimeKey, TimeKey),
+    order: Order,
+    cursor: Option<u64>,
+    limit: usize,
+) -> StdResult<(Vec<PointsRecord>, u32, Option<u64>)> {
+    let cursor = cursor
+        .map(|seq| {
+            legacy
+                .get(seq as usize)
+                .map(|record| (record.timestamp, seq))
+                .ok_or_else(|| StdError::not_found("cursor"))
+        })
+        .transpose()?;
+    check_cursor(cursor, (low, high))?;
+    
+    let mut keyed: Vec<(TimeKey, &PointsRecord)> = legacy
+        .iter()
+        .enumerate()
+        .map(|(seq, record)| ((record.timestamp, seq as u64), record))
+        .filter(|(key, record)| low <= *key && *key <= high && matches(record))
+        .collect();
+    keyed.sort_by_key(|(key, _)| *key);
+    if let Order::Descending = order {
+        keyed.reverse();
+    }
+    
+    let total = keyed.len() as u32;
+    let page: Vec<(TimeKey, &PointsRecord)> = keyed
+        .into_iter()
+        .filter(|(key, _)| match (order, cursor) {
+            (Order::Ascending, Some(cursor)) => *key > cursor,
+            (Order::Descending, Some(cursor)) => *key < cursor,
+            _ => true,
+        })
+        .take(limit)
+        .collect();
+    
+    let next_cursor = if page.len() >= limit { page.last().map(|(key, _)| key.1) } else { None };
+    let records = page.into_iter().map(|(_, record)| record.clone()).collect();
+    Ok((records, total, next_cursor))
+}
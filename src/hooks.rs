@@ -0,0 +1,155 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_json_binary, Addr, CosmosMsg, Deps, DepsMut, Env, Order, StdResult, Storage, SubMsg, Uint128, WasmMsg};
+use crate::error::ContractError;
+use crate::state::{HookEventType, HookFailure, PointsReason, HOOKS, HOOK_FAILURES};
+use crate::guard::guard_hook_msg;
+use crate::msg::HookInfo;
+
+/// 钩子数量上限
+pub const MAX_HOOKS: usize = 10;
+
+/// 发送给订阅合约的钩子消息
+#[cw_serde]
+pub enum RegistryHookMsg {
+    UserRegistered {
+        user: String,
+        referrer: Option<String>,
+        registered_at: u64,
+    },
+    ReferralEstablished {
+        referrer: String,
+        referee: String,
+        timestamp: u64,
+    },
+    PointsChanged {
+        user: String,
+        old_points: Uint128,
+        new_points: Uint128,
+        reason: PointsReason,
+    },
+}
+
+/// 订阅合约需实现的执行消息
+#[cw_serde]
+pub enum RegistryHookExecuteMsg {
+    RegistryHook(RegistryHookMsg),
+}
+
+impl RegistryHookMsg {
+    pub fn event_type(&self) -> HookEventType {
+        match self {
+            RegistryHookMsg::UserRegistered { .. } => HookEventType::UserRegistered,
+            RegistryHookMsg::ReferralEstablished { .. } => HookEventType::ReferralEstablished,
+            RegistryHookMsg::PointsChanged { .. } => HookEventType::PointsChanged,
+        }
+    }
+    
+    /// 包装为发往订阅合约的消息
+    pub fn into_cosmos_msg(&self, contract_addr: &Addr) -> StdResult<CosmosMsg> {
+        let msg = WasmMsg::Execute {
+            contract_addr: contract_addr.to_string(),
+            msg: to_json_binary(&RegistryHookExecuteMsg::RegistryHook(self.clone()))?,
+            funds: vec![],
+        };
+        Ok(msg.into())
+    }
+}
+
+/// 为订阅了该事件的所有钩子生成受保护的消息，单个钩子失败不影响本次操作
+pub fn prepare_hooks(
+    storage: &mut dyn Storage,
+    msg: &RegistryHookMsg,
+) -> Result<Vec<SubMsg>, ContractError> {
+    let event_type = msg.event_type();
+    
+    let subscribers = HOOKS
+        .range(storage, None, None, Order::Ascending)
+        .filter(|item| match item {
+            Ok((_, events)) => events.contains(&event_type),
+            Err(_) => true,
+        })
+        .map(|item| item.map(|(addr, _)| addr))
+        .collect::<StdResult<Vec<_>>>()?;
+    
+    subscribers
+        .iter()
+        .map(|addr| guard_hook_msg(storage, msg.into_cosmos_msg(addr)?, addr))
+        .collect()
+}
+
+/// 添加钩子
+pub fn add_hook(
+    deps: DepsMut,
+    addr: &Addr,
+    events: Vec<HookEventType>,
+) -> Result<(), ContractError> {
+    if events.is_empty() {
+        return Err(ContractError::InvalidParameter {
+            parameter: "events".to_string(),
+            value: "empty".to_string(),
+        });
+    }
+    
+    // 已存在的钩子视为更新订阅事件，不计入数量
+    if !HOOKS.has(deps.storage, addr) {
+        let count = HOOKS.keys(deps.storage, None, None, Order::Ascending).count();
+        if count >= MAX_HOOKS {
+            return Err(ContractError::LimitExceeded {
+                limit_type: "hooks".to_string(),
+                limit_value: MAX_HOOKS as u32,
+                actual_value: (count + 1) as u32,
+            });
+        }
+    }
+    
+    let mut unique_events = Vec::new();
+    for event in events {
+        if !unique_events.contains(&event) {
+            unique_events.push(event);
+        }
+    }
+    HOOKS.save(deps.storage, addr, &unique_events)?;
+    
+    Ok(())
+}
+
+/// 移除钩子
+pub fn remove_hook(deps: DepsMut, addr: &Addr) -> Result<(), ContractError> {
+    if !HOOKS.has(deps.storage, addr) {
+        return Err(ContractError::InvalidParameter {
+            parameter: "hook".to_string(),
+            value: addr.to_string(),
+        });
+    }
+    
+    HOOKS.remove(deps.storage, addr);
+    HOOK_FAILURES.remove(deps.storage, addr);
+    Ok(())
+}
+
+/// 记录钩子调用失败
+pub fn record_hook_failure(
+    storage: &mut dyn Storage,
+    env: &Env,
+    addr: &Addr,
+    error: &str,
+) -> StdResult<()> {
+    let count = HOOK_FAILURES.may_load(storage, addr)?.map_or(0, |failure| failure.count);
+    HOOK_FAILURES.save(storage, addr, &HookFailure {
+        count: count + 1,
+        last_error: error.to_string(),
+        last_failed_at: env.block.time.seconds(),
+    })
+}
+
+/// 列出所有钩子及其失败记录
+pub fn list_hooks(deps: Deps) -> StdResult<Vec<HookInfo>> {
+    HOOKS
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| {
+            let (addr, events) = item?;
+            let failure = HOOK_FAILURES.may_load(deps.storage, &addr)?;
+            Ok(HookInfo { addr, events, failure })
+        })
+        .collect()
+}
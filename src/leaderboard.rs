@@ -0,0 +1,286 @@
+use cosmwasm_std::{Addr, Deps, Order, StdError, StdResult, Storage, Uint128};
+use cw_storage_plus::{Bound, Map, PrefixBound};
+use crate::msg::{LeaderboardCursor, LeaderboardFilter};
+use crate::state::{
+    RankingGroup, RankingMember, UserInfo, UserLevel, UserStatus, GROUP_RANKING, POINTS_RANKING, RANKING_GROUPS,
+    REFERRAL_RANKING, USER_MAP,
+};
+
+/// 排行榜种类
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Ranking {
+    /// 按积分排名
+    Points,
+    /// 按推荐人数排名
+    Referrals,
+}
+
+impl Ranking {
+    fn members<'a>(self) -> Map<(u128, &'a Addr), RankingMember> {
+        match self {
+            Ranking::Points => POINTS_RANKING,
+            Ranking::Referrals => REFERRAL_RANKING,
+        }
+    }
+    
+    fn name(self) -> &'static str {
+        match self {
+            Ranking::Points => "points",
+            Ranking::Referrals => "referrals",
+        }
+    }
+    
+    /// 用户在该排行榜上的分值
+    pub fn score(self, user_info: &UserInfo) -> u128 {
+        match self {
+            Ranking::Points => user_info.reward_points.u128(),
+            Ranking::Referrals => user_info.referral_stats.total_referrals as u128,
+        }
+    }
+}
+
+/// 排行榜条目
+#[derive(Clone, Debug, PartialEq)]
+pub struct RankedEntry {
+    pub user: Addr,
+    pub score: u128,
+    pub level: UserLevel,
+    pub rank: u32,
+}
+
+pub fn ranking_member(user_info: &UserInfo) -> RankingMember {
+    RankingMember {
+        level: user_info.user_level.clone(),
+        status: user_info.status.clone(),
+    }
+}
+
+fn matches_filter(filter: &LeaderboardFilter, member: &RankingMember) -> bool {
+    filter.level.as_ref().is_none_or(|level| *level == member.level)
+        && filter.status.as_ref().is_none_or(|status| *status == member.status)
+        && !(filter.exclude_banned && member.status == UserStatus::Banned)
+}
+
+/// 分组在 RANKING_GROUPS 中的键
+fn group_key(member: &RankingMember) -> String {
+    format!("{:?}:{:?}", member.level, member.status)
+}
+
+/// 分组成员索引的前缀：排行榜名与分组键
+fn group_index(ranking: Ranking, member: &RankingMember) -> String {
+    format!("{}:{}", ranking.name(), group_key(member))
+}
+
+/// 调整分组人数并同步分组成员索引
+fn adjust_group(
+    storage: &mut dyn Storage,
+    ranking: Ranking,
+    user: &Addr,
+    score: u128,
+    member: &RankingMember,
+    add: bool,
+) -> StdResult<()> {
+    let key = group_key(member);
+    let mut group = RANKING_GROUPS
+        .may_load(storage, (ranking.name(), &key))?
+        .unwrap_or_else(|| RankingGroup {
+            member: member.clone(),
+            count: 0,
+        });
+    group.count = if add { group.count + 1 } else { group.count.saturating_sub(1) };
+    
+    if group.count == 0 {
+        RANKING_GROUPS.remove(storage, (ranking.name(), &key));
+    } else {
+        RANKING_GROUPS.save(storage, (ranking.name(), &key), &group)?;
+    }
+    
+    let index = group_index(ranking, member);
+    if add {
+        GROUP_RANKING.save(storage, (&index, score, user), &true)?;
+    } else {
+        GROUP_RANKING.remove(storage, (&index, score, user));
+    }
+    Ok(())
+}
+
+/// 更新用户在排行榜上的分值，分值为 0 时移出排行榜
+pub fn update_ranking(
+    storage: &mut dyn Storage,
+    ranking: Ranking,
+    user: &Addr,
+    old_score: u128,
+    new_score: u128,
+    member: &RankingMember,
+) -> StdResult<()> {
+    let members = ranking.members();
+    
+    if let Some(old) = members.may_load(storage, (old_score, user))? {
+        members.remove(storage, (old_score, user));
+        adjust_group(storage, ranking, user, old_score, &old, false)?;
+    }
+    
+    if new_score > 0 {
+        if let Some(existing) = members.may_load(storage, (new_score, user))? {
+            adjust_group(storage, ranking, user, new_score, &existing, false)?;
+        }
+        members.save(storage, (new_score, user), member)?;
+        adjust_group(storage, ranking, user, new_score, member, true)?;
+    }
+    
+    Ok(())
+}
+
+/// 按用户数据重建排行榜、分组人数与分组成员索引，用于迁移
+pub fn rebuild_rankings(storage: &mut dyn Storage) -> StdResult<()> {
+    POINTS_RANKING.clear(storage);
+    REFERRAL_RANKING.clear(storage);
+    RANKING_GROUPS.clear(storage);
+    GROUP_RANKING.clear(storage);
+    
+    let users: Vec<(Addr, UserInfo)> = USER_MAP
+        .range(storage, None, None, Order::Ascending)
+        .collect::<StdResult<_>>()?;
+    for (user, user_info) in users {
+        let member = ranking_member(&user_info);
+        for ranking in [Ranking::Points, Ranking::Referrals] {
+            update_ranking(storage, ranking, &user, 0, ranking.score(&user_info), &member)?;
+        }
+    }
+    Ok(())
+}
+
+/// 等级或状态变化后同步排行榜上的筛选属性
+pub fn sync_member(storage: &mut dyn Storage, user: &Addr, user_info: &UserInfo) -> StdResult<()> {
+    let member = ranking_member(user_info);
+    for ranking in [Ranking::Points, Ranking::Referrals] {
+        let score = ranking.score(user_info);
+        if ranking.members().has(storage, (score, user)) {
+            update_ranking(storage, ranking, user, score, score, &member)?;
+        }
+    }
+    Ok(())
+}
+
+/// 符合筛选条件的上榜人数
+pub fn ranking_total(deps: Deps, ranking: Ranking, filter: &LeaderboardFilter) -> StdResult<u32> {
+    RANKING_GROUPS
+        .prefix(ranking.name())
+        .range(deps.storage, None, None, Order::Ascending)
+        .try_fold(0, |total, item| {
+            let (_, group) = item?;
+            Ok(if matches_filter(filter, &group.member) { total + group.count } else { total })
+        })
+}
+
+/// 按分值降序分页读取排行榜，排名为同分并列的绝对名次
+///
+/// 只遍历符合筛选条件的分组索引并按分值归并，每页读取的条目数与页大小相当。
+/// 游标只携带上一页最后一名的用户与分值，须与存储中的条目一致，
+/// 其名次由排在它之前的人数重新计算。
+pub fn ranking_page(
+    deps: Deps,
+    ranking: Ranking,
+    filter: &LeaderboardFilter,
+    start_after: Option<&LeaderboardCursor>,
+    limit: usize,
+) -> StdResult<(Vec<RankedEntry>, Option<LeaderboardCursor>)> {
+    let groups: Vec<RankingMember> = RANKING_GROUPS
+        .prefix(ranking.name())
+        .range(deps.storage, None, None, Order::Ascending)
+        .filter_map(|item| match item {
+            Ok((_, group)) if !matches_filter(filter, &group.member) => None,
+            item => Some(item.map(|(_, group)| group.member)),
+        })
+        .collect::<StdResult<_>>()?;
+    
+    let cursor = match start_after {
+        Some(cursor) => {
+            let user = deps.api.addr_validate(&cursor.user)?;
+            let score = cursor.score.u128();
+            let valid = ranking
+                .members()
+                .may_load(deps.storage, (score, &user))?
+                .is_some_and(|member| matches_filter(filter, &member));
+            if !valid {
+                return Err(StdError::not_found("cursor"));
+            }
+            Some((score, user))
+        }
+        None => None,
+    };
+    
+    // 游标之前的人数及其中同分的人数
+    let (mut position, mut last) = match &cursor {
+        Some((score, user)) => {
+            let (mut ahead, mut tied) = (0u32, 0u32);
+            for member in &groups {
+                let index = group_index(ranking, member);
+                ahead += GROUP_RANKING
+                    .sub_prefix(&index)
+                    .keys_raw(deps.storage, Some(Bound::exclusive((*score, user))), None, Order::Ascending)
+                    .count() as u32;
+                tied += GROUP_RANKING
+                    .prefix((&index, *score))
+                    .keys_raw(deps.storage, Some(Bound::exclusive(user)), None, Order::Ascending)
+                    .count() as u32;
+            }
+            (ahead + 1, Some((*score, ahead - tied + 1)))
+        }
+        None => (0, None),
+    };
+    
+    let mut heads = Vec::new();
+    for member in groups {
+        let max = cursor.as_ref().map(|(score, user)| Bound::exclusive((*score, user)));
+        let mut keys = GROUP_RANKING
+            .sub_prefix(&group_index(ranking, &member))
+            .keys(deps.storage, None, max, Order::Descending);
+        let head = keys.next().transpose()?;
+        heads.push((member, head, keys));
+    }
+    
+    let mut entries = Vec::new();
+    while entries.len() < limit {
+        // 取各分组当前头部中分值最高者
+        let Some(index) = (0..heads.len()).filter(|i| heads[*i].1.is_some()).max_by_key(|i| heads[*i].1.clone()) else {
+            break;
+        };
+        let (member, head, keys) = &mut heads[index];
+        let next = keys.next().transpose()?;
+        let Some((score, user)) = std::mem::replace(head, next) else {
+            break;
+        };
+        
+        position += 1;
+        let rank = match last {
+            Some((last_score, last_rank)) if last_score == score => last_rank,
+            _ => position,
+        };
+        last = Some((score, rank));
+        
+        entries.push(RankedEntry {
+            user,
+            score,
+            level: member.level.clone(),
+            rank,
+        });
+    }
+    
+    let next_cursor = match entries.last() {
+        Some(entry) if entries.len() >= limit => Some(LeaderboardCursor {
+            user: entry.user.to_string(),
+            score: Uint128::from(entry.score),
+        }),
+        _ => None,
+    };
+    Ok((entries, next_cursor))
+}
+
+/// 用户的积分名次：分值更高的上榜人数加一
+pub fn points_rank(deps: Deps, points: u128) -> StdResult<u32> {
+    let higher = POINTS_RANKING
+        .prefix_range(deps.storage, Some(PrefixBound::exclusive(points)), None, Order::Ascending)
+        .count();
+    Ok(higher as u32 + 1)
+}
@@ -8,9 +8,26 @@ pub mod user;
 pub mod referral;
 pub mod points;
 pub mod security;
+pub mod transfer;
+pub mod hooks;
+pub mod guard;
+pub mod voucher;
+pub mod referral_code;
+pub mod team;
+pub mod simulation;
+pub mod caps;
+pub mod campaign;
+pub mod season;
+pub mod distribution;
+pub mod leaderboard;
+pub mod stats;
+pub mod supply;
+pub mod history;
+pub mod events;
+pub mod migration;
 
 pub use crate::error::ContractError;
-pub use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg};
+pub use crate::msg::{ExecuteMsg, InstantiateMsg, QueryMsg, SudoMsg};
 pub use crate::state::{UserInfo, UserLevel, SystemConfig, PointsRules};
 
 // 版本信息
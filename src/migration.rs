@@ -0,0 +1,24 @@
+use cosmwasm_std::{DepsMut, Env, Response};
+use crate::error::ContractError;
+
+/// 按现有用户数据重建派生状态，可重复执行
+pub fn migrate(deps: DepsMut, env: Env) -> Result<Response, ContractError> {
+    // 余额检查点与积分总量
+    let total_points = crate::points::seed_points_snapshots(deps.storage, &env)?;
+    
+    // 用户总数、推荐关系数及等级、状态分布
+    let stats = crate::stats::rebuild_stats(deps.storage)?;
+    
+    // 积分与推荐人数排行榜及其分组索引
+    crate::leaderboard::rebuild_rankings(deps.storage)?;
+    
+    // 旧版本保存在用户信息中的积分流水
+    let history_users = crate::history::migrate_legacy_histories(deps.storage)?;
+    
+    Ok(Response::new()
+        .add_attribute("action", "migrate")
+        .add_attribute("total_points", total_points.to_string())
+        .add_attribute("total_users", stats.total_users.to_string())
+        .add_attribute("history_users", history_users.to_string())
+        .add_attribute("contract_version", crate::CONTRACT_VERSION))
+}
@@ -1,6 +1,12 @@
 use cosmwasm_schema::{cw_serde, QueryResponses};
-use cosmwasm_std::{Addr, Uint128};
-use crate::state::{UserInfo, UserLevel, SystemConfig, PointsRules, PointsReason};
+use cosmwasm_std::{Addr, Binary, Decimal, Uint128};
+use crate::voucher::InvitationVoucher;
+use crate::state::{
+    UserInfo, UserLevel, SystemConfig, PointsRules, PointsReason, TransferConfig,
+    DailyTransferUsage, HookEventType, HookFailure, UserStatus, ReferralCode, ReferralRewardAction,
+    BindReferrerConfig, DeactivationConfig, EarningCapsConfig, CapPolicy,
+    Campaign, RateOverrides, Season, Distribution, ReasonAmount,
+};
 
 #[cw_serde]
 pub struct InstantiateMsg {
@@ -11,9 +17,10 @@ pub struct InstantiateMsg {
 
 #[cw_serde]
 pub enum ExecuteMsg {
-    /// 用户注册
+    /// 用户注册（referrer 与 referral_code 二选一）
     Register {
         referrer: Option<String>,
+        referral_code: Option<String>,
     },
     
     /// 分配积分
@@ -49,6 +56,153 @@ pub enum ExecuteMsg {
     EmergencyPause {
         paused: bool,
     },
+    
+    /// 用户间转账积分
+    TransferPoints {
+        to: String,
+        amount: Uint128,
+        memo: Option<String>,
+    },
+    
+    /// 更新转账配置
+    UpdateTransferConfig {
+        config: TransferConfig,
+    },
+    
+    /// 添加钩子（重复添加时更新订阅事件）
+    AddHook {
+        addr: String,
+        events: Vec<HookEventType>,
+    },
+    
+    /// 移除钩子
+    RemoveHook {
+        addr: String,
+    },
+    
+    /// 登记邀请凭证签名公钥（ed25519）
+    RegisterVoucherKey {
+        public_key: Binary,
+    },
+    
+    /// 使用推荐人签发的邀请凭证注册
+    RegisterWithVoucher {
+        voucher: InvitationVoucher,
+        signature: Binary,
+    },
+    
+    /// 创建推荐码
+    CreateReferralCode {
+        code: String,
+        max_uses: Option<u32>,
+        expires_at: Option<u64>,
+    },
+    
+    /// 批量导入已有用户及推荐关系（管理员）
+    ImportUsers {
+        users: Vec<ImportedUser>,
+    },
+    
+    /// 更正用户推荐人（管理员）
+    ReassignReferrer {
+        user: String,
+        new_referrer: String,
+        reason: String,
+        reward_action: Option<ReferralRewardAction>,
+    },
+    
+    /// 为未绑定推荐人的用户补绑推荐人
+    BindReferrer {
+        referrer: String,
+    },
+    
+    /// 更新补绑推荐人配置
+    UpdateBindReferrerConfig {
+        config: BindReferrerConfig,
+    },
+    
+    /// 注销自己的账户
+    Deactivate {},
+    
+    /// 注销指定用户（管理员）
+    AdminDeactivate {
+        user: String,
+        reason: String,
+    },
+    
+    /// 更新注销配置
+    UpdateDeactivationConfig {
+        config: DeactivationConfig,
+    },
+    
+    /// 更新积分获得上限与预算
+    UpdateEarningCaps {
+        config: EarningCapsConfig,
+    },
+    
+    /// 创建限时活动
+    CreateCampaign {
+        name: String,
+        start_time: u64,
+        end_time: u64,
+        budget: Option<Uint128>,
+        multiplier: Option<Decimal>,
+        rate_overrides: Option<RateOverrides>,
+        eligible_reasons: Vec<PointsReason>,
+    },
+    
+    /// 取消活动
+    CancelCampaign { id: u64 },
+    
+    /// 开始新赛季
+    StartSeason { name: String },
+    
+    /// 结束当前赛季并归档排名
+    CloseSeason {},
+    
+    /// 发布默克尔分发
+    CreateDistribution {
+        merkle_root: Binary,
+        total: Uint128,
+        expires_at: u64,
+    },
+    
+    /// 领取分发积分
+    ClaimDistribution {
+        id: u64,
+        amount: Uint128,
+        proof: Vec<Binary>,
+    },
+    
+    /// 过期后回收未领取的积分
+    ReclaimDistribution {
+        id: u64,
+        recipient: Option<String>,
+    },
+}
+
+/// 迁移时按现有用户数据重建派生状态
+#[cw_serde]
+pub struct MigrateMsg {}
+
+/// 链上治理通过 sudo 调用的紧急控制消息
+#[cw_serde]
+pub enum SudoMsg {
+    /// 强制暂停/恢复
+    ForcePause {
+        paused: bool,
+    },
+    
+    /// 更换管理员
+    ReplaceAdmin {
+        admin: String,
+    },
+    
+    /// 冻结/解冻指定用户，仅在正常与冻结之间切换，其他状态的用户跳过
+    FreezeUsers {
+        users: Vec<String>,
+        frozen: bool,
+    },
 }
 
 #[cw_serde]
@@ -81,19 +235,31 @@ pub enum QueryMsg {
     #[returns(UserPointsResponse)]
     GetUserPoints { user: String },
     
-    /// 查询积分排行榜
+    /// 查询积分排行榜，start_after 为上一页返回的 next_cursor
     #[returns(LeaderboardResponse)]
     GetPointsLeaderboard {
         limit: Option<u32>,
-        start_after: Option<String>,
+        start_after: Option<LeaderboardCursor>,
+        filter: Option<LeaderboardFilter>,
+    },
+    
+    /// 查询推荐人数排行榜，start_after 为上一页返回的 next_cursor
+    #[returns(ReferralLeaderboardResponse)]
+    GetReferralLeaderboard {
+        limit: Option<u32>,
+        start_after: Option<LeaderboardCursor>,
+        filter: Option<LeaderboardFilter>,
     },
     
-    /// 查询用户积分历史
+    /// 查询用户积分历史，start_after 为时间戳（不含），cursor 为上一页返回的顺序号
     #[returns(PointsHistoryResponse)]
     GetPointsHistory {
         user: String,
         limit: Option<u32>,
         start_after: Option<u64>,
+        filter: Option<PointsHistoryFilter>,
+        order: Option<SortOrder>,
+        cursor: Option<u64>,
     },
     
     /// 查询系统配置
@@ -114,6 +280,134 @@ pub enum QueryMsg {
         referrer: String,
         referee: String,
     },
+    
+    /// 查询转账配置
+    #[returns(TransferConfigResponse)]
+    GetTransferConfig {},
+    
+    /// 查询用户当日转账用量
+    #[returns(TransferUsageResponse)]
+    GetTransferUsage { user: String },
+    
+    /// 查询钩子列表
+    #[returns(HooksResponse)]
+    ListHooks {},
+    
+    /// 批量查询用户摘要（未注册用户返回 registered: false）
+    #[returns(UsersSummaryResponse)]
+    GetUsersSummary { users: Vec<String> },
+    
+    /// 查询推荐人凭证公钥
+    #[returns(VoucherKeyResponse)]
+    GetVoucherKey { user: String },
+    
+    /// 查询凭证是否已使用
+    #[returns(VoucherStatusResponse)]
+    GetVoucherStatus { referrer: String, nonce: u64 },
+    
+    /// 解析推荐码
+    #[returns(ReferralCodeResponse)]
+    ResolveReferralCode { code: String },
+    
+    /// 查询用户创建的推荐码
+    #[returns(ReferralCodesResponse)]
+    ListMyCodes {
+        user: String,
+        start_after: Option<String>,
+        limit: Option<u32>,
+    },
+    
+    /// 查询补绑推荐人配置
+    #[returns(BindReferrerConfigResponse)]
+    GetBindReferrerConfig {},
+    
+    /// 查询注销配置
+    #[returns(DeactivationConfigResponse)]
+    GetDeactivationConfig {},
+    
+    /// 分页查询下级团队（按层级、同层按地址排序），start_after 为上一页返回的 next_cursor
+    #[returns(DownlineResponse)]
+    GetDownline {
+        user: String,
+        max_depth: Option<u32>,
+        start_after: Option<DownlineCursor>,
+        limit: Option<u32>,
+    },
+    
+    /// 查询团队各层级人数
+    #[returns(TeamSummaryResponse)]
+    GetTeamSummary { user: String },
+    
+    /// 模拟注册，返回预计结果而不写入存储
+    #[returns(SimulateRegisterResponse)]
+    SimulateRegister {
+        user: String,
+        referrer: Option<String>,
+    },
+    
+    /// 模拟多层级奖励分配，返回各上级明细（已应用获得上限与预算）
+    #[returns(SimulateAllocationResponse)]
+    SimulateAllocation {
+        user: String,
+        base_points: Uint128,
+        reason: PointsReason,
+    },
+    
+    /// 查询积分上限与预算余量（指定用户时包含其个人额度）
+    #[returns(BudgetStatusResponse)]
+    GetBudgetStatus { user: Option<String> },
+    
+    /// 查询活动
+    #[returns(CampaignResponse)]
+    GetCampaign { id: u64 },
+    
+    /// 分页列出活动
+    #[returns(CampaignsResponse)]
+    ListCampaigns {
+        start_after: Option<u64>,
+        limit: Option<u32>,
+    },
+    
+    /// 查询赛季，未指定时返回当前赛季
+    #[returns(SeasonResponse)]
+    GetSeason { season_id: Option<u64> },
+    
+    /// 查询赛季排行榜（已结束赛季返回归档排名）
+    #[returns(SeasonLeaderboardResponse)]
+    GetSeasonLeaderboard {
+        season_id: u64,
+        limit: Option<u32>,
+    },
+    
+    /// 查询用户赛季积分
+    #[returns(UserSeasonPointsResponse)]
+    GetUserSeasonPoints {
+        season_id: u64,
+        user: String,
+    },
+    
+    /// 查询用户在指定区块高度（该区块执行前）的积分
+    #[returns(UserPointsAtResponse)]
+    GetUserPointsAt {
+        user: String,
+        height: u64,
+    },
+    
+    /// 查询指定区块高度（该区块执行前）的积分总量
+    #[returns(TotalPointsAtResponse)]
+    GetTotalPointsAt { height: u64 },
+    
+    /// 查询分发
+    #[returns(DistributionResponse)]
+    GetDistribution { id: u64 },
+    
+    /// 查询用户的分发领取记录
+    #[returns(DistributionClaimResponse)]
+    GetDistributionClaim { id: u64, user: String },
+    
+    /// 查询积分供应及来源
+    #[returns(SupplyResponse)]
+    GetSupply {},
 }
 
 // 响应结构体定义
@@ -153,10 +447,31 @@ pub struct UserPointsResponse {
     pub rank: Option<u32>,
 }
 
+/// 排行榜筛选条件
+#[cw_serde]
+#[derive(Default)]
+pub struct LeaderboardFilter {
+    pub level: Option<UserLevel>,
+    pub status: Option<UserStatus>,
+    /// 排除被封禁的用户
+    #[serde(default)]
+    pub exclude_banned: bool,
+}
+
+/// 排行榜翻页游标，记录上一页最后一名的用户与分值，名次由合约重新计算
+#[cw_serde]
+pub struct LeaderboardCursor {
+    pub user: String,
+    pub score: Uint128,
+}
+
 #[cw_serde]
 pub struct LeaderboardResponse {
     pub entries: Vec<LeaderboardEntry>,
+    /// 符合筛选条件的上榜总人数
     pub total: u32,
+    /// 最后一页为 None
+    pub next_cursor: Option<LeaderboardCursor>,
 }
 
 #[cw_serde]
@@ -167,10 +482,50 @@ pub struct LeaderboardEntry {
     pub rank: u32,
 }
 
+#[cw_serde]
+pub struct ReferralLeaderboardResponse {
+    pub entries: Vec<ReferralLeaderboardEntry>,
+    /// 符合筛选条件的上榜总人数
+    pub total: u32,
+    /// 最后一页为 None
+    pub next_cursor: Option<LeaderboardCursor>,
+}
+
+#[cw_serde]
+pub struct ReferralLeaderboardEntry {
+    pub user: Addr,
+    pub referrals: u32,
+    pub level: UserLevel,
+    pub rank: u32,
+}
+
+/// 积分历史筛选条件
+#[cw_serde]
+#[derive(Default)]
+pub struct PointsHistoryFilter {
+    pub reason: Option<PointsReason>,
+    pub related_user: Option<String>,
+    pub event_id: Option<String>,
+    /// 时间范围（含两端）
+    pub from_time: Option<u64>,
+    pub to_time: Option<u64>,
+}
+
+#[cw_serde]
+#[derive(Default)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
 #[cw_serde]
 pub struct PointsHistoryResponse {
     pub records: Vec<crate::state::PointsRecord>,
+    /// 符合筛选条件的流水数
     pub total: u32,
+    /// 下一页游标，没有更多记录时为 None
+    pub next_cursor: Option<u64>,
 }
 
 #[cw_serde]
@@ -187,6 +542,9 @@ pub struct PointsRulesResponse {
 pub struct LevelStatsResponse {
     pub stats: std::collections::HashMap<UserLevel, u32>,
     pub total_users: u32,
+    pub status_stats: std::collections::HashMap<UserStatus, u32>,
+    /// 当前有效的推荐关系数
+    pub total_referrals: u32,
 }
 
 #[cw_serde]
@@ -203,4 +561,270 @@ pub struct RewardAllocation {
     pub reason: PointsReason,
     pub related_user: Option<String>,
     pub event_id: Option<String>,
-}
\ No newline at end of file
+}
+
+#[cw_serde]
+pub struct TransferConfigResponse {
+    pub config: TransferConfig,
+}
+
+#[cw_serde]
+pub struct TransferUsageResponse {
+    pub usage: DailyTransferUsage,
+}
+
+#[cw_serde]
+pub struct HooksResponse {
+    pub hooks: Vec<HookInfo>,
+}
+
+#[cw_serde]
+pub struct HookInfo {
+    pub addr: Addr,
+    pub events: Vec<HookEventType>,
+    /// 最近的调用失败，钩子失败不会回滚触发它的操作
+    pub failure: Option<HookFailure>,
+}
+
+#[cw_serde]
+pub struct UsersSummaryResponse {
+    pub users: Vec<UserSummary>,
+}
+
+#[cw_serde]
+pub struct UserSummary {
+    pub user: Addr,
+    pub registered: bool,
+    pub level: Option<UserLevel>,
+    pub points: Uint128,
+    pub referrer: Option<Addr>,
+    pub status: Option<UserStatus>,
+}
+
+#[cw_serde]
+pub struct VoucherKeyResponse {
+    pub public_key: Option<Binary>,
+}
+
+#[cw_serde]
+pub struct VoucherStatusResponse {
+    pub used: bool,
+}
+
+#[cw_serde]
+pub struct ReferralCodeResponse {
+    pub code: Option<ReferralCode>,
+    pub usable: bool,
+}
+
+#[cw_serde]
+pub struct ReferralCodesResponse {
+    pub codes: Vec<ReferralCode>,
+}
+
+#[cw_serde]
+pub struct ImportedUser {
+    pub address: String,
+    pub referrer: Option<String>,
+    pub registered_at: u64,
+    pub points: Uint128,
+    /// 作为等级下限保留，之后按推荐人数重算的等级不会低于该值
+    pub level: UserLevel,
+}
+
+#[cw_serde]
+pub struct BindReferrerConfigResponse {
+    pub config: BindReferrerConfig,
+}
+
+#[cw_serde]
+pub struct DeactivationConfigResponse {
+    pub config: DeactivationConfig,
+}
+
+#[cw_serde]
+pub struct DownlineMember {
+    pub user: Addr,
+    pub depth: u32,
+    pub level: UserLevel,
+    pub points: Uint128,
+}
+
+/// 下级分页游标
+#[cw_serde]
+pub struct DownlineCursor {
+    pub depth: u32,
+    pub user: String,
+}
+
+#[cw_serde]
+pub struct DownlineResponse {
+    pub members: Vec<DownlineMember>,
+    /// 最后一页为 None
+    pub next_cursor: Option<DownlineCursor>,
+}
+
+#[cw_serde]
+pub struct TeamSummaryResponse {
+    pub user: Addr,
+    /// 第 i 项为第 i+1 层的人数
+    pub depth_counts: Vec<u32>,
+    pub total_members: u32,
+    pub active_members: u32,
+    pub downline_points: Uint128,
+}
+
+#[cw_serde]
+pub struct ReferralLimitStatus {
+    /// 推荐冷却剩余秒数，0 表示已就绪
+    pub cooldown_remaining: u64,
+    pub daily_referrals: u32,
+    pub max_daily_referrals: u32,
+}
+
+#[cw_serde]
+pub struct AllocationShare {
+    pub user: Addr,
+    pub depth: u32,
+    pub rate: Decimal,
+    pub multiplier: Decimal,
+    pub points: Uint128,
+    pub campaign_id: Option<u64>,
+}
+
+#[cw_serde]
+pub struct SimulateAllocationResponse {
+    pub user: Addr,
+    pub base_points: Uint128,
+    pub reason: PointsReason,
+    pub shares: Vec<AllocationShare>,
+    pub total_points: Uint128,
+}
+
+#[cw_serde]
+pub struct SimulateRegisterResponse {
+    pub success: bool,
+    pub error: Option<String>,
+    pub chain_depth: u32,
+    pub referral_limits: Option<ReferralLimitStatus>,
+    /// 注册过程中各上级实际入账的积分，仅列出有入账的上级
+    pub upline_payouts: Vec<UplinePayout>,
+}
+
+#[cw_serde]
+pub struct UplinePayout {
+    pub user: Addr,
+    pub depth: u32,
+    pub points: Uint128,
+}
+
+#[cw_serde]
+pub struct BudgetHeadroom {
+    pub limit: Uint128,
+    pub used: Uint128,
+    pub remaining: Uint128,
+}
+
+#[cw_serde]
+pub struct ReasonBudgetStatus {
+    pub reason: PointsReason,
+    pub budget: Option<BudgetHeadroom>,
+    pub user_daily: Option<BudgetHeadroom>,
+    /// used 为自配置上限起的累计获得量，不含配置前获得的积分
+    pub user_lifetime: Option<BudgetHeadroom>,
+}
+
+#[cw_serde]
+pub struct BudgetStatusResponse {
+    pub policy: CapPolicy,
+    pub period: u64,
+    pub period_index: u64,
+    pub global: Option<BudgetHeadroom>,
+    pub reasons: Vec<ReasonBudgetStatus>,
+}
+
+#[cw_serde]
+pub struct CampaignResponse {
+    pub campaign: Campaign,
+    /// 当前是否在生效
+    pub running: bool,
+}
+
+#[cw_serde]
+pub struct CampaignsResponse {
+    pub campaigns: Vec<Campaign>,
+}
+
+#[cw_serde]
+pub struct SeasonResponse {
+    pub season: Option<Season>,
+}
+
+#[cw_serde]
+pub struct SeasonRankEntry {
+    pub rank: u32,
+    pub user: Addr,
+    pub points: Uint128,
+}
+
+#[cw_serde]
+pub struct SeasonLeaderboardResponse {
+    pub season: Season,
+    pub entries: Vec<SeasonRankEntry>,
+    /// 赛季已结束，排名为归档结果
+    pub finalized: bool,
+}
+
+#[cw_serde]
+pub struct UserSeasonPointsResponse {
+    pub season_id: u64,
+    pub user: Addr,
+    pub points: Uint128,
+}
+
+#[cw_serde]
+pub struct UserPointsAtResponse {
+    pub user: Addr,
+    pub height: u64,
+    pub points: Uint128,
+}
+
+#[cw_serde]
+pub struct TotalPointsAtResponse {
+    pub height: u64,
+    pub total_points: Uint128,
+}
+
+#[cw_serde]
+pub struct DistributionResponse {
+    pub distribution: Distribution,
+    pub expired: bool,
+}
+
+#[cw_serde]
+pub struct DistributionClaimResponse {
+    pub id: u64,
+    pub user: Addr,
+    /// 未领取时为 None
+    pub claimed: Option<Uint128>,
+}
+
+#[cw_serde]
+pub struct SupplyResponse {
+    /// 当前所有用户余额之和
+    pub total_supply: Uint128,
+    pub issued: Uint128,
+    pub withdrawn: Uint128,
+    pub deducted: Uint128,
+    /// 用户间转账转出总额，不计入发放与扣除
+    pub transferred: Uint128,
+    /// 转账中未到账的手续费（未配置金库时销毁）
+    pub burned: Uint128,
+    /// 衰减与过期移除的积分，尚无对应机制时为 0
+    pub decayed: Uint128,
+    pub expired: Uint128,
+    pub issued_by_reason: Vec<ReasonAmount>,
+    pub deducted_by_reason: Vec<ReasonAmount>,
+    /// 发放减去提取、扣除、衰减、过期与销毁等于余额总和
+    pub balanced: bool,
+}
@@ -1,9 +1,14 @@
-use cosmwasm_std::{Addr, Uint128, Deps, DepsMut, Env, Response, Decimal, Order};
-use crate::state::{USER_MAP, POINTS_RULES, POINTS_LEADERBOARD, PointsRecord, PointsReason};
+use cosmwasm_std::{Addr, Uint128, Deps, DepsMut, Env, Order, Response, Decimal, StdError, StdResult};
+use crate::state::{USER_MAP, POINTS_RULES, POINTS_SNAPSHOTS, TOTAL_POINTS, Campaign, PointsRecord, PointsReason};
 use crate::error::ContractError;
 use crate::user::{get_user_info, update_user_level};
+use crate::hooks::{prepare_hooks, RegistryHookMsg};
+use crate::events::RegistryEvent;
+use crate::msg::{AllocationShare, LeaderboardCursor, LeaderboardFilter};
+use crate::leaderboard::Ranking;
+use crate::supply::SupplyFlow;
 
-/// 分配积分给用户
+/// 分配积分给用户，进行中的活动会自动生效
 pub fn allocate_points_to_user(
     deps: &mut DepsMut,
     env: &Env,
@@ -12,104 +17,301 @@ pub fn allocate_points_to_user(
     reason: PointsReason,
     related_user: Option<Addr>,
     event_id: Option<String>,
+) -> Result<Response, ContractError> {
+    // 剩余预算不足以覆盖本次发放时不应用活动
+    let campaign = crate::campaign::find_campaign(deps.storage, env, &reason)?
+        .map(|campaign| {
+            let boosted = crate::campaign::apply_multiplier(points, campaign.multiplier);
+            (campaign, boosted)
+        })
+        .filter(|(campaign, boosted)| {
+            crate::campaign::remaining_budget(campaign).is_none_or(|remaining| remaining >= *boosted)
+        });
+    
+    match campaign {
+        Some((campaign, boosted)) => {
+            credit_points(deps, env, user, boosted, reason, related_user, event_id, Some(campaign.id), true)
+        }
+        None => credit_points(deps, env, user, points, reason, related_user, event_id, None, true),
+    }
+}
+
+/// 按原额记入积分，不应用活动与获得上限，也不计入上限用量
+///
+/// 用于退款补偿、数据导入、推荐奖励转移与分发领取等内部记入，
+/// 这些积分并非新的获得，截断或加成都会导致积分丢失或超发。
+pub fn credit_points_exact(
+    deps: &mut DepsMut,
+    env: &Env,
+    user: &Addr,
+    points: Uint128,
+    reason: PointsReason,
+    related_user: Option<Addr>,
+    event_id: Option<String>,
+) -> Result<Response, ContractError> {
+    credit_points(deps, env, user, points, reason, related_user, event_id, None, false)
+}
+
+/// 记入积分，活动效果已由调用方计算
+#[allow(clippy::too_many_arguments)]
+fn credit_points(
+    deps: &mut DepsMut,
+    env: &Env,
+    user: &Addr,
+    points: Uint128,
+    reason: PointsReason,
+    related_user: Option<Addr>,
+    event_id: Option<String>,
+    campaign_id: Option<u64>,
+    apply_caps: bool,
 ) -> Result<Response, ContractError> {
     let mut user_info = get_user_info(deps.as_ref(), user)?;
+    let old_points = user_info.reward_points;
+    
+    // 应用获得上限与预算
+    let requested_points = points;
+    let points = if apply_caps {
+        crate::caps::apply_earning_caps(deps.storage, env, user, points, &reason)?
+    } else {
+        points
+    };
+    if points.is_zero() && !requested_points.is_zero() {
+        return Ok(Response::new()
+            .add_attribute("action", "points_capped")
+            .add_attribute("user", user.to_string())
+            .add_attribute("requested_points", requested_points.to_string())
+            .add_attribute("reason", format!("{:?}", reason)));
+    }
+    
+    // 计入活动预算
+    if let Some(campaign_id) = campaign_id {
+        crate::campaign::charge_campaign(deps.storage, env, campaign_id, points)?;
+    }
     
     // 添加积分
     user_info.reward_points += points;
+    if reason == PointsReason::ReferralReward {
+        if let Some(ref referee) = related_user {
+            crate::referral::record_referral_reward(deps.storage, user, referee, points)?;
+        }
+    }
     
     // 记录积分历史
-    user_info.points_history.push(PointsRecord {
+    let record = PointsRecord {
         points_change: points,
         reason: reason.clone(),
         timestamp: env.block.time.seconds(),
         related_user,
-        event_id,
-    });
+        event_id: event_id.clone(),
+        campaign_id,
+    };
+    crate::history::record_history(deps.storage, user, &mut user_info.points_history, &record)?;
     
     // 保存用户信息
     USER_MAP.save(deps.storage, user, &user_info)?;
+    checkpoint_points(deps.storage, env, user, old_points, user_info.reward_points)?;
+    crate::supply::record_supply(deps.storage, SupplyFlow::credit(&reason), &reason, points)?;
     
     // 更新用户等级
-    let _level_response = update_user_level(deps, user, user_info.referral_stats.total_referrals)?;
+    let level_response = update_user_level(deps, user, user_info.referral_stats.total_referrals)?;
     
     // 更新排行榜
-    update_leaderboard(deps.storage, user, user_info.reward_points)?;
+    update_leaderboard(deps.storage, user, old_points)?;
     
-    Ok(Response::new()
+    // 累计上级团队积分
+    crate::team::record_team_points(deps.storage, user, points, &reason)?;
+    
+    // 计入当前赛季
+    crate::season::record_season_points(deps.storage, user, points, &reason, true)?;
+    
+    // 通知订阅合约
+    let hook_msgs = prepare_hooks(deps.storage, &RegistryHookMsg::PointsChanged {
+        user: user.to_string(),
+        old_points,
+        new_points: user_info.reward_points,
+        reason: reason.clone(),
+    })?;
+    
+    let points_event = RegistryEvent::PointsChanged {
+        user: user.clone(),
+        reason: reason.clone(),
+        old_points,
+        new_points: user_info.reward_points,
+        event_id,
+    };
+    
+    let mut response = Response::new()
+        .add_submessages(hook_msgs)
+        .add_event(points_event)
+        .add_events(level_response.events)
         .add_attribute("action", "points_allocated")
         .add_attribute("user", user.to_string())
         .add_attribute("points", points.to_string())
         .add_attribute("reason", format!("{:?}", reason))
-        .add_attribute("total_points", user_info.reward_points.to_string()))
+        .add_attribute("total_points", user_info.reward_points.to_string());
+    if points < requested_points {
+        response = response.add_attribute("requested_points", requested_points.to_string());
+    }
+    if let Some(campaign_id) = campaign_id {
+        response = response.add_attribute("campaign_id", campaign_id.to_string());
+    }
+    
+    Ok(response)
 }
 
-/// 分配多层级推荐奖励
-pub fn allocate_multi_level_rewards(
+/// 扣除用户积分
+pub fn deduct_points_from_user(
     deps: &mut DepsMut,
     env: &Env,
-    referee: &Addr,
-    base_points: Uint128,
+    user: &Addr,
+    points: Uint128,
     reason: PointsReason,
+    related_user: Option<Addr>,
     event_id: Option<String>,
 ) -> Result<Response, ContractError> {
+    let mut user_info = get_user_info(deps.as_ref(), user)?;
+    let old_points = user_info.reward_points;
+    
+    // 检查积分余额
+    if user_info.reward_points < points {
+        return Err(ContractError::InsufficientPoints {
+            required: points,
+            available: user_info.reward_points,
+        });
+    }
+    
+    // 扣除积分
+    user_info.reward_points -= points;
+    
+    // 记录积分历史
+    let record = PointsRecord {
+        points_change: points,
+        reason: reason.clone(),
+        timestamp: env.block.time.seconds(),
+        related_user,
+        event_id: event_id.clone(),
+        campaign_id: None,
+    };
+    crate::history::record_history(deps.storage, user, &mut user_info.points_history, &record)?;
+    
+    // 保存用户信息
+    USER_MAP.save(deps.storage, user, &user_info)?;
+    checkpoint_points(deps.storage, env, user, old_points, user_info.reward_points)?;
+    crate::supply::record_supply(deps.storage, SupplyFlow::debit(&reason), &reason, points)?;
+    
+    // 更新排行榜
+    update_leaderboard(deps.storage, user, old_points)?;
+    
+    // 处罚扣减当前赛季积分
+    crate::season::record_season_points(deps.storage, user, points, &reason, false)?;
+    
+    // 通知订阅合约
+    let hook_msgs = prepare_hooks(deps.storage, &RegistryHookMsg::PointsChanged {
+        user: user.to_string(),
+        old_points,
+        new_points: user_info.reward_points,
+        reason: reason.clone(),
+    })?;
+    
+    let points_event = RegistryEvent::PointsChanged {
+        user: user.clone(),
+        reason: reason.clone(),
+        old_points,
+        new_points: user_info.reward_points,
+        event_id,
+    };
+    
+    Ok(Response::new()
+        .add_submessages(hook_msgs)
+        .add_event(points_event)
+        .add_attribute("action", "points_deducted")
+        .add_attribute("user", user.to_string())
+        .add_attribute("points", points.to_string())
+        .add_attribute("reason", format!("{:?}", reason))
+        .add_attribute("total_points", user_info.reward_points.to_string()))
+}
+
+/// 计算多层级推荐奖励，按层级返回各上级应得积分
+///
+/// 实际发放与模拟查询共用此计算，保证两者一致。
+pub fn calculate_multi_level_rewards(
+    deps: Deps,
+    env: &Env,
+    referee: &Addr,
+    base_points: Uint128,
+    reason: &PointsReason,
+) -> Result<Vec<AllocationShare>, ContractError> {
+    // 活动剩余预算不足以覆盖本次全部奖励时按常规规则计算
+    if let Some(campaign) = crate::campaign::find_campaign(deps.storage, env, reason)? {
+        let shares = compute_shares(deps, referee, base_points, Some(&campaign))?;
+        let total: Uint128 = shares.iter().map(|share| share.points).sum();
+        if crate::campaign::remaining_budget(&campaign).is_none_or(|remaining| remaining >= total) {
+            return Ok(shares);
+        }
+    }
+    
+    compute_shares(deps, referee, base_points, None)
+}
+
+fn compute_shares(
+    deps: Deps,
+    referee: &Addr,
+    base_points: Uint128,
+    campaign: Option<&Campaign>,
+) -> Result<Vec<AllocationShare>, ContractError> {
     let config = crate::state::CONFIG.load(deps.storage)?;
     let rules = POINTS_RULES.load(deps.storage)?;
     
-    let mut response = Response::new();
+    let mut shares = Vec::new();
     let mut current_user = referee.clone();
     let mut level = 1;
     
     // 向上追溯推荐链
     while level <= config.max_referral_depth {
         if let Ok(Some(referrer)) = crate::state::REFERRAL_CHAIN.may_load(deps.storage, &current_user) {
-            // 计算当前层级的奖励比例
+            let user_info = get_user_info(deps, &referrer)?;
+            
+            // 计算当前层级的奖励比例，已注销节点不再获得奖励，但仍占用层级
+            let overrides = campaign.and_then(|campaign| campaign.rate_overrides.as_ref());
             let rate = match level {
-                1 => rules.direct_referral_rate,
-                2 => rules.level_2_rate,
-                3 => rules.level_3_rate,
+                _ if user_info.status == crate::state::UserStatus::Closed => Decimal::zero(),
+                1 => overrides.and_then(|o| o.direct_referral_rate).unwrap_or(rules.direct_referral_rate),
+                2 => overrides.and_then(|o| o.level_2_rate).unwrap_or(rules.level_2_rate),
+                3 => overrides.and_then(|o| o.level_3_rate).unwrap_or(rules.level_3_rate),
                 _ => Decimal::zero(),
             };
             
-            if !rate.is_zero() {
-                // 计算奖励积分
-                let reward_points = base_points.multiply_ratio(
-                    rate.atomics(),
-                    Uint128::from(10_u128.pow(rate.decimal_places()))
-                );
-                
-                // 应用等级倍数
-                let user_info = get_user_info(deps.as_ref(), &referrer)?;
-                let default_multiplier = Decimal::one();
-                let level_multiplier = rules.level_multipliers
-                    .get(&user_info.user_level)
-                    .unwrap_or(&default_multiplier);
-                
-                let final_points = reward_points.multiply_ratio(
-                    level_multiplier.atomics(),
-                    Uint128::from(10_u128.pow(level_multiplier.decimal_places()))
-                );
-                
-                // 分配积分
-                let allocation_response = allocate_points_to_user(
-                    deps,
-                    env,
-                    &referrer,
-                    final_points,
-                    reason.clone(),
-                    Some(referee.clone()),
-                    event_id.clone(),
-                )?;
-                
-                response = response.add_attributes(allocation_response.attributes);
-                
-                // 添加事件属性
-                response = response
-                    .add_attribute("level", level.to_string())
-                    .add_attribute("referrer", referrer.to_string())
-                    .add_attribute("points", final_points.to_string());
+            // 计算奖励积分
+            let reward_points = base_points.multiply_ratio(
+                rate.atomics(),
+                Uint128::from(10_u128.pow(rate.decimal_places()))
+            );
+            
+            // 应用等级倍数
+            let level_multiplier = rules.level_multipliers
+                .get(&user_info.user_level)
+                .cloned()
+                .unwrap_or_else(Decimal::one);
+            
+            let mut final_points = reward_points.multiply_ratio(
+                level_multiplier.atomics(),
+                Uint128::from(10_u128.pow(level_multiplier.decimal_places()))
+            );
+            
+            // 应用活动倍数
+            if let Some(campaign) = campaign {
+                final_points = crate::campaign::apply_multiplier(final_points, campaign.multiplier);
             }
             
+            shares.push(AllocationShare {
+                user: referrer.clone(),
+                depth: level,
+                rate,
+                multiplier: level_multiplier,
+                points: final_points,
+                campaign_id: campaign.map(|campaign| campaign.id),
+            });
+            
             current_user = referrer;
             level += 1;
         } else {
@@ -117,19 +319,104 @@ pub fn allocate_multi_level_rewards(
         }
     }
     
+    Ok(shares)
+}
+
+/// 分配多层级推荐奖励
+pub fn allocate_multi_level_rewards(
+    deps: &mut DepsMut,
+    env: &Env,
+    referee: &Addr,
+    base_points: Uint128,
+    reason: PointsReason,
+    event_id: Option<String>,
+) -> Result<Response, ContractError> {
+    let shares = calculate_multi_level_rewards(deps.as_ref(), env, referee, base_points, &reason)?;
+    let mut response = Response::new();
+    
+    for share in shares.into_iter().filter(|share| !share.points.is_zero()) {
+        // 分配积分，活动效果已计入份额
+        let allocation_response = credit_points(
+            deps,
+            env,
+            &share.user,
+            share.points,
+            reason.clone(),
+            Some(referee.clone()),
+            event_id.clone(),
+            share.campaign_id,
+            true,
+        )?;
+        
+        response = response
+            .add_submessages(allocation_response.messages)
+            .add_events(allocation_response.events)
+            .add_attributes(allocation_response.attributes);
+        
+        // 添加事件属性
+        response = response
+            .add_attribute("level", share.depth.to_string())
+            .add_attribute("referrer", share.user.to_string())
+            .add_attribute("points", share.points.to_string());
+    }
+    
     Ok(response)
 }
 
-/// 更新排行榜
+/// 记录用户余额与积分总量的区块检查点
+fn checkpoint_points(
+    storage: &mut dyn cosmwasm_std::Storage,
+    env: &Env,
+    user: &Addr,
+    old_points: Uint128,
+    new_points: Uint128,
+) -> Result<(), ContractError> {
+    let height = env.block.height;
+    POINTS_SNAPSHOTS.save(storage, user, &new_points, height)?;
+    
+    // 总量缺失（未执行迁移）时报错，而不是下溢
+    let total = TOTAL_POINTS.may_load(storage)?.unwrap_or_default();
+    let total = total
+        .checked_add(new_points)
+        .and_then(|total| total.checked_sub(old_points))
+        .map_err(StdError::from)?;
+    TOTAL_POINTS.save(storage, &total, height)?;
+    
+    Ok(())
+}
+
+/// 按现有用户余额重建余额检查点与积分总量，用于迁移
+pub fn seed_points_snapshots(storage: &mut dyn cosmwasm_std::Storage, env: &Env) -> StdResult<Uint128> {
+    let height = env.block.height;
+    let balances = USER_MAP
+        .range(storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(user, info)| (user, info.reward_points)))
+        .collect::<StdResult<Vec<_>>>()?;
+    
+    let mut total = Uint128::zero();
+    for (user, points) in balances {
+        POINTS_SNAPSHOTS.save(storage, &user, &points, height)?;
+        total = total.checked_add(points)?;
+    }
+    TOTAL_POINTS.save(storage, &total, height)?;
+    Ok(total)
+}
+
+/// 更新排行榜，等级与状态以存储中的最新值为准
 pub fn update_leaderboard(
     storage: &mut dyn cosmwasm_std::Storage,
     user: &Addr,
-    points: Uint128,
+    old_points: Uint128,
 ) -> Result<(), ContractError> {
-    // 使用积分作为键，用户地址作为值
-    // 注意：这里需要处理相同积分的情况
-    let key = points.u128();
-    POINTS_LEADERBOARD.save(storage, key, user)?;
+    let user_info = USER_MAP.load(storage, user)?;
+    crate::leaderboard::update_ranking(
+        storage,
+        Ranking::Points,
+        user,
+        old_points.u128(),
+        user_info.reward_points.u128(),
+        &crate::leaderboard::ranking_member(&user_info),
+    )?;
     Ok(())
 }
 
@@ -137,24 +424,20 @@ pub fn update_leaderboard(
 pub fn get_leaderboard(
     deps: Deps,
     limit: Option<u32>,
-    _start_after: Option<String>,
+    start_after: Option<LeaderboardCursor>,
 ) -> Result<Vec<(Addr, Uint128, u32)>, ContractError> {
-    let limit = limit.unwrap_or(100);
-    let mut entries = Vec::new();
-    let mut rank = 1;
-    
-    // 按积分降序排列
-    let range = POINTS_LEADERBOARD
-        .range(deps.storage, None, None, Order::Descending)
-        .take(limit as usize);
-    
-    for result in range {
-        let (points, user) = result?;
-        entries.push((user, Uint128::from(points), rank));
-        rank += 1;
-    }
+    let (entries, _) = crate::leaderboard::ranking_page(
+        deps,
+        Ranking::Points,
+        &LeaderboardFilter::default(),
+        start_after.as_ref(),
+        limit.unwrap_or(100) as usize,
+    )?;
     
-    Ok(entries)
+    Ok(entries
+        .into_iter()
+        .map(|entry| (entry.user, Uint128::from(entry.score), entry.rank))
+        .collect())
 }
 
 /// 提取积分
@@ -165,6 +448,7 @@ pub fn withdraw_points(
     amount: Uint128,
 ) -> Result<Response, ContractError> {
     let mut user_info = get_user_info(deps.as_ref(), user)?;
+    let old_points = user_info.reward_points;
     
     // 检查积分余额
     if user_info.reward_points < amount {
@@ -178,21 +462,43 @@ pub fn withdraw_points(
     user_info.reward_points -= amount;
     
     // 记录提取历史
-    user_info.points_history.push(PointsRecord {
+    let record = PointsRecord {
         points_change: Uint128::zero().saturating_sub(amount),
         reason: PointsReason::ManualAdjustment,
         timestamp: env.block.time.seconds(),
         related_user: None,
         event_id: Some("withdrawal".to_string()),
-    });
+        campaign_id: None,
+    };
+    crate::history::record_history(deps.storage, user, &mut user_info.points_history, &record)?;
     
     // 保存用户信息
     USER_MAP.save(deps.storage, user, &user_info)?;
+    checkpoint_points(deps.storage, env, user, old_points, user_info.reward_points)?;
+    crate::supply::record_supply(deps.storage, SupplyFlow::Withdrawn, &PointsReason::ManualAdjustment, amount)?;
     
     // 更新排行榜
-    update_leaderboard(deps.storage, user, user_info.reward_points)?;
+    update_leaderboard(deps.storage, user, old_points)?;
+    
+    // 通知订阅合约
+    let hook_msgs = prepare_hooks(deps.storage, &RegistryHookMsg::PointsChanged {
+        user: user.to_string(),
+        old_points,
+        new_points: user_info.reward_points,
+        reason: PointsReason::ManualAdjustment,
+    })?;
+    
+    let points_event = RegistryEvent::PointsChanged {
+        user: user.clone(),
+        reason: PointsReason::ManualAdjustment,
+        old_points,
+        new_points: user_info.reward_points,
+        event_id: Some("withdrawal".to_string()),
+    };
     
     Ok(Response::new()
+        .add_submessages(hook_msgs)
+        .add_event(points_event)
         .add_attribute("action", "points_withdrawn")
         .add_attribute("user", user.to_string())
         .add_attribute("amount", amount.to_string())
@@ -1,11 +1,36 @@
-use cosmwasm_std::{Uint128, Deps, StdResult, Order};
+use cosmwasm_std::{Uint128, Deps, Env, Order, StdError, StdResult};
+use cw_storage_plus::Bound;
 use crate::msg::{
     UserInfoResponse, ReferrerResponse, DirectReferralsResponse, ReferralChainResponse,
     ReferralNode, UserPointsResponse, LeaderboardResponse, LeaderboardEntry,
     PointsHistoryResponse, ConfigResponse, PointsRulesResponse, LevelStatsResponse,
-    ValidationResponse,
+    ValidationResponse, TransferConfigResponse, TransferUsageResponse, HooksResponse,
+    UsersSummaryResponse, UserSummary, VoucherKeyResponse, VoucherStatusResponse,
+    ReferralCodeResponse, ReferralCodesResponse, BindReferrerConfigResponse,
+    DeactivationConfigResponse, DownlineMember, DownlineResponse, DownlineCursor, TeamSummaryResponse,
+    SimulateRegisterResponse, SimulateAllocationResponse, BudgetStatusResponse,
+    CampaignResponse, CampaignsResponse, SeasonResponse, SeasonLeaderboardResponse,
+    UserSeasonPointsResponse, UserPointsAtResponse, TotalPointsAtResponse,
+    DistributionResponse, DistributionClaimResponse, LeaderboardFilter, ReferralLeaderboardResponse,
+    ReferralLeaderboardEntry, SupplyResponse, PointsHistoryFilter, SortOrder, LeaderboardCursor,
 };
-use crate::state::{CONFIG, POINTS_RULES, USER_MAP, REFERRAL_CHAIN, POINTS_LEADERBOARD};
+use crate::state::{CONFIG, POINTS_RULES, USER_MAP, REFERRAL_CHAIN, DOWNLINE};
+use crate::leaderboard::{ranking_page, ranking_total, Ranking};
+
+/// 批量查询用户数量上限
+pub const MAX_USERS_SUMMARY: usize = 500;
+
+/// 排行榜分页默认与最大条数
+pub const DEFAULT_LEADERBOARD_LIMIT: u32 = 100;
+pub const MAX_LEADERBOARD_LIMIT: u32 = 100;
+
+/// 积分历史分页默认与最大条数
+pub const DEFAULT_HISTORY_LIMIT: u32 = 100;
+pub const MAX_HISTORY_LIMIT: u32 = 100;
+
+/// 下级分页默认与最大条数
+pub const DEFAULT_DOWNLINE_LIMIT: u32 = 30;
+pub const MAX_DOWNLINE_LIMIT: u32 = 100;
 
 /// 查询用户信息
 pub fn query_user_info(deps: Deps, user: String) -> StdResult<UserInfoResponse> {
@@ -41,7 +66,7 @@ pub fn query_direct_referrals(
     // 应用分页
     if let Some(start) = start_after {
         let start_addr = deps.api.addr_validate(&start)?;
-        if let Some(pos) = referrals.iter().position(|addr| addr == &start_addr) {
+        if let Some(pos) = referrals.iter().position(|addr| *addr == start_addr) {
             referrals = referrals.into_iter().skip(pos + 1).collect();
         }
     }
@@ -90,6 +115,79 @@ pub fn query_referral_chain(
     })
 }
 
+/// 分页查询下级团队
+pub fn query_downline(
+    deps: Deps,
+    user: String,
+    max_depth: Option<u32>,
+    start_after: Option<DownlineCursor>,
+    limit: Option<u32>,
+) -> StdResult<DownlineResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let config = CONFIG.load(deps.storage)?;
+    let max_depth = max_depth
+        .unwrap_or(config.max_referral_depth)
+        .min(config.max_referral_depth);
+    let limit = limit.unwrap_or(DEFAULT_DOWNLINE_LIMIT).min(MAX_DOWNLINE_LIMIT) as usize;
+    
+    // 游标必须是该用户的下级
+    let start = match start_after {
+        Some(cursor) => {
+            let member = deps.api.addr_validate(&cursor.user)?;
+            if !DOWNLINE.has(deps.storage, (&user_addr, cursor.depth, &member)) {
+                return Err(StdError::not_found("cursor"));
+            }
+            Some((cursor.depth, member))
+        }
+        None => None,
+    };
+    let min = start.as_ref().map(|(depth, member)| Bound::exclusive((*depth, member)));
+    
+    let mut members = Vec::new();
+    for item in DOWNLINE.sub_prefix(&user_addr).keys(deps.storage, min, None, Order::Ascending) {
+        let (depth, member) = item?;
+        if depth > max_depth || members.len() >= limit {
+            break;
+        }
+        let member_info = USER_MAP.load(deps.storage, &member)?;
+        members.push(DownlineMember {
+            user: member,
+            depth,
+            level: member_info.user_level,
+            points: member_info.reward_points,
+        });
+    }
+    
+    let next_cursor = match members.last() {
+        Some(member) if members.len() >= limit => Some(DownlineCursor {
+            depth: member.depth,
+            user: member.user.to_string(),
+        }),
+        _ => None,
+    };
+    Ok(DownlineResponse { members, next_cursor })
+}
+
+/// 查询团队各层级人数
+pub fn query_team_summary(deps: Deps, user: String) -> StdResult<TeamSummaryResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let stats = crate::team::get_team_stats(deps, &user_addr)?;
+    
+    let mut depth_counts = stats.depth_counts;
+    // 去掉末尾的空层级
+    while depth_counts.last() == Some(&0) {
+        depth_counts.pop();
+    }
+    
+    Ok(TeamSummaryResponse {
+        user: user_addr,
+        total_members: depth_counts.iter().sum(),
+        active_members: stats.active_counts.iter().sum(),
+        depth_counts,
+        downline_points: stats.downline_points,
+    })
+}
+
 /// 查询用户积分
 pub fn query_user_points(deps: Deps, user: String) -> StdResult<UserPointsResponse> {
     let user_addr = deps.api.addr_validate(&user)?;
@@ -109,32 +207,55 @@ pub fn query_user_points(deps: Deps, user: String) -> StdResult<UserPointsRespon
 pub fn query_points_leaderboard(
     deps: Deps,
     limit: Option<u32>,
-    _start_after: Option<String>,
+    start_after: Option<LeaderboardCursor>,
+    filter: Option<LeaderboardFilter>,
 ) -> StdResult<LeaderboardResponse> {
-    let limit = limit.unwrap_or(100);
-    let mut entries = Vec::new();
-    let mut rank = 1;
-    
-    // 按积分降序排列
-    let range = POINTS_LEADERBOARD
-        .range(deps.storage, None, None, Order::Descending)
-        .take(limit as usize);
-    
-    for result in range {
-        let (points, user) = result?;
-        let user_info = USER_MAP.load(deps.storage, &user)?;
-        entries.push(LeaderboardEntry {
-            user: user.clone(),
-            points: Uint128::from(points),
-            level: user_info.user_level,
-            rank,
-        });
-        rank += 1;
-    }
+    let limit = limit.unwrap_or(DEFAULT_LEADERBOARD_LIMIT).min(MAX_LEADERBOARD_LIMIT) as usize;
+    let filter = filter.unwrap_or_default();
+    
+    let (entries, next_cursor) = ranking_page(deps, Ranking::Points, &filter, start_after.as_ref(), limit)?;
+    let entries = entries
+        .into_iter()
+        .map(|entry| LeaderboardEntry {
+            user: entry.user,
+            points: Uint128::from(entry.score),
+            level: entry.level,
+            rank: entry.rank,
+        })
+        .collect();
     
     Ok(LeaderboardResponse {
-        entries: entries.clone(),
-        total: entries.len() as u32,
+        entries,
+        total: ranking_total(deps, Ranking::Points, &filter)?,
+        next_cursor,
+    })
+}
+
+/// 查询推荐人数排行榜
+pub fn query_referral_leaderboard(
+    deps: Deps,
+    limit: Option<u32>,
+    start_after: Option<LeaderboardCursor>,
+    filter: Option<LeaderboardFilter>,
+) -> StdResult<ReferralLeaderboardResponse> {
+    let limit = limit.unwrap_or(DEFAULT_LEADERBOARD_LIMIT).min(MAX_LEADERBOARD_LIMIT) as usize;
+    let filter = filter.unwrap_or_default();
+    
+    let (entries, next_cursor) = ranking_page(deps, Ranking::Referrals, &filter, start_after.as_ref(), limit)?;
+    let entries = entries
+        .into_iter()
+        .map(|entry| ReferralLeaderboardEntry {
+            user: entry.user,
+            referrals: entry.score as u32,
+            level: entry.level,
+            rank: entry.rank,
+        })
+        .collect();
+    
+    Ok(ReferralLeaderboardResponse {
+        entries,
+        total: ranking_total(deps, Ranking::Referrals, &filter)?,
+        next_cursor,
     })
 }
 
@@ -144,26 +265,46 @@ pub fn query_points_history(
     user: String,
     limit: Option<u32>,
     start_after: Option<u64>,
+    filter: Option<PointsHistoryFilter>,
+    order: Option<SortOrder>,
+    cursor: Option<u64>,
 ) -> StdResult<PointsHistoryResponse> {
     let user_addr = deps.api.addr_validate(&user)?;
-    let user_info = USER_MAP.load(deps.storage, &user_addr)?;
+    if !USER_MAP.has(deps.storage, &user_addr) {
+        return Err(StdError::not_found("user"));
+    }
     
-    let limit = limit.unwrap_or(100);
-    let mut records = user_info.points_history;
+    let limit = limit.unwrap_or(DEFAULT_HISTORY_LIMIT).min(MAX_HISTORY_LIMIT) as usize;
+    let mut filter = filter.unwrap_or_default();
+    let related_user = filter
+        .related_user
+        .as_ref()
+        .map(|related_user| deps.api.addr_validate(related_user))
+        .transpose()?;
     
-    // 应用分页
+    // start_after 为时间戳，只返回之后的记录
     if let Some(start) = start_after {
-        records = records.into_iter()
-            .filter(|record| record.timestamp > start)
-            .collect();
+        filter.from_time = Some(filter.from_time.unwrap_or_default().max(start.saturating_add(1)));
     }
     
-    let total = records.len() as u32;
-    records.truncate(limit as usize);
+    let order = match order.unwrap_or_default() {
+        SortOrder::Ascending => Order::Ascending,
+        SortOrder::Descending => Order::Descending,
+    };
+    let (records, total, next_cursor) = crate::history::points_history(
+        deps,
+        &user_addr,
+        &filter,
+        related_user.as_ref(),
+        order,
+        cursor,
+        limit,
+    )?;
     
     Ok(PointsHistoryResponse {
         records,
         total,
+        next_cursor,
     })
 }
 
@@ -181,84 +322,285 @@ pub fn query_points_rules(deps: Deps) -> StdResult<PointsRulesResponse> {
 
 /// 查询用户等级统计
 pub fn query_level_stats(deps: Deps) -> StdResult<LevelStatsResponse> {
-    let mut stats = std::collections::HashMap::new();
-    let mut total_users = 0;
-    
-    // 遍历所有用户统计等级
-    let users: Vec<_> = USER_MAP
-        .range(deps.storage, None, None, Order::Ascending)
-        .collect::<StdResult<Vec<_>>>()?;
-    
-    for (_, user_info) in users {
-        let count = stats.entry(user_info.user_level).or_insert(0);
-        *count += 1;
-        total_users += 1;
-    }
-    
-    Ok(LevelStatsResponse {
-        stats,
-        total_users,
-    })
+    crate::stats::level_stats(deps)
 }
 
 /// 验证推荐关系
 pub fn query_validate_referral(
     deps: Deps,
+    env: Env,
     referrer: String,
     referee: String,
 ) -> StdResult<ValidationResponse> {
-    let referrer_addr = deps.api.addr_validate(&referrer)?;
-    let referee_addr = deps.api.addr_validate(&referee)?;
-    
-    // 检查推荐人是否存在
-    if !USER_MAP.has(deps.storage, &referrer_addr) {
-        return Ok(ValidationResponse {
-            is_valid: false,
-            reason: Some("Referrer not registered".to_string()),
-            depth: None,
-        });
-    }
+    // 与模拟注册走同一流程
+    let simulation = query_simulate_register(deps, env, referee, Some(referrer))?;
     
-    // 检查被推荐人是否已注册
-    if USER_MAP.has(deps.storage, &referee_addr) {
-        return Ok(ValidationResponse {
-            is_valid: false,
-            reason: Some("Referee already registered".to_string()),
-            depth: None,
-        });
+    Ok(ValidationResponse {
+        is_valid: simulation.success,
+        reason: simulation.error,
+        depth: simulation.success.then_some(simulation.chain_depth),
+    })
+}
+
+/// 模拟注册
+pub fn query_simulate_register(
+    deps: Deps,
+    env: Env,
+    user: String,
+    referrer: Option<String>,
+) -> StdResult<SimulateRegisterResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let referrer_addr = referrer.map(|r| deps.api.addr_validate(&r)).transpose()?;
+    
+    crate::simulation::simulate_register(deps, &env, &user_addr, referrer_addr)
+        .map_err(|err| cosmwasm_std::StdError::generic_err(err.to_string()))
+}
+
+/// 模拟多层级奖励分配
+pub fn query_simulate_allocation(
+    deps: Deps,
+    env: Env,
+    user: String,
+    base_points: Uint128,
+    reason: crate::state::PointsReason,
+) -> StdResult<SimulateAllocationResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let mut shares = crate::points::calculate_multi_level_rewards(deps, &env, &user_addr, base_points, &reason)
+        .map_err(|err| cosmwasm_std::StdError::generic_err(err.to_string()))?;
+    crate::simulation::apply_caps_to_shares(deps, &env, &reason, &mut shares)
+        .map_err(|err| cosmwasm_std::StdError::generic_err(err.to_string()))?;
+    let total_points = shares.iter().map(|share| share.points).sum();
+    
+    Ok(SimulateAllocationResponse {
+        user: user_addr,
+        base_points,
+        reason,
+        shares,
+        total_points,
+    })
+}
+
+/// 查询积分上限与预算余量
+pub fn query_budget_status(deps: Deps, env: Env, user: Option<String>) -> StdResult<BudgetStatusResponse> {
+    let user_addr = user.map(|user| deps.api.addr_validate(&user)).transpose()?;
+    crate::caps::get_budget_status(deps, &env, user_addr.as_ref())
+}
+
+/// 查询活动
+pub fn query_campaign(deps: Deps, env: Env, id: u64) -> StdResult<CampaignResponse> {
+    let campaign = crate::state::CAMPAIGNS.load(deps.storage, id)?;
+    let running = crate::campaign::is_running(&campaign, env.block.time.seconds());
+    Ok(CampaignResponse { campaign, running })
+}
+
+/// 分页列出活动
+pub fn query_campaigns(deps: Deps, start_after: Option<u64>, limit: Option<u32>) -> StdResult<CampaignsResponse> {
+    let campaigns = crate::campaign::list_campaigns(deps, start_after, limit)?;
+    Ok(CampaignsResponse { campaigns })
+}
+
+/// 查询赛季
+pub fn query_season(deps: Deps, season_id: Option<u64>) -> StdResult<SeasonResponse> {
+    let season_id = match season_id {
+        Some(season_id) => Some(season_id),
+        None => crate::state::CURRENT_SEASON.may_load(deps.storage)?,
+    };
+    let season = season_id
+        .map(|season_id| crate::state::SEASONS.may_load(deps.storage, season_id))
+        .transpose()?
+        .flatten();
+    Ok(SeasonResponse { season })
+}
+
+/// 查询赛季排行榜
+pub fn query_season_leaderboard(
+    deps: Deps,
+    season_id: u64,
+    limit: Option<u32>,
+) -> StdResult<SeasonLeaderboardResponse> {
+    let limit = limit.unwrap_or(100).min(crate::season::MAX_SEASON_SNAPSHOT as u32) as usize;
+    let (season, entries) = crate::season::season_leaderboard(deps, season_id, limit)?;
+    
+    Ok(SeasonLeaderboardResponse {
+        finalized: season.end_time.is_some(),
+        season,
+        entries,
+    })
+}
+
+/// 查询用户赛季积分
+pub fn query_user_season_points(
+    deps: Deps,
+    season_id: u64,
+    user: String,
+) -> StdResult<UserSeasonPointsResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let points = crate::season::user_season_points(deps, season_id, &user_addr)?;
+    
+    Ok(UserSeasonPointsResponse {
+        season_id,
+        user: user_addr,
+        points,
+    })
+}
+
+/// 查询用户在指定高度的积分
+pub fn query_user_points_at(deps: Deps, user: String, height: u64) -> StdResult<UserPointsAtResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let points = crate::state::POINTS_SNAPSHOTS
+        .may_load_at_height(deps.storage, &user_addr, height)?
+        .unwrap_or_default();
+    
+    Ok(UserPointsAtResponse {
+        user: user_addr,
+        height,
+        points,
+    })
+}
+
+/// 查询指定高度的积分总量
+pub fn query_total_points_at(deps: Deps, height: u64) -> StdResult<TotalPointsAtResponse> {
+    let total_points = crate::state::TOTAL_POINTS
+        .may_load_at_height(deps.storage, height)?
+        .unwrap_or_default();
+    
+    Ok(TotalPointsAtResponse { height, total_points })
+}
+
+/// 查询分发
+pub fn query_distribution(deps: Deps, env: Env, id: u64) -> StdResult<DistributionResponse> {
+    let distribution = crate::state::DISTRIBUTIONS.load(deps.storage, id)?;
+    let expired = distribution.reclaimed.is_some() || env.block.time.seconds() > distribution.expires_at;
+    Ok(DistributionResponse { distribution, expired })
+}
+
+/// 查询用户的分发领取记录
+pub fn query_distribution_claim(deps: Deps, id: u64, user: String) -> StdResult<DistributionClaimResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let claimed = crate::state::DISTRIBUTION_CLAIMS.may_load(deps.storage, (id, &user_addr))?;
+    Ok(DistributionClaimResponse {
+        id,
+        user: user_addr,
+        claimed,
+    })
+}
+
+/// 查询积分供应
+pub fn query_supply(deps: Deps) -> StdResult<SupplyResponse> {
+    crate::supply::get_supply(deps)
+}
+
+/// 查询转账配置
+pub fn query_transfer_config(deps: Deps) -> StdResult<TransferConfigResponse> {
+    let config = crate::transfer::load_transfer_config(deps)?;
+    Ok(TransferConfigResponse { config })
+}
+
+/// 查询用户当日转账用量
+pub fn query_transfer_usage(deps: Deps, env: Env, user: String) -> StdResult<TransferUsageResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let usage = crate::transfer::get_daily_usage(deps, &env, &user_addr)?;
+    
+    Ok(TransferUsageResponse { usage })
+}
+
+/// 查询钩子列表
+pub fn query_hooks(deps: Deps) -> StdResult<HooksResponse> {
+    let hooks = crate::hooks::list_hooks(deps)?;
+    
+    Ok(HooksResponse { hooks })
+}
+
+/// 批量查询用户摘要
+pub fn query_users_summary(deps: Deps, users: Vec<String>) -> StdResult<UsersSummaryResponse> {
+    if users.len() > MAX_USERS_SUMMARY {
+        return Err(cosmwasm_std::StdError::generic_err(format!(
+            "Too many users: limit {}, actual {}",
+            MAX_USERS_SUMMARY,
+            users.len()
+        )));
     }
     
-    // 检查循环推荐
-    match crate::referral::check_circular_referral(deps, &referrer_addr, &referee_addr) {
-        Ok(_) => Ok(ValidationResponse {
-            is_valid: true,
-            reason: None,
-            depth: Some(1),
-        }),
-        Err(_) => Ok(ValidationResponse {
-            is_valid: false,
-            reason: Some("Circular referral detected".to_string()),
-            depth: None,
-        }),
+    let mut summaries = Vec::with_capacity(users.len());
+    for user in users {
+        let user_addr = deps.api.addr_validate(&user)?;
+        let summary = match USER_MAP.may_load(deps.storage, &user_addr)? {
+            Some(user_info) => UserSummary {
+                user: user_addr,
+                registered: true,
+                level: Some(user_info.user_level),
+                points: user_info.reward_points,
+                referrer: user_info.recommender,
+                status: Some(user_info.status),
+            },
+            None => UserSummary {
+                user: user_addr,
+                registered: false,
+                level: None,
+                points: Uint128::zero(),
+                referrer: None,
+                status: None,
+            },
+        };
+        summaries.push(summary);
     }
+    
+    Ok(UsersSummaryResponse { users: summaries })
 }
 
-/// 计算用户排名
-fn calculate_user_rank(deps: Deps, points: &Uint128) -> StdResult<Option<u32>> {
-    let mut rank = 1;
+/// 查询推荐人凭证公钥
+pub fn query_voucher_key(deps: Deps, user: String) -> StdResult<VoucherKeyResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let public_key = crate::state::VOUCHER_KEYS.may_load(deps.storage, &user_addr)?;
     
-    // 遍历排行榜计算排名
-    let range = POINTS_LEADERBOARD
-        .range(deps.storage, None, None, Order::Descending);
+    Ok(VoucherKeyResponse { public_key })
+}
+
+/// 查询凭证是否已使用
+pub fn query_voucher_status(deps: Deps, referrer: String, nonce: u64) -> StdResult<VoucherStatusResponse> {
+    let referrer_addr = deps.api.addr_validate(&referrer)?;
+    let used = crate::state::USED_VOUCHERS.has(deps.storage, (&referrer_addr, nonce));
     
-    for result in range {
-        let (leaderboard_points, _) = result?;
-        if Uint128::from(leaderboard_points) > *points {
-            rank += 1;
-        } else {
-            break;
-        }
-    }
+    Ok(VoucherStatusResponse { used })
+}
+
+/// 解析推荐码
+pub fn query_resolve_referral_code(deps: Deps, env: Env, code: String) -> StdResult<ReferralCodeResponse> {
+    let code = crate::referral_code::resolve_referral_code(deps, &code)?;
+    let usable = code
+        .as_ref()
+        .map(|code| crate::referral_code::check_code_usable(&env, code).is_ok())
+        .unwrap_or(false);
+    
+    Ok(ReferralCodeResponse { code, usable })
+}
+
+/// 查询用户创建的推荐码
+pub fn query_user_codes(
+    deps: Deps,
+    user: String,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<ReferralCodesResponse> {
+    let user_addr = deps.api.addr_validate(&user)?;
+    let codes = crate::referral_code::list_user_codes(deps, &user_addr, start_after, limit)?;
     
-    Ok(Some(rank))
+    Ok(ReferralCodesResponse { codes })
+}
+
+/// 查询补绑推荐人配置
+pub fn query_bind_referrer_config(deps: Deps) -> StdResult<BindReferrerConfigResponse> {
+    let config = crate::state::BIND_REFERRER_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+    Ok(BindReferrerConfigResponse { config })
+}
+
+/// 查询注销配置
+pub fn query_deactivation_config(deps: Deps) -> StdResult<DeactivationConfigResponse> {
+    let config = crate::state::DEACTIVATION_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+    Ok(DeactivationConfigResponse { config })
+}
+
+/// 计算用户排名
+fn calculate_user_rank(deps: Deps, points: &Uint128) -> StdResult<Option<u32>> {
+    Ok(Some(crate::leaderboard::points_rank(deps, points.u128())?))
 }
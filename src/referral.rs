@@ -1,7 +1,12 @@
-use cosmwasm_std::{Addr, Deps, DepsMut, Env, Response};
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, Response, StdResult, Storage, Uint128};
 use crate::error::ContractError;
-use crate::state::{REFERRAL_CHAIN, USER_MAP};
-use crate::user::add_referral_relation;
+use crate::state::{
+    DailyReferralCount, PointsReason, ReferralRewardAction, UserStatus, BIND_REFERRER_CONFIG, CONFIG,
+    DAILY_REFERRALS, OUTSTANDING_REFERRAL_REWARDS, REFERRAL_CHAIN, USER_MAP,
+};
+use crate::user::{add_referral_relation, get_user_info, remove_referral_relation, update_user_level};
+use crate::points::{credit_points_exact, deduct_points_from_user};
+use crate::events::RegistryEvent;
 
 /// 验证推荐关系
 pub fn validate_referral(
@@ -9,8 +14,8 @@ pub fn validate_referral(
     referrer: &Addr,
     referee: &Addr,
 ) -> Result<(), ContractError> {
-    // 检查推荐人是否存在
-    if !USER_MAP.has(deps.storage, referrer) {
+    // 检查推荐人是否存在且未注销
+    if !is_open_referrer(deps, referrer)? {
         return Err(ContractError::InvalidReferrer {
             referrer: referrer.to_string(),
         });
@@ -29,6 +34,13 @@ pub fn validate_referral(
     Ok(())
 }
 
+/// 推荐人已注册且未注销
+fn is_open_referrer(deps: Deps, referrer: &Addr) -> Result<bool, ContractError> {
+    Ok(USER_MAP
+        .may_load(deps.storage, referrer)?
+        .is_some_and(|info| info.status != UserStatus::Closed))
+}
+
 /// 检查循环推荐
 pub fn check_circular_referral(
     deps: Deps,
@@ -61,9 +73,67 @@ pub fn check_circular_referral(
     Ok(())
 }
 
+/// 查询推荐人剩余冷却时间与当日已推荐次数
+pub fn referral_limit_status(
+    deps: Deps,
+    env: &Env,
+    referrer: &Addr,
+) -> Result<(u64, DailyReferralCount), ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let now = env.block.time.seconds();
+    let referrer_info = get_user_info(deps, referrer)?;
+    
+    let cooldown_remaining = referrer_info
+        .referral_stats
+        .last_referral_time
+        .map(|last_referral_time| {
+            let elapsed = now.saturating_sub(last_referral_time);
+            config.referral_cooldown.saturating_sub(elapsed)
+        })
+        .unwrap_or_default();
+    
+    let today = now / crate::transfer::SECONDS_PER_DAY;
+    let daily = match DAILY_REFERRALS.may_load(deps.storage, referrer)? {
+        Some(daily) if daily.day == today => daily,
+        _ => DailyReferralCount { day: today, count: 0 },
+    };
+    
+    Ok((cooldown_remaining, daily))
+}
+
+/// 检查推荐人的冷却期与每日推荐上限
+pub fn check_referral_limits(
+    deps: Deps,
+    env: &Env,
+    referrer: &Addr,
+) -> Result<DailyReferralCount, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    let (cooldown_remaining, mut daily) = referral_limit_status(deps, env, referrer)?;
+    
+    // 冷却期
+    if cooldown_remaining > 0 {
+        return Err(ContractError::CooldownNotReached {
+            cooldown_type: "referral".to_string(),
+            remaining_time: cooldown_remaining,
+        });
+    }
+    
+    // 每日上限
+    daily.count += 1;
+    if daily.count > config.max_daily_referrals {
+        return Err(ContractError::LimitExceeded {
+            limit_type: "daily_referrals".to_string(),
+            limit_value: config.max_daily_referrals,
+            actual_value: daily.count,
+        });
+    }
+    
+    Ok(daily)
+}
+
 /// 建立推荐关系
 pub fn establish_referral_relation(
-    deps: DepsMut,
+    mut deps: DepsMut,
     env: &Env,
     referrer: &Addr,
     referee: &Addr,
@@ -72,15 +142,239 @@ pub fn establish_referral_relation(
     validate_referral(deps.as_ref(), referrer, referee)?;
     
     // 添加推荐关系
-    add_referral_relation(deps, env, referrer, referee)?;
+    add_referral_relation(deps.branch(), env, referrer, referee)?;
+    
+    // 通知订阅合约
+    let hook_msgs = crate::hooks::prepare_hooks(deps.storage, &crate::hooks::RegistryHookMsg::ReferralEstablished {
+        referrer: referrer.to_string(),
+        referee: referee.to_string(),
+        timestamp: env.block.time.seconds(),
+    })?;
     
     Ok(Response::new()
+        .add_submessages(hook_msgs)
+        .add_event(RegistryEvent::ReferralEstablished {
+            referrer: referrer.clone(),
+            referee: referee.clone(),
+            timestamp: env.block.time.seconds(),
+        })
         .add_attribute("action", "referral_established")
         .add_attribute("referrer", referrer.to_string())
         .add_attribute("referee", referee.to_string())
         .add_attribute("timestamp", env.block.time.seconds().to_string()))
 }
 
+/// 为未绑定推荐人的用户补绑推荐人
+pub fn bind_referrer(
+    mut deps: DepsMut,
+    env: &Env,
+    user: &Addr,
+    referrer: &Addr,
+) -> Result<Response, ContractError> {
+    let bind_config = BIND_REFERRER_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+    if bind_config.window == 0 {
+        return Err(ContractError::FeatureDisabled {
+            feature: "bind_referrer".to_string(),
+        });
+    }
+    
+    crate::security::check_user_active(deps.as_ref(), user)?;
+    let mut user_info = get_user_info(deps.as_ref(), user)?;
+    
+    // 只能绑定一次
+    if user_info.recommender.is_some() || REFERRAL_CHAIN.has(deps.storage, user) {
+        return Err(ContractError::InvalidReferrer {
+            referrer: referrer.to_string(),
+        });
+    }
+    
+    // 必须在注册后的窗口期内
+    let deadline = user_info.registered_at.saturating_add(bind_config.window);
+    if env.block.time.seconds() > deadline {
+        return Err(ContractError::InvalidParameter {
+            parameter: "bind_window".to_string(),
+            value: format!("expired at {}", deadline),
+        });
+    }
+    
+    // 与注册相同的推荐人校验
+    if user == referrer || !is_open_referrer(deps.as_ref(), referrer)? {
+        return Err(ContractError::InvalidReferrer {
+            referrer: referrer.to_string(),
+        });
+    }
+    check_circular_referral(deps.as_ref(), referrer, user)?;
+    let daily = check_referral_limits(deps.as_ref(), env, referrer)?;
+    DAILY_REFERRALS.save(deps.storage, referrer, &daily)?;
+    
+    add_referral_relation(deps.branch(), env, referrer, user)?;
+    user_info.recommender = Some(referrer.clone());
+    USER_MAP.save(deps.storage, user, &user_info)?;
+    
+    // 通知订阅合约
+    let hook_msgs = crate::hooks::prepare_hooks(deps.storage, &crate::hooks::RegistryHookMsg::ReferralEstablished {
+        referrer: referrer.to_string(),
+        referee: user.to_string(),
+        timestamp: env.block.time.seconds(),
+    })?;
+    let mut response = Response::new()
+        .add_submessages(hook_msgs)
+        .add_event(RegistryEvent::ReferralEstablished {
+            referrer: referrer.clone(),
+            referee: user.clone(),
+            timestamp: env.block.time.seconds(),
+        });
+    
+    // 按推荐规则向上级发放奖励
+    if bind_config.trigger_payout {
+        let rules = crate::state::POINTS_RULES.load(deps.storage)?;
+        let payout_response = crate::points::allocate_multi_level_rewards(
+            &mut deps,
+            env,
+            user,
+            rules.base_points,
+            PointsReason::ReferralReward,
+            Some(format!("bind:{}", user)),
+        )?;
+        response = response
+            .add_submessages(payout_response.messages)
+            .add_events(payout_response.events)
+            .add_attributes(payout_response.attributes);
+    }
+    
+    Ok(response
+        .add_attribute("action", "referrer_bound")
+        .add_attribute("user", user.to_string())
+        .add_attribute("referrer", referrer.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 更正用户的推荐人
+pub fn reassign_referrer(
+    mut deps: DepsMut,
+    env: &Env,
+    user: &Addr,
+    new_referrer: &Addr,
+    reward_action: ReferralRewardAction,
+) -> Result<Response, ContractError> {
+    let mut user_info = get_user_info(deps.as_ref(), user)?;
+    let old_referrer = REFERRAL_CHAIN.may_load(deps.storage, user)?;
+    
+    if user_info.status == UserStatus::Closed {
+        return Err(ContractError::InvalidUserStatus {
+            user: user.to_string(),
+            status: format!("{:?}", user_info.status),
+        });
+    }
+    
+    if user == new_referrer || old_referrer.as_ref() == Some(new_referrer) {
+        return Err(ContractError::InvalidReferrer {
+            referrer: new_referrer.to_string(),
+        });
+    }
+    
+    if !is_open_referrer(deps.as_ref(), new_referrer)? {
+        return Err(ContractError::InvalidReferrer {
+            referrer: new_referrer.to_string(),
+        });
+    }
+    
+    // 新推荐人不能是该用户的下级
+    check_circular_referral(deps.as_ref(), new_referrer, user)?;
+    
+    if let Some(ref old) = old_referrer {
+        remove_referral_relation(deps.branch(), old, user)?;
+    }
+    
+    // 管理员更正不应触发新推荐人的推荐冷却
+    let last_referral_time = get_user_info(deps.as_ref(), new_referrer)?.referral_stats.last_referral_time;
+    add_referral_relation(deps.branch(), env, new_referrer, user)?;
+    let mut new_referrer_info = get_user_info(deps.as_ref(), new_referrer)?;
+    new_referrer_info.referral_stats.last_referral_time = last_referral_time;
+    USER_MAP.save(deps.storage, new_referrer, &new_referrer_info)?;
+    
+    user_info.recommender = Some(new_referrer.clone());
+    USER_MAP.save(deps.storage, user, &user_info)?;
+    
+    // 按推荐人数重新计算双方等级
+    let mut response = Response::new();
+    for referrer in old_referrer.iter().chain(std::iter::once(new_referrer)) {
+        let total_referrals = get_user_info(deps.as_ref(), referrer)?.referral_stats.total_referrals;
+        let level_response = update_user_level(&mut deps, referrer, total_referrals)?;
+        response = response
+            .add_events(level_response.events)
+            .add_attributes(level_response.attributes);
+    }
+    
+    // 处理原推荐人因该用户获得、尚未追回的直推奖励；无论如何处理，原推荐人的未追回记录都清除，
+    // 避免再次更正时重复追回
+    let mut moved_points = Uint128::zero();
+    let outstanding = match old_referrer {
+        Some(ref old) => {
+            let outstanding = OUTSTANDING_REFERRAL_REWARDS.may_load(deps.storage, (old, user))?.unwrap_or_default();
+            OUTSTANDING_REFERRAL_REWARDS.remove(deps.storage, (old, user));
+            outstanding
+        }
+        None => Uint128::zero(),
+    };
+    if let (Some(ref old), false) = (&old_referrer, reward_action == ReferralRewardAction::Keep) {
+        let old_info = get_user_info(deps.as_ref(), old)?;
+        moved_points = outstanding.min(old_info.reward_points);
+        
+        if !moved_points.is_zero() {
+            let event_id = Some(format!("reassign:{}", user));
+            let deduct_response = deduct_points_from_user(
+                &mut deps,
+                env,
+                old,
+                moved_points,
+                PointsReason::Penalty,
+                Some(user.clone()),
+                event_id.clone(),
+            )?;
+            response = response
+                .add_submessages(deduct_response.messages)
+                .add_events(deduct_response.events);
+            
+            if reward_action == ReferralRewardAction::Transfer {
+                let allocation_response = credit_points_exact(
+                    &mut deps,
+                    env,
+                    new_referrer,
+                    moved_points,
+                    PointsReason::ReferralReward,
+                    Some(user.clone()),
+                    event_id,
+                )?;
+                response = response
+                    .add_submessages(allocation_response.messages)
+                    .add_events(allocation_response.events);
+            }
+        }
+    }
+    
+    // 通知订阅合约
+    let hook_msgs = crate::hooks::prepare_hooks(deps.storage, &crate::hooks::RegistryHookMsg::ReferralEstablished {
+        referrer: new_referrer.to_string(),
+        referee: user.to_string(),
+        timestamp: env.block.time.seconds(),
+    })?;
+    
+    Ok(response
+        .add_submessages(hook_msgs)
+        .add_event(RegistryEvent::ReferralEstablished {
+            referrer: new_referrer.clone(),
+            referee: user.clone(),
+            timestamp: env.block.time.seconds(),
+        })
+        .add_attribute("action", "referrer_reassigned")
+        .add_attribute("user", user.to_string())
+        .add_attribute("old_referrer", old_referrer.map(|addr| addr.to_string()).unwrap_or_default())
+        .add_attribute("new_referrer", new_referrer.to_string())
+        .add_attribute("reward_action", format!("{:?}", reward_action))
+        .add_attribute("moved_points", moved_points.to_string()))
+}
+
 /// 获取推荐链
 pub fn get_referral_chain(
     deps: Deps,
@@ -106,3 +400,19 @@ pub fn get_referral_chain(
     
     Ok(chain)
 }
+
+/// 记录推荐人因某下级获得的推荐奖励，更正推荐人时据此追回
+pub fn record_referral_reward(
+    storage: &mut dyn Storage,
+    referrer: &Addr,
+    referee: &Addr,
+    points: Uint128,
+) -> StdResult<()> {
+    if points.is_zero() {
+        return Ok(());
+    }
+    OUTSTANDING_REFERRAL_REWARDS.update(storage, (referrer, referee), |outstanding| -> StdResult<_> {
+        Ok(outstanding.unwrap_or_default() + points)
+    })?;
+    Ok(())
+}
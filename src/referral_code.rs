@@ -0,0 +1,188 @@
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, Order, StdResult};
+use cw_storage_plus::Bound;
+use crate::error::ContractError;
+use crate::state::{ReferralCode, REFERRAL_CODES, USER_CODES, USER_MAP};
+
+/// 推荐码长度范围
+pub const MIN_CODE_LENGTH: usize = 4;
+pub const MAX_CODE_LENGTH: usize = 20;
+
+/// 每个用户可创建的推荐码上限
+pub const MAX_CODES_PER_USER: usize = 10;
+
+/// 保留推荐码，防止冒充官方
+const RESERVED_CODES: &[&str] = &[
+    "admin", "administrator", "root", "system", "official", "support", "help",
+    "luckee", "luckeedao", "registry", "contract", "team", "staff", "moderator",
+];
+
+/// 不允许出现在推荐码中的词
+const BLOCKED_WORDS: &[&str] = &[
+    "fuck", "shit", "bitch", "cunt", "pussy", "nigger", "nigga", "faggot", "whore",
+    "slut", "nazi",
+];
+
+fn invalid_code(code: &str, reason: &str) -> ContractError {
+    ContractError::InvalidReferralCode {
+        code: code.to_string(),
+        reason: reason.to_string(),
+    }
+}
+
+/// 规范化推荐码（统一小写）
+pub fn normalize_code(code: &str) -> String {
+    code.trim().to_ascii_lowercase()
+}
+
+/// 校验推荐码格式
+pub fn validate_code(code: &str) -> Result<(), ContractError> {
+    if code.len() < MIN_CODE_LENGTH || code.len() > MAX_CODE_LENGTH {
+        return Err(invalid_code(code, "length out of range"));
+    }
+    
+    if !code.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-' || c == '_') {
+        return Err(invalid_code(code, "only a-z, 0-9, '-' and '_' are allowed"));
+    }
+    
+    if !code.starts_with(|c: char| c.is_ascii_alphanumeric()) {
+        return Err(invalid_code(code, "must start with a letter or digit"));
+    }
+    
+    if RESERVED_CODES.contains(&code) {
+        return Err(invalid_code(code, "reserved"));
+    }
+    
+    // 去掉分隔符后再检查，避免 f-u-c-k 之类的绕过
+    let compact: String = code.chars().filter(|c| c.is_ascii_alphanumeric()).collect();
+    if BLOCKED_WORDS.iter().any(|word| compact.contains(word)) {
+        return Err(invalid_code(code, "contains blocked word"));
+    }
+    
+    Ok(())
+}
+
+/// 创建推荐码
+pub fn create_referral_code(
+    deps: DepsMut,
+    env: &Env,
+    owner: &Addr,
+    code: &str,
+    max_uses: Option<u32>,
+    expires_at: Option<u64>,
+) -> Result<ReferralCode, ContractError> {
+    if !USER_MAP.has(deps.storage, owner) {
+        return Err(ContractError::UserNotRegistered {
+            user: owner.to_string(),
+        });
+    }
+    
+    let code = normalize_code(code);
+    validate_code(&code)?;
+    
+    if REFERRAL_CODES.has(deps.storage, &code) {
+        return Err(ContractError::ReferralCodeTaken { code });
+    }
+    
+    if max_uses == Some(0) {
+        return Err(ContractError::InvalidParameter {
+            parameter: "max_uses".to_string(),
+            value: "0".to_string(),
+        });
+    }
+    
+    if let Some(expires_at) = expires_at {
+        if expires_at <= env.block.time.seconds() {
+            return Err(ContractError::InvalidParameter {
+                parameter: "expires_at".to_string(),
+                value: expires_at.to_string(),
+            });
+        }
+    }
+    
+    let owned = USER_CODES
+        .prefix(owner)
+        .keys(deps.storage, None, None, Order::Ascending)
+        .count();
+    if owned >= MAX_CODES_PER_USER {
+        return Err(ContractError::LimitExceeded {
+            limit_type: "referral_codes".to_string(),
+            limit_value: MAX_CODES_PER_USER as u32,
+            actual_value: (owned + 1) as u32,
+        });
+    }
+    
+    let referral_code = ReferralCode {
+        code: code.clone(),
+        owner: owner.clone(),
+        max_uses,
+        uses: 0,
+        expires_at,
+        created_at: env.block.time.seconds(),
+        last_used_at: None,
+    };
+    REFERRAL_CODES.save(deps.storage, &code, &referral_code)?;
+    USER_CODES.save(deps.storage, (owner, &code), &true)?;
+    
+    Ok(referral_code)
+}
+
+/// 检查推荐码当前是否可用
+pub fn check_code_usable(env: &Env, referral_code: &ReferralCode) -> Result<(), ContractError> {
+    if let Some(expires_at) = referral_code.expires_at {
+        if env.block.time.seconds() > expires_at {
+            return Err(invalid_code(&referral_code.code, "expired"));
+        }
+    }
+    
+    if let Some(max_uses) = referral_code.max_uses {
+        if referral_code.uses >= max_uses {
+            return Err(invalid_code(&referral_code.code, "usage limit reached"));
+        }
+    }
+    
+    Ok(())
+}
+
+/// 使用推荐码，返回推荐人地址
+pub fn consume_referral_code(
+    deps: DepsMut,
+    env: &Env,
+    code: &str,
+) -> Result<Addr, ContractError> {
+    let code = normalize_code(code);
+    let mut referral_code = REFERRAL_CODES
+        .may_load(deps.storage, &code)?
+        .ok_or_else(|| invalid_code(&code, "not found"))?;
+    
+    check_code_usable(env, &referral_code)?;
+    
+    referral_code.uses += 1;
+    referral_code.last_used_at = Some(env.block.time.seconds());
+    REFERRAL_CODES.save(deps.storage, &code, &referral_code)?;
+    
+    Ok(referral_code.owner)
+}
+
+/// 查询推荐码
+pub fn resolve_referral_code(deps: Deps, code: &str) -> StdResult<Option<ReferralCode>> {
+    REFERRAL_CODES.may_load(deps.storage, &normalize_code(code))
+}
+
+/// 列出用户创建的推荐码
+pub fn list_user_codes(
+    deps: Deps,
+    owner: &Addr,
+    start_after: Option<String>,
+    limit: Option<u32>,
+) -> StdResult<Vec<ReferralCode>> {
+    let limit = limit.unwrap_or(MAX_CODES_PER_USER as u32) as usize;
+    let start = start_after.map(|code| normalize_code(&code));
+    let min = start.as_deref().map(Bound::exclusive);
+    
+    USER_CODES
+        .prefix(owner)
+        .keys(deps.storage, min, None, Order::Ascending)
+        .take(limit)
+        .map(|code| REFERRAL_CODES.load(deps.storage, &code?))
+        .collect()
+}
@@ -0,0 +1,152 @@
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, Order, StdResult, Storage, Uint128};
+use crate::error::ContractError;
+use crate::msg::SeasonRankEntry;
+use crate::state::{
+    PointsReason, Season, CURRENT_SEASON, NEXT_SEASON_ID, SEASONS, SEASON_POINTS, SEASON_RANKING,
+    SEASON_SNAPSHOTS,
+};
+
+/// 赛季结束时归档的排名条数
+pub const MAX_SEASON_SNAPSHOT: usize = 100;
+
+/// 赛季名称长度上限
+pub const MAX_SEASON_NAME_LENGTH: usize = 64;
+
+/// 开始新赛季，同一时间只能有一个进行中的赛季
+pub fn start_season(deps: DepsMut, env: &Env, name: String) -> Result<Season, ContractError> {
+    if let Some(current) = CURRENT_SEASON.may_load(deps.storage)? {
+        return Err(ContractError::InvalidParameter {
+            parameter: "season".to_string(),
+            value: format!("season {} still running", current),
+        });
+    }
+    
+    if name.is_empty() || name.len() > MAX_SEASON_NAME_LENGTH {
+        return Err(ContractError::InvalidParameter {
+            parameter: "name".to_string(),
+            value: format!("length {}", name.len()),
+        });
+    }
+    
+    let id = NEXT_SEASON_ID.may_load(deps.storage)?.unwrap_or(1);
+    NEXT_SEASON_ID.save(deps.storage, &(id + 1))?;
+    
+    let season = Season {
+        id,
+        name,
+        start_time: env.block.time.seconds(),
+        end_time: None,
+        participants: 0,
+    };
+    SEASONS.save(deps.storage, id, &season)?;
+    CURRENT_SEASON.save(deps.storage, &id)?;
+    
+    Ok(season)
+}
+
+/// 结束当前赛季并归档最终排名
+pub fn close_season(deps: DepsMut, env: &Env) -> Result<Season, ContractError> {
+    let id = CURRENT_SEASON.may_load(deps.storage)?.ok_or_else(|| ContractError::InvalidParameter {
+        parameter: "season".to_string(),
+        value: "no running season".to_string(),
+    })?;
+    
+    let snapshot = live_ranking(deps.storage, id, MAX_SEASON_SNAPSHOT)?;
+    SEASON_SNAPSHOTS.save(deps.storage, id, &snapshot)?;
+    
+    let mut season = SEASONS.load(deps.storage, id)?;
+    season.end_time = Some(env.block.time.seconds());
+    SEASONS.save(deps.storage, id, &season)?;
+    CURRENT_SEASON.remove(deps.storage);
+    
+    Ok(season)
+}
+
+/// 按当前积分排序的赛季排名，与主排行榜一致，同分并列
+fn live_ranking(storage: &dyn Storage, season_id: u64, limit: usize) -> StdResult<Vec<SeasonRankEntry>> {
+    let mut entries: Vec<SeasonRankEntry> = Vec::new();
+    for (key, position) in SEASON_RANKING
+        .sub_prefix(season_id)
+        .keys(storage, None, None, Order::Descending)
+        .take(limit)
+        .zip(1..)
+    {
+        let (points, user) = key?;
+        let points = Uint128::from(points);
+        let rank = match entries.last() {
+            Some(last) if last.points == points => last.rank,
+            _ => position,
+        };
+        entries.push(SeasonRankEntry { rank, user, points });
+    }
+    Ok(entries)
+}
+
+/// 积分变动计入当前赛季
+///
+/// 获得积分（转账除外）增加赛季积分，处罚扣减赛季积分。
+pub fn record_season_points(
+    storage: &mut dyn Storage,
+    user: &Addr,
+    points: Uint128,
+    reason: &PointsReason,
+    credit: bool,
+) -> Result<(), ContractError> {
+    let counted = if credit {
+        !matches!(reason, PointsReason::TransferIn | PointsReason::TransferFee)
+    } else {
+        *reason == PointsReason::Penalty
+    };
+    if points.is_zero() || !counted {
+        return Ok(());
+    }
+    
+    let Some(season_id) = CURRENT_SEASON.may_load(storage)? else {
+        return Ok(());
+    };
+    
+    let old_points = SEASON_POINTS.may_load(storage, (season_id, user))?;
+    if old_points.is_none() {
+        if !credit {
+            return Ok(());
+        }
+        let mut season = SEASONS.load(storage, season_id)?;
+        season.participants += 1;
+        SEASONS.save(storage, season_id, &season)?;
+    }
+    
+    let old_points = old_points.unwrap_or_default();
+    let new_points = if credit {
+        old_points + points
+    } else {
+        old_points.saturating_sub(points)
+    };
+    
+    SEASON_RANKING.remove(storage, (season_id, old_points.u128(), user));
+    SEASON_RANKING.save(storage, (season_id, new_points.u128(), user), &true)?;
+    SEASON_POINTS.save(storage, (season_id, user), &new_points)?;
+    
+    Ok(())
+}
+
+/// 查询赛季排行榜，已结束的赛季返回归档排名
+pub fn season_leaderboard(
+    deps: Deps,
+    season_id: u64,
+    limit: usize,
+) -> StdResult<(Season, Vec<SeasonRankEntry>)> {
+    let season = SEASONS.load(deps.storage, season_id)?;
+    let entries = match SEASON_SNAPSHOTS.may_load(deps.storage, season_id)? {
+        Some(mut snapshot) => {
+            snapshot.truncate(limit);
+            snapshot
+        }
+        None => live_ranking(deps.storage, season_id, limit)?,
+    };
+    Ok((season, entries))
+}
+
+/// 查询用户的赛季积分
+pub fn user_season_points(deps: Deps, season_id: u64, user: &Addr) -> StdResult<Uint128> {
+    Ok(SEASON_POINTS.may_load(deps.storage, (season_id, user))?.unwrap_or_default())
+}
@@ -1,5 +1,5 @@
-use cosmwasm_std::{Addr, Deps, DepsMut};
-use crate::state::{CONFIG, REENTRANCY_LOCK};
+use cosmwasm_std::{Addr, Deps, Storage};
+use crate::state::{UserStatus, CONFIG, REENTRANCY_LOCK, USER_MAP};
 use crate::error::ContractError;
 
 /// 检查管理员权限
@@ -9,7 +9,7 @@ pub fn check_admin_permission(
 ) -> Result<(), ContractError> {
     let config = CONFIG.load(deps.storage)?;
     
-    if sender != &config.admin {
+    if *sender != config.admin {
         return Err(ContractError::Unauthorized {
             message: "Admin permission required".to_string(),
         });
@@ -31,6 +31,62 @@ pub fn check_system_paused(
     Ok(())
 }
 
+/// 设置紧急暂停状态
+pub fn set_emergency_pause(
+    storage: &mut dyn Storage,
+    paused: bool,
+) -> Result<(), ContractError> {
+    let mut config = CONFIG.load(storage)?;
+    config.emergency_paused = paused;
+    CONFIG.save(storage, &config)?;
+    Ok(())
+}
+
+/// 更换管理员
+pub fn set_admin(
+    storage: &mut dyn Storage,
+    admin: Addr,
+) -> Result<(), ContractError> {
+    let mut config = CONFIG.load(storage)?;
+    config.admin = admin;
+    CONFIG.save(storage, &config)?;
+    Ok(())
+}
+
+/// 检查用户是否处于正常状态
+pub fn check_user_active(
+    deps: Deps,
+    user: &Addr,
+) -> Result<(), ContractError> {
+    let user_info = USER_MAP.may_load(deps.storage, user)?
+        .ok_or_else(|| ContractError::UserNotRegistered { user: user.to_string() })?;
+    
+    if user_info.status != UserStatus::Active {
+        return Err(ContractError::InvalidUserStatus {
+            user: user.to_string(),
+            status: format!("{:?}", user_info.status),
+        });
+    }
+    
+    Ok(())
+}
+
+/// 设置用户状态（冻结/解冻）
+pub fn set_user_status(
+    storage: &mut dyn Storage,
+    user: &Addr,
+    status: UserStatus,
+) -> Result<(), ContractError> {
+    let mut user_info = USER_MAP.may_load(storage, user)?
+        .ok_or_else(|| ContractError::UserNotRegistered { user: user.to_string() })?;
+    crate::team::update_team_activity(storage, user, &user_info.status, &status)?;
+    crate::stats::record_status_change(storage, &user_info.status, &status)?;
+    user_info.status = status;
+    USER_MAP.save(storage, user, &user_info)?;
+    crate::leaderboard::sync_member(storage, user, &user_info)?;
+    Ok(())
+}
+
 /// 检查重入锁
 pub fn check_reentrancy_lock(
     deps: Deps,
@@ -44,10 +100,10 @@ pub fn check_reentrancy_lock(
 
 /// 设置重入锁
 pub fn set_reentrancy_lock(
-    deps: DepsMut,
+    storage: &mut dyn Storage,
     locked: bool,
 ) -> Result<(), ContractError> {
-    REENTRANCY_LOCK.save(deps.storage, &locked)?;
+    REENTRANCY_LOCK.save(storage, &locked)?;
     Ok(())
 }
 
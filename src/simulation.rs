@@ -0,0 +1,198 @@
+use std::cmp::Ordering;
+use std::collections::BTreeMap;
+use std::iter::Peekable;
+use std::ops::Bound;
+use cosmwasm_std::{Addr, Deps, DepsMut, Env, MessageInfo, Order, Record, Storage};
+use crate::error::ContractError;
+use crate::msg::{AllocationShare, ReferralLimitStatus, SimulateRegisterResponse, UplinePayout};
+use crate::state::{CONFIG, PointsReason, USER_MAP};
+
+/// 缓冲层的记录，值为 None 表示已删除
+type OverlayIter<'b> = Box<dyn Iterator<Item = (&'b Vec<u8>, &'b Option<Vec<u8>>)> + 'b>;
+
+/// 只读存储之上的写缓冲层，写入仅保存在内存中，查询结束即丢弃
+pub struct SimulationStorage<'a> {
+    base: &'a dyn Storage,
+    /// None 表示已删除
+    overlay: BTreeMap<Vec<u8>, Option<Vec<u8>>>,
+}
+
+impl<'a> SimulationStorage<'a> {
+    pub fn new(base: &'a dyn Storage) -> Self {
+        Self {
+            base,
+            overlay: BTreeMap::new(),
+        }
+    }
+}
+
+impl Storage for SimulationStorage<'_> {
+    fn get(&self, key: &[u8]) -> Option<Vec<u8>> {
+        match self.overlay.get(key) {
+            Some(value) => value.clone(),
+            None => self.base.get(key),
+        }
+    }
+    
+    fn range<'b>(
+        &'b self,
+        start: Option<&[u8]>,
+        end: Option<&[u8]>,
+        order: Order,
+    ) -> Box<dyn Iterator<Item = Record> + 'b> {
+        let lower = start.map_or(Bound::Unbounded, |start| Bound::Included(start.to_vec()));
+        let upper = end.map_or(Bound::Unbounded, |end| Bound::Excluded(end.to_vec()));
+        let overlay = self.overlay.range((lower, upper));
+        let overlay: OverlayIter<'b> = match order {
+            Order::Ascending => Box::new(overlay),
+            Order::Descending => Box::new(overlay.rev()),
+        };
+        Box::new(MergedRange {
+            base: self.base.range(start, end, order).peekable(),
+            overlay: overlay.peekable(),
+            order,
+        })
+    }
+    
+    fn set(&mut self, key: &[u8], value: &[u8]) {
+        self.overlay.insert(key.to_vec(), Some(value.to_vec()));
+    }
+    
+    fn remove(&mut self, key: &[u8]) {
+        self.overlay.insert(key.to_vec(), None);
+    }
+}
+
+/// 按序逐条合并底层与缓冲层的记录，同一键以缓冲层为准
+struct MergedRange<'b> {
+    base: Peekable<Box<dyn Iterator<Item = Record> + 'b>>,
+    overlay: Peekable<OverlayIter<'b>>,
+    order: Order,
+}
+
+impl Iterator for MergedRange<'_> {
+    type Item = Record;
+    
+    fn next(&mut self) -> Option<Record> {
+        loop {
+            let from_overlay = match (self.base.peek(), self.overlay.peek()) {
+                (None, None) => return None,
+                (Some(_), None) => false,
+                (None, Some(_)) => true,
+                (Some((base_key, _)), Some((overlay_key, _))) => {
+                    let ordering = base_key.as_slice().cmp(overlay_key.as_slice());
+                    let ordering = match self.order {
+                        Order::Ascending => ordering,
+                        Order::Descending => ordering.reverse(),
+                    };
+                    match ordering {
+                        Ordering::Less => false,
+                        Ordering::Greater => true,
+                        Ordering::Equal => {
+                            self.base.next();
+                            true
+                        }
+                    }
+                }
+            };
+            
+            if !from_overlay {
+                return self.base.next();
+            }
+            // 已删除的键跳过
+            if let Some((key, Some(value))) = self.overlay.next() {
+                return Some((key.clone(), value.clone()));
+            }
+        }
+    }
+}
+
+/// 模拟注册：在缓冲层上执行与 Register 相同的流程，不写入链上存储
+pub fn simulate_register(
+    deps: Deps,
+    env: &Env,
+    user: &Addr,
+    referrer: Option<Addr>,
+) -> Result<SimulateRegisterResponse, ContractError> {
+    let mut storage = SimulationStorage::new(deps.storage);
+    let mut sim_deps = DepsMut {
+        storage: &mut storage,
+        api: deps.api,
+        querier: deps.querier,
+    };
+    
+    // 推荐人当前的冷却与每日上限状态（仅约束补绑推荐人），无论注册能否成功都返回
+    let referral_limits = match referrer {
+        Some(ref referrer) if USER_MAP.has(deps.storage, referrer) => {
+            let config = CONFIG.load(deps.storage)?;
+            let (cooldown_remaining, daily) = crate::referral::referral_limit_status(deps, env, referrer)?;
+            Some(ReferralLimitStatus {
+                cooldown_remaining,
+                daily_referrals: daily.count,
+                max_daily_referrals: config.max_daily_referrals,
+            })
+        }
+        _ => None,
+    };
+    
+    let info = MessageInfo {
+        sender: user.clone(),
+        funds: vec![],
+    };
+    let result = crate::execute::execute_register(
+        sim_deps.branch(),
+        env.clone(),
+        info,
+        referrer.map(|referrer| referrer.to_string()),
+        None,
+    );
+    if let Err(err) = result {
+        return Ok(SimulateRegisterResponse {
+            success: false,
+            error: Some(err.to_string()),
+            chain_depth: 0,
+            referral_limits,
+            upline_payouts: vec![],
+        });
+    }
+    
+    let chain = crate::referral::get_referral_chain(sim_deps.as_ref(), user, None)?;
+    
+    // 注册过程中各上级实际入账的积分，与执行前的余额比较
+    let mut upline_payouts = Vec::new();
+    for (depth, upline) in chain.iter().enumerate() {
+        let before = USER_MAP.load(deps.storage, upline)?.reward_points;
+        let after = USER_MAP.load(sim_deps.storage, upline)?.reward_points;
+        if after > before {
+            upline_payouts.push(UplinePayout {
+                user: upline.clone(),
+                depth: depth as u32 + 1,
+                points: after - before,
+            });
+        }
+    }
+    
+    Ok(SimulateRegisterResponse {
+        success: true,
+        error: None,
+        chain_depth: chain.len() as u32,
+        referral_limits,
+        upline_payouts,
+    })
+}
+
+/// 在缓冲层上依次对各份额应用获得上限与预算，得到实际可发放的积分
+///
+/// 与真实发放的顺序一致，前面份额占用的预算会影响后面的份额；拒绝策略下返回相同的错误。
+pub fn apply_caps_to_shares(
+    deps: Deps,
+    env: &Env,
+    reason: &PointsReason,
+    shares: &mut [AllocationShare],
+) -> Result<(), ContractError> {
+    let mut storage = SimulationStorage::new(deps.storage);
+    for share in shares.iter_mut() {
+        share.points = crate::caps::apply_earning_caps(&mut storage, env, &share.user, share.points, reason)?;
+    }
+    Ok(())
+}
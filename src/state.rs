@@ -1,5 +1,5 @@
-use cosmwasm_std::{Addr, Uint128, Decimal};
-use cw_storage_plus::{Item, Map};
+use cosmwasm_std::{Addr, Binary, Uint128, Decimal};
+use cw_storage_plus::{Item, Map, SnapshotItem, SnapshotMap, Strategy};
 use serde::{Deserialize, Serialize};
 use schemars::JsonSchema;
 use std::str::FromStr;
@@ -14,11 +14,12 @@ pub struct UserInfo {
     pub last_active_at: u64,
     pub user_level: UserLevel,
     pub referral_stats: ReferralStats,
+    /// 旧版本保存的积分流水，迁移或下次积分变动时迁入流水索引后清空；请通过 GetPointsHistory 查询
     pub points_history: Vec<PointsRecord>,
     pub status: UserStatus,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum UserLevel {
     Bronze,
@@ -63,6 +64,9 @@ pub struct PointsRecord {
     pub timestamp: u64,
     pub related_user: Option<Addr>,
     pub event_id: Option<String>,
+    /// 发放时生效的活动
+    #[serde(default)]
+    pub campaign_id: Option<u64>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
@@ -73,14 +77,19 @@ pub enum PointsReason {
     ActivityBonus,
     Penalty,
     ManualAdjustment,
+    TransferOut,
+    TransferIn,
+    TransferFee,
+    Distribution,
 }
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Hash, JsonSchema)]
 #[serde(rename_all = "snake_case")]
 pub enum UserStatus {
     Active,
     Suspended,
     Banned,
+    Closed,
 }
 
 // 系统配置
@@ -116,11 +125,339 @@ pub struct ActivityRule {
     pub cooldown: u64,
 }
 
+// 积分转账配置
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct TransferConfig {
+    pub enabled: bool,
+    pub fee_rate: Decimal,
+    /// 手续费接收方（需为已注册用户），为空时手续费销毁
+    pub fee_recipient: Option<Addr>,
+    pub max_daily_amount: Option<Uint128>,
+    pub max_daily_count: Option<u32>,
+}
+
+// 用户当日转账用量
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct DailyTransferUsage {
+    pub day: u64,
+    pub amount: Uint128,
+    pub count: u32,
+}
+
+// 钩子事件类型
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum HookEventType {
+    UserRegistered,
+    ReferralEstablished,
+    PointsChanged,
+}
+
+// 受保护的外部调用（等待 reply）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct PendingCall {
+    pub compensation: Option<Compensation>,
+    /// 钩子通知：失败时只记录，不回滚
+    #[serde(default)]
+    pub hook: Option<Addr>,
+}
+
+// 钩子调用失败记录
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct HookFailure {
+    pub count: u32,
+    pub last_error: String,
+    pub last_failed_at: u64,
+}
+
+// 外部调用失败时的积分补偿
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Compensation {
+    pub user: Addr,
+    pub points: Uint128,
+    pub reason: PointsReason,
+}
+
+// 推荐码
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReferralCode {
+    pub code: String,
+    pub owner: Addr,
+    pub max_uses: Option<u32>,
+    pub uses: u32,
+    pub expires_at: Option<u64>,
+    pub created_at: u64,
+    pub last_used_at: Option<u64>,
+}
+
+// 更正推荐人时对历史推荐奖励的处理方式
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ReferralRewardAction {
+    Keep,
+    ClawBack,
+    Transfer,
+}
+
+// 补绑推荐人配置
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct BindReferrerConfig {
+    /// 注册后允许补绑的时长（秒），0 表示关闭
+    pub window: u64,
+    /// 补绑后是否按推荐规则向上级发放奖励
+    pub trigger_payout: bool,
+}
+
+// 注销用户的直接下级处理方式
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum DownlinePolicy {
+    /// 挂到被注销用户的上级名下
+    #[default]
+    ReparentToGrandparent,
+    /// 保留在已注销节点下
+    KeepTombstoned,
+}
+
+// 注销用户的剩余积分处理方式
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum PointsSettlement {
+    /// 按提取结算
+    #[default]
+    Settle,
+    /// 直接作废
+    Forfeit,
+}
+
+// 用户注销配置
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct DeactivationConfig {
+    pub downline_policy: DownlinePolicy,
+    pub points_settlement: PointsSettlement,
+}
+
+// 团队聚合数据，按层级记录（第 i 项为第 i+1 层）
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct TeamStats {
+    pub depth_counts: Vec<u32>,
+    pub active_counts: Vec<u32>,
+    /// 成员在团队内期间累计获得的积分
+    pub downline_points: Uint128,
+}
+
+// 超出上限时的处理策略
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum CapPolicy {
+    /// 截断到剩余额度
+    #[default]
+    Truncate,
+    /// 拒绝整笔发放
+    Reject,
+}
+
+// 按积分原因配置的获得上限
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReasonCap {
+    pub reason: PointsReason,
+    pub user_daily: Option<Uint128>,
+    /// 自该原因配置上限起累计的获得量上限，配置前获得的积分不计入
+    pub user_lifetime: Option<Uint128>,
+    /// 该原因每周期的全局预算
+    pub global_per_period: Option<Uint128>,
+}
+
+// 积分获得上限与预算配置
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct EarningCapsConfig {
+    pub policy: CapPolicy,
+    /// 全局预算周期（秒）
+    pub period: u64,
+    /// 所有原因合计的每周期全局预算
+    pub global_budget: Option<Uint128>,
+    pub caps: Vec<ReasonCap>,
+}
+
+// 用户按原因的积分获得量
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct EarningUsage {
+    pub day: u64,
+    pub daily: Uint128,
+    pub lifetime: Uint128,
+}
+
+// 预算周期用量
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct PeriodUsage {
+    pub period: u64,
+    pub amount: Uint128,
+}
+
+// 活动期间覆盖的推荐费率，未设置的层级沿用 PointsRules
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct RateOverrides {
+    pub direct_referral_rate: Option<Decimal>,
+    pub level_2_rate: Option<Decimal>,
+    pub level_3_rate: Option<Decimal>,
+}
+
+// 限时积分活动
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Campaign {
+    pub id: u64,
+    pub name: String,
+    pub start_time: u64,
+    pub end_time: u64,
+    /// 活动期间可发放的积分总量
+    pub budget: Option<Uint128>,
+    pub spent: Uint128,
+    pub multiplier: Decimal,
+    pub rate_overrides: Option<RateOverrides>,
+    pub eligible_reasons: Vec<PointsReason>,
+    pub cancelled: bool,
+    pub created_at: u64,
+}
+
+// 赛季
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Season {
+    pub id: u64,
+    pub name: String,
+    pub start_time: u64,
+    pub end_time: Option<u64>,
+    pub participants: u32,
+}
+
+// 默克尔分发
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct Distribution {
+    pub id: u64,
+    pub merkle_root: Binary,
+    pub total: Uint128,
+    pub claimed: Uint128,
+    pub expires_at: u64,
+    pub created_at: u64,
+    /// 过期后回收的未领取积分
+    pub reclaimed: Option<Uint128>,
+}
+
+// 排行榜成员的筛选属性
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RankingMember {
+    pub level: UserLevel,
+    pub status: UserStatus,
+}
+
+// 排行榜按筛选属性分组的人数
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct RankingGroup {
+    pub member: RankingMember,
+    pub count: u32,
+}
+
+// 全局统计计数
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct GlobalStats {
+    pub total_users: u32,
+    /// 当前有效的推荐关系数
+    pub total_referrals: u32,
+}
+
+// 按积分原因累计的数量
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema)]
+pub struct ReasonAmount {
+    pub reason: PointsReason,
+    pub amount: Uint128,
+}
+
+// 推荐人当日推荐次数
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, JsonSchema, Default)]
+pub struct DailyReferralCount {
+    pub day: u64,
+    pub count: u32,
+}
+
 // 存储定义
 pub const CONFIG: Item<SystemConfig> = Item::new("config");
 pub const POINTS_RULES: Item<PointsRules> = Item::new("points_rules");
 pub const USER_MAP: Map<&Addr, UserInfo> = Map::new("user_map");
 pub const REFERRAL_CHAIN: Map<&Addr, Addr> = Map::new("referral_chain");
-pub const POINTS_LEADERBOARD: Map<u128, Addr> = Map::new("points_leaderboard");
-pub const LEVEL_STATS: Map<UserLevel, u32> = Map::new("level_stats");
-pub const REENTRANCY_LOCK: Item<bool> = Item::new("reentrancy_lock");
\ No newline at end of file
+pub const LEVEL_STATS: Map<&str, u32> = Map::new("level_stats");
+pub const REENTRANCY_LOCK: Item<bool> = Item::new("reentrancy_lock");
+pub const TRANSFER_CONFIG: Item<TransferConfig> = Item::new("transfer_config");
+pub const DAILY_TRANSFERS: Map<&Addr, DailyTransferUsage> = Map::new("daily_transfers");
+pub const HOOKS: Map<&Addr, Vec<HookEventType>> = Map::new("hooks");
+pub const PENDING_CALLS: Map<u64, PendingCall> = Map::new("pending_calls");
+pub const NEXT_CALL_ID: Item<u64> = Item::new("next_call_id");
+pub const VOUCHER_KEYS: Map<&Addr, Binary> = Map::new("voucher_keys");
+pub const USED_VOUCHERS: Map<(&Addr, u64), bool> = Map::new("used_vouchers");
+pub const REFERRAL_CODES: Map<&str, ReferralCode> = Map::new("referral_codes");
+pub const USER_CODES: Map<(&Addr, &str), bool> = Map::new("user_codes");
+pub const BIND_REFERRER_CONFIG: Item<BindReferrerConfig> = Item::new("bind_referrer_config");
+pub const DAILY_REFERRALS: Map<&Addr, DailyReferralCount> = Map::new("daily_referrals");
+pub const DEACTIVATION_CONFIG: Item<DeactivationConfig> = Item::new("deactivation_config");
+pub const TEAM_STATS: Map<&Addr, TeamStats> = Map::new("team_stats");
+pub const EARNING_CAPS: Item<EarningCapsConfig> = Item::new("earning_caps");
+pub const USER_EARNINGS: Map<(&Addr, &str), EarningUsage> = Map::new("user_earnings");
+pub const REASON_BUDGET_USAGE: Map<&str, PeriodUsage> = Map::new("reason_budget_usage");
+pub const GLOBAL_BUDGET_USAGE: Item<PeriodUsage> = Item::new("global_budget_usage");
+pub const CAMPAIGNS: Map<u64, Campaign> = Map::new("campaigns");
+pub const LIVE_CAMPAIGNS: Map<u64, bool> = Map::new("live_campaigns");
+pub const NEXT_CAMPAIGN_ID: Item<u64> = Item::new("next_campaign_id");
+pub const SEASONS: Map<u64, Season> = Map::new("seasons");
+pub const CURRENT_SEASON: Item<u64> = Item::new("current_season");
+pub const NEXT_SEASON_ID: Item<u64> = Item::new("next_season_id");
+pub const SEASON_POINTS: Map<(u64, &Addr), Uint128> = Map::new("season_points");
+pub const SEASON_RANKING: Map<(u64, u128, &Addr), bool> = Map::new("season_ranking");
+pub const SEASON_SNAPSHOTS: Map<u64, Vec<crate::msg::SeasonRankEntry>> = Map::new("season_snapshots");
+pub const POINTS_SNAPSHOTS: SnapshotMap<&Addr, Uint128> = SnapshotMap::new(
+    "points_snapshots",
+    "points_snapshots__checkpoints",
+    "points_snapshots__changelog",
+    Strategy::EveryBlock,
+);
+pub const TOTAL_POINTS: SnapshotItem<Uint128> = SnapshotItem::new(
+    "total_points",
+    "total_points__checkpoints",
+    "total_points__changelog",
+    Strategy::EveryBlock,
+);
+
+// 默克尔分发
+pub const DISTRIBUTIONS: Map<u64, Distribution> = Map::new("distributions");
+pub const NEXT_DISTRIBUTION_ID: Item<u64> = Item::new("next_distribution_id");
+pub const DISTRIBUTION_CLAIMS: Map<(u64, &Addr), Uint128> = Map::new("distribution_claims");
+
+// 排行榜：(分值, 用户) -> 筛选属性，分值为 0 的用户不上榜
+pub const POINTS_RANKING: Map<(u128, &Addr), RankingMember> = Map::new("points_ranking");
+pub const REFERRAL_RANKING: Map<(u128, &Addr), RankingMember> = Map::new("referral_ranking");
+pub const RANKING_GROUPS: Map<(&str, &str), RankingGroup> = Map::new("ranking_groups");
+// 排行榜按筛选属性分组的成员索引：("排行榜:等级:状态", 分值, 用户)
+pub const GROUP_RANKING: Map<(&str, u128, &Addr), bool> = Map::new("group_ranking");
+
+// 全局统计
+pub const STATUS_STATS: Map<&str, u32> = Map::new("status_stats");
+pub const GLOBAL_STATS: Item<GlobalStats> = Item::new("global_stats");
+
+// 积分供应流水：(类别, 原因) -> 累计数量
+pub const SUPPLY_FLOWS: Map<(&str, &str), ReasonAmount> = Map::new("supply_flows");
+
+// 积分流水：按用户顺序号存储，并按事件、关联用户、原因的每种组合建立索引及计数
+// 索引键为 (用户, 筛选键, (时间戳, 顺序号))
+pub const POINTS_RECORDS: Map<(&Addr, u64), PointsRecord> = Map::new("points_records");
+pub const NEXT_RECORD_SEQ: Map<&Addr, u64> = Map::new("next_record_seq");
+pub const HISTORY_INDEX: Map<(&Addr, &str, (u64, u64)), bool> = Map::new("history_index");
+pub const HISTORY_COUNTS: Map<(&Addr, &str), u32> = Map::new("history_counts");
+
+// 推荐人因某下级获得、尚未追回的推荐奖励：(推荐人, 下级) -> 积分
+pub const OUTSTANDING_REFERRAL_REWARDS: Map<(&Addr, &Addr), Uint128> = Map::new("outstanding_referral_rewards");
+
+// 钩子失败记录
+pub const HOOK_FAILURES: Map<&Addr, HookFailure> = Map::new("hook_failures");
+
+// 下级索引：(上级, 层级, 下级)，层级不超过 max_referral_depth
+pub const DOWNLINE: Map<(&Addr, u32, &Addr), bool> = Map::new("downline");
+
+// 导入时保留的最低等级，按推荐人数重算等级时不低于该值
+pub const LEVEL_FLOORS: Map<&Addr, UserLevel> = Map::new("level_floors");
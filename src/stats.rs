@@ -0,0 +1,109 @@
+use std::collections::HashMap;
+use cosmwasm_std::{Deps, Order, OverflowError, OverflowOperation, StdError, StdResult, Storage};
+use cw_storage_plus::Map;
+use crate::msg::LevelStatsResponse;
+use crate::state::{
+    GlobalStats, UserInfo, UserLevel, UserStatus, GLOBAL_STATS, LEVEL_STATS, REFERRAL_CHAIN, STATUS_STATS, USER_MAP,
+};
+
+const LEVELS: [UserLevel; 4] = [UserLevel::Bronze, UserLevel::Silver, UserLevel::Gold, UserLevel::Platinum];
+const STATUSES: [UserStatus; 4] = [UserStatus::Active, UserStatus::Suspended, UserStatus::Banned, UserStatus::Closed];
+
+/// 计数加一或减一，越界说明计数器与实际数据不一致
+fn step(count: u32, add: bool) -> StdResult<u32> {
+    let next = if add { count.checked_add(1) } else { count.checked_sub(1) };
+    next.ok_or_else(|| {
+        let operation = if add { OverflowOperation::Add } else { OverflowOperation::Sub };
+        StdError::overflow(OverflowError::new(operation))
+    })
+}
+
+fn adjust(storage: &mut dyn Storage, counters: &Map<&str, u32>, key: &str, add: bool) -> StdResult<()> {
+    let count = counters.may_load(storage, key)?.unwrap_or_default();
+    counters.save(storage, key, &step(count, add)?)
+}
+
+/// 新用户计入总数及等级、状态分布
+pub fn record_user_added(storage: &mut dyn Storage, user_info: &UserInfo) -> StdResult<()> {
+    let mut stats = GLOBAL_STATS.may_load(storage)?.unwrap_or_default();
+    stats.total_users = step(stats.total_users, true)?;
+    GLOBAL_STATS.save(storage, &stats)?;
+    
+    adjust(storage, &LEVEL_STATS, &format!("{:?}", user_info.user_level), true)?;
+    adjust(storage, &STATUS_STATS, &format!("{:?}", user_info.status), true)
+}
+
+/// 用户等级变化
+pub fn record_level_change(storage: &mut dyn Storage, old: &UserLevel, new: &UserLevel) -> StdResult<()> {
+    if old == new {
+        return Ok(());
+    }
+    adjust(storage, &LEVEL_STATS, &format!("{:?}", old), false)?;
+    adjust(storage, &LEVEL_STATS, &format!("{:?}", new), true)
+}
+
+/// 用户状态变化
+pub fn record_status_change(storage: &mut dyn Storage, old: &UserStatus, new: &UserStatus) -> StdResult<()> {
+    if old == new {
+        return Ok(());
+    }
+    adjust(storage, &STATUS_STATS, &format!("{:?}", old), false)?;
+    adjust(storage, &STATUS_STATS, &format!("{:?}", new), true)
+}
+
+/// 推荐关系建立或解除
+pub fn record_referral(storage: &mut dyn Storage, added: bool) -> StdResult<()> {
+    let mut stats = GLOBAL_STATS.may_load(storage)?.unwrap_or_default();
+    stats.total_referrals = step(stats.total_referrals, added)?;
+    GLOBAL_STATS.save(storage, &stats)
+}
+
+/// 按现有用户重建全部统计计数器，用于迁移
+pub fn rebuild_stats(storage: &mut dyn Storage) -> StdResult<GlobalStats> {
+    let mut level_counts: HashMap<String, u32> = HashMap::new();
+    let mut status_counts: HashMap<String, u32> = HashMap::new();
+    let mut stats = GlobalStats::default();
+    for item in USER_MAP.range(storage, None, None, Order::Ascending) {
+        let (_, user_info) = item?;
+        stats.total_users += 1;
+        *level_counts.entry(format!("{:?}", user_info.user_level)).or_default() += 1;
+        *status_counts.entry(format!("{:?}", user_info.status)).or_default() += 1;
+    }
+    stats.total_referrals = REFERRAL_CHAIN.keys(storage, None, None, Order::Ascending).count() as u32;
+    
+    for level in LEVELS {
+        let key = format!("{:?}", level);
+        LEVEL_STATS.save(storage, &key, &level_counts.get(&key).copied().unwrap_or_default())?;
+    }
+    for status in STATUSES {
+        let key = format!("{:?}", status);
+        STATUS_STATS.save(storage, &key, &status_counts.get(&key).copied().unwrap_or_default())?;
+    }
+    GLOBAL_STATS.save(storage, &stats)?;
+    Ok(stats)
+}
+
+/// 读取统计计数器
+pub fn level_stats(deps: Deps) -> StdResult<LevelStatsResponse> {
+    let mut stats = HashMap::new();
+    for level in LEVELS {
+        if let Some(count) = LEVEL_STATS.may_load(deps.storage, &format!("{:?}", level))?.filter(|count| *count > 0) {
+            stats.insert(level, count);
+        }
+    }
+    
+    let mut status_stats = HashMap::new();
+    for status in STATUSES {
+        if let Some(count) = STATUS_STATS.may_load(deps.storage, &format!("{:?}", status))?.filter(|count| *count > 0) {
+            status_stats.insert(status, count);
+        }
+    }
+    
+    let global = GLOBAL_STATS.may_load(deps.storage)?.unwrap_or_default();
+    Ok(LevelStatsResponse {
+        stats,
+        total_users: global.total_users,
+        status_stats,
+        total_referrals: global.total_referrals,
+    })
+}
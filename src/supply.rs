@@ -0,0 +1,113 @@
+use cosmwasm_std::{Deps, Order, StdResult, Storage, Uint128};
+use crate::msg::SupplyResponse;
+use crate::state::{PointsReason, ReasonAmount, SUPPLY_FLOWS, TOTAL_POINTS};
+
+/// 积分流入流出的类别，用户间转账单独计入，不算作发放或扣除
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SupplyFlow {
+    Issued,
+    Withdrawn,
+    Deducted,
+    TransferredIn,
+    TransferredOut,
+    /// 积分衰减，尚无衰减机制时为 0
+    Decayed,
+    /// 积分过期，尚无过期机制时为 0
+    Expired,
+}
+
+impl SupplyFlow {
+    fn key(self) -> &'static str {
+        match self {
+            SupplyFlow::Issued => "issued",
+            SupplyFlow::Withdrawn => "withdrawn",
+            SupplyFlow::Deducted => "deducted",
+            SupplyFlow::TransferredIn => "transferred_in",
+            SupplyFlow::TransferredOut => "transferred_out",
+            SupplyFlow::Decayed => "decayed",
+            SupplyFlow::Expired => "expired",
+        }
+    }
+    
+    /// 入账对应的类别
+    pub fn credit(reason: &PointsReason) -> Self {
+        match reason {
+            PointsReason::TransferIn | PointsReason::TransferFee => SupplyFlow::TransferredIn,
+            _ => SupplyFlow::Issued,
+        }
+    }
+    
+    /// 扣除对应的类别
+    pub fn debit(reason: &PointsReason) -> Self {
+        match reason {
+            PointsReason::TransferOut => SupplyFlow::TransferredOut,
+            _ => SupplyFlow::Deducted,
+        }
+    }
+}
+
+/// 累计一笔积分流动
+pub fn record_supply(
+    storage: &mut dyn Storage,
+    flow: SupplyFlow,
+    reason: &PointsReason,
+    amount: Uint128,
+) -> StdResult<()> {
+    if amount.is_zero() {
+        return Ok(());
+    }
+    
+    let reason_key = format!("{:?}", reason);
+    let mut entry = SUPPLY_FLOWS
+        .may_load(storage, (flow.key(), &reason_key))?
+        .unwrap_or_else(|| ReasonAmount {
+            reason: reason.clone(),
+            amount: Uint128::zero(),
+        });
+    entry.amount += amount;
+    SUPPLY_FLOWS.save(storage, (flow.key(), &reason_key), &entry)
+}
+
+fn flow_breakdown(deps: Deps, flow: SupplyFlow) -> StdResult<Vec<ReasonAmount>> {
+    SUPPLY_FLOWS
+        .prefix(flow.key())
+        .range(deps.storage, None, None, Order::Ascending)
+        .map(|item| item.map(|(_, entry)| entry))
+        .collect()
+}
+
+fn flow_total(breakdown: &[ReasonAmount]) -> Uint128 {
+    breakdown.iter().map(|entry| entry.amount).sum()
+}
+
+/// 汇总积分供应，并校验 发放 - 移除（提取、扣除、衰减、过期、转账销毁） == 余额总和
+pub fn get_supply(deps: Deps) -> StdResult<SupplyResponse> {
+    let issued_by_reason = flow_breakdown(deps, SupplyFlow::Issued)?;
+    let withdrawn = flow_total(&flow_breakdown(deps, SupplyFlow::Withdrawn)?);
+    let deducted_by_reason = flow_breakdown(deps, SupplyFlow::Deducted)?;
+    let transferred = flow_total(&flow_breakdown(deps, SupplyFlow::TransferredOut)?);
+    let received = flow_total(&flow_breakdown(deps, SupplyFlow::TransferredIn)?);
+    let decayed = flow_total(&flow_breakdown(deps, SupplyFlow::Decayed)?);
+    let expired = flow_total(&flow_breakdown(deps, SupplyFlow::Expired)?);
+    
+    let issued = flow_total(&issued_by_reason);
+    let deducted = flow_total(&deducted_by_reason);
+    // 转出未全部到账的部分为未配置金库时销毁的手续费
+    let burned = transferred.saturating_sub(received);
+    let removed = withdrawn + deducted + decayed + expired + burned;
+    let total_supply = TOTAL_POINTS.may_load(deps.storage)?.unwrap_or_default();
+    
+    Ok(SupplyResponse {
+        total_supply,
+        issued,
+        withdrawn,
+        deducted,
+        transferred,
+        burned,
+        decayed,
+        expired,
+        issued_by_reason,
+        deducted_by_reason,
+        balanced: received <= transferred && issued.checked_sub(removed).ok() == Some(total_supply),
+    })
+}
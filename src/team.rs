@@ -0,0 +1,162 @@
+use cosmwasm_std::{Addr, Deps, Order, StdResult, Storage, Uint128};
+use crate::error::ContractError;
+use crate::state::{PointsReason, TeamStats, UserStatus, CONFIG, DOWNLINE, REFERRAL_CHAIN, TEAM_STATS, USER_MAP};
+
+/// 推荐关系变动时随被推荐人一起移动的下级人数上限（max_referral_depth 以内），限制单笔写入量
+pub const MAX_MOVED_SUBTREE: usize = 500;
+
+/// 依次访问 max_referral_depth 以内的上级，回调参数为 (上级, 层级)
+fn for_each_ancestor(
+    storage: &mut dyn Storage,
+    start: &Addr,
+    mut visit: impl FnMut(&mut TeamStats, usize),
+) -> Result<(), ContractError> {
+    let max_depth = CONFIG.load(storage)?.max_referral_depth as usize;
+    let mut ancestor = Some(start.clone());
+    let mut depth = 1;
+    
+    while let Some(current) = ancestor {
+        if depth > max_depth {
+            break;
+        }
+        let mut stats = TEAM_STATS.may_load(storage, &current)?.unwrap_or_default();
+        stats.depth_counts.resize(stats.depth_counts.len().max(max_depth), 0);
+        stats.active_counts.resize(stats.active_counts.len().max(max_depth), 0);
+        visit(&mut stats, depth);
+        TEAM_STATS.save(storage, &current, &stats)?;
+        
+        ancestor = REFERRAL_CHAIN.may_load(storage, &current)?;
+        depth += 1;
+    }
+    
+    Ok(())
+}
+
+fn apply(value: &mut u32, delta: u32, add: bool) {
+    *value = if add { value.saturating_add(delta) } else { value.saturating_sub(delta) };
+}
+
+/// 将被推荐人及其下级写入（或移出）推荐人及其上级的下级索引，下级过多时拒绝
+fn update_downline_index(
+    storage: &mut dyn Storage,
+    referrer: &Addr,
+    referee: &Addr,
+    add: bool,
+) -> Result<(), ContractError> {
+    let max_depth = CONFIG.load(storage)?.max_referral_depth;
+    let mut subtree = vec![(0, referee.clone())];
+    for item in DOWNLINE.sub_prefix(referee).keys(storage, None, None, Order::Ascending).take(MAX_MOVED_SUBTREE + 1) {
+        subtree.push(item?);
+    }
+    if subtree.len() > MAX_MOVED_SUBTREE + 1 {
+        return Err(ContractError::LimitExceeded {
+            limit_type: "moved_subtree".to_string(),
+            limit_value: MAX_MOVED_SUBTREE as u32,
+            actual_value: (subtree.len() - 1) as u32,
+        });
+    }
+    
+    let mut ancestor = Some(referrer.clone());
+    let mut depth = 1;
+    while let Some(current) = ancestor {
+        if depth > max_depth {
+            break;
+        }
+        for (offset, member) in &subtree {
+            if depth + offset > max_depth {
+                break;
+            }
+            if add {
+                DOWNLINE.save(storage, (&current, depth + offset, member), &true)?;
+            } else {
+                DOWNLINE.remove(storage, (&current, depth + offset, member));
+            }
+        }
+        ancestor = REFERRAL_CHAIN.may_load(storage, &current)?;
+        depth += 1;
+    }
+    
+    Ok(())
+}
+
+/// 将被推荐人及其团队计入（或移出）推荐人及其上级的团队数据
+///
+/// 需在推荐链仍指向推荐人时调用。新注册用户尚未保存，视为活跃且无下级。
+pub fn update_team_membership(
+    storage: &mut dyn Storage,
+    referrer: &Addr,
+    referee: &Addr,
+    add: bool,
+) -> Result<(), ContractError> {
+    update_downline_index(storage, referrer, referee, add)?;
+    
+    let active = USER_MAP
+        .may_load(storage, referee)?
+        .is_none_or(|info| info.status == UserStatus::Active);
+    let subtree = TEAM_STATS.may_load(storage, referee)?.unwrap_or_default();
+    
+    for_each_ancestor(storage, referrer, |stats, depth| {
+        apply(&mut stats.depth_counts[depth - 1], 1, add);
+        if active {
+            apply(&mut stats.active_counts[depth - 1], 1, add);
+        }
+        
+        // 被推荐人的第 k 层即上级的第 depth + k 层
+        for (offset, count) in subtree.depth_counts.iter().enumerate() {
+            if let Some(value) = stats.depth_counts.get_mut(depth + offset) {
+                apply(value, *count, add);
+            }
+        }
+        for (offset, count) in subtree.active_counts.iter().enumerate() {
+            if let Some(value) = stats.active_counts.get_mut(depth + offset) {
+                apply(value, *count, add);
+            }
+        }
+    })
+}
+
+/// 用户活跃状态变化时更新上级的活跃人数
+pub fn update_team_activity(
+    storage: &mut dyn Storage,
+    user: &Addr,
+    old_status: &UserStatus,
+    new_status: &UserStatus,
+) -> Result<(), ContractError> {
+    let was_active = *old_status == UserStatus::Active;
+    let is_active = *new_status == UserStatus::Active;
+    if was_active == is_active {
+        return Ok(());
+    }
+    
+    let Some(referrer) = REFERRAL_CHAIN.may_load(storage, user)? else {
+        return Ok(());
+    };
+    for_each_ancestor(storage, &referrer, |stats, depth| {
+        apply(&mut stats.active_counts[depth - 1], 1, is_active);
+    })
+}
+
+/// 成员获得积分时累加到上级的团队积分
+pub fn record_team_points(
+    storage: &mut dyn Storage,
+    user: &Addr,
+    points: Uint128,
+    reason: &PointsReason,
+) -> Result<(), ContractError> {
+    // 转账只是积分流动，不计为获得
+    if points.is_zero() || matches!(reason, PointsReason::TransferIn | PointsReason::TransferFee) {
+        return Ok(());
+    }
+    
+    let Some(referrer) = REFERRAL_CHAIN.may_load(storage, user)? else {
+        return Ok(());
+    };
+    for_each_ancestor(storage, &referrer, |stats, _| {
+        stats.downline_points += points;
+    })
+}
+
+/// 查询团队聚合数据
+pub fn get_team_stats(deps: Deps, user: &Addr) -> StdResult<TeamStats> {
+    Ok(TEAM_STATS.may_load(deps.storage, user)?.unwrap_or_default())
+}
@@ -0,0 +1,197 @@
+use cosmwasm_std::{Addr, Uint128, Deps, DepsMut, Env, Response};
+use crate::error::ContractError;
+use crate::state::{
+    TransferConfig, DailyTransferUsage, PointsReason, TRANSFER_CONFIG, DAILY_TRANSFERS, USER_MAP,
+};
+use crate::points::{allocate_points_to_user, deduct_points_from_user};
+use crate::security::check_user_active;
+
+/// 每日秒数
+pub const SECONDS_PER_DAY: u64 = 86_400;
+
+/// 备注最大长度
+pub const MAX_MEMO_LENGTH: usize = 256;
+
+/// 加载转账配置（未配置时视为关闭）
+pub fn load_transfer_config(deps: Deps) -> Result<TransferConfig, ContractError> {
+    Ok(TRANSFER_CONFIG.may_load(deps.storage)?.unwrap_or_default())
+}
+
+/// 校验转账配置
+pub fn validate_transfer_config(
+    deps: Deps,
+    config: &TransferConfig,
+) -> Result<(), ContractError> {
+    if config.fee_rate > cosmwasm_std::Decimal::one() {
+        return Err(ContractError::InvalidParameter {
+            parameter: "fee_rate".to_string(),
+            value: config.fee_rate.to_string(),
+        });
+    }
+    
+    // 手续费接收方必须是已注册用户
+    if let Some(ref recipient) = config.fee_recipient {
+        if !USER_MAP.has(deps.storage, recipient) {
+            return Err(ContractError::UserNotRegistered {
+                user: recipient.to_string(),
+            });
+        }
+    }
+    
+    Ok(())
+}
+
+/// 查询用户当日转账用量
+pub fn get_daily_usage(
+    deps: Deps,
+    env: &Env,
+    user: &Addr,
+) -> Result<DailyTransferUsage, ContractError> {
+    let today = env.block.time.seconds() / SECONDS_PER_DAY;
+    
+    match DAILY_TRANSFERS.may_load(deps.storage, user)? {
+        Some(usage) if usage.day == today => Ok(usage),
+        _ => Ok(DailyTransferUsage {
+            day: today,
+            ..Default::default()
+        }),
+    }
+}
+
+/// 用户间转账积分
+pub fn transfer_points(
+    deps: &mut DepsMut,
+    env: &Env,
+    sender: &Addr,
+    recipient: &Addr,
+    amount: Uint128,
+    memo: Option<String>,
+) -> Result<Response, ContractError> {
+    let config = load_transfer_config(deps.as_ref())?;
+    
+    if !config.enabled {
+        return Err(ContractError::FeatureDisabled {
+            feature: "transfer".to_string(),
+        });
+    }
+    
+    if amount.is_zero() {
+        return Err(ContractError::InvalidParameter {
+            parameter: "amount".to_string(),
+            value: amount.to_string(),
+        });
+    }
+    
+    if sender == recipient {
+        return Err(ContractError::InvalidParameter {
+            parameter: "to".to_string(),
+            value: recipient.to_string(),
+        });
+    }
+    
+    if let Some(ref memo) = memo {
+        if memo.len() > MAX_MEMO_LENGTH {
+            return Err(ContractError::InvalidParameter {
+                parameter: "memo".to_string(),
+                value: format!("length {}", memo.len()),
+            });
+        }
+    }
+    
+    // 检查双方状态
+    check_user_active(deps.as_ref(), sender)?;
+    check_user_active(deps.as_ref(), recipient)?;
+    
+    // 检查每日限额
+    let mut usage = get_daily_usage(deps.as_ref(), env, sender)?;
+    usage.amount += amount;
+    usage.count += 1;
+    
+    if let Some(max_amount) = config.max_daily_amount {
+        if usage.amount > max_amount {
+            return Err(ContractError::AmountLimitExceeded {
+                limit_type: "daily_transfer_amount".to_string(),
+                limit_value: max_amount,
+                actual_value: usage.amount,
+            });
+        }
+    }
+    
+    if let Some(max_count) = config.max_daily_count {
+        if usage.count > max_count {
+            return Err(ContractError::LimitExceeded {
+                limit_type: "daily_transfer_count".to_string(),
+                limit_value: max_count,
+                actual_value: usage.count,
+            });
+        }
+    }
+    
+    DAILY_TRANSFERS.save(deps.storage, sender, &usage)?;
+    
+    // 计算手续费
+    let fee = amount.multiply_ratio(
+        config.fee_rate.atomics(),
+        Uint128::from(10_u128.pow(config.fee_rate.decimal_places())),
+    );
+    let received = amount - fee;
+    
+    // 扣除发送方积分，双方历史记录互相关联
+    let deduct_response = deduct_points_from_user(
+        deps,
+        env,
+        sender,
+        amount,
+        PointsReason::TransferOut,
+        Some(recipient.clone()),
+        None,
+    )?;
+    let mut hook_msgs = deduct_response.messages;
+    let mut events = deduct_response.events;
+    
+    if !received.is_zero() {
+        let received_response = allocate_points_to_user(
+            deps,
+            env,
+            recipient,
+            received,
+            PointsReason::TransferIn,
+            Some(sender.clone()),
+            None,
+        )?;
+        hook_msgs.extend(received_response.messages);
+        events.extend(received_response.events);
+    }
+    
+    // 手续费转入金库，未配置金库时直接销毁
+    let fee_destination = match config.fee_recipient {
+        Some(ref treasury) if !fee.is_zero() => {
+            let fee_response = allocate_points_to_user(
+                deps,
+                env,
+                treasury,
+                fee,
+                PointsReason::TransferFee,
+                Some(sender.clone()),
+                None,
+            )?;
+            hook_msgs.extend(fee_response.messages);
+            events.extend(fee_response.events);
+            treasury.to_string()
+        }
+        Some(ref treasury) => treasury.to_string(),
+        None => "burned".to_string(),
+    };
+    
+    Ok(Response::new()
+        .add_submessages(hook_msgs)
+        .add_events(events)
+        .add_attribute("action", "points_transferred")
+        .add_attribute("from", sender.to_string())
+        .add_attribute("to", recipient.to_string())
+        .add_attribute("amount", amount.to_string())
+        .add_attribute("fee", fee.to_string())
+        .add_attribute("fee_destination", fee_destination)
+        .add_attribute("received", received.to_string())
+        .add_attribute("memo", memo.unwrap_or_default()))
+}
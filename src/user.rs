@@ -1,6 +1,11 @@
-use cosmwasm_std::{Addr, Uint128, Deps, DepsMut, Env, Response, Decimal};
-use crate::state::{UserInfo, UserLevel, ReferralStats, UserStatus, USER_MAP, REFERRAL_CHAIN};
+use cosmwasm_std::{Addr, Uint128, Deps, DepsMut, Env, Response, Decimal, Storage, Timestamp};
+use crate::state::{UserInfo, UserLevel, ReferralStats, UserStatus, PointsReason, DownlinePolicy, PointsSettlement, CONFIG, DEACTIVATION_CONFIG, USER_MAP, REFERRAL_CHAIN, LEVEL_FLOORS};
 use crate::error::ContractError;
+use crate::msg::ImportedUser;
+use crate::events::RegistryEvent;
+
+/// 单次导入用户数量上限
+pub const MAX_IMPORT_BATCH: usize = 200;
 
 /// 创建新用户信息
 pub fn create_user_info(
@@ -27,6 +32,180 @@ pub fn create_user_info(
     }
 }
 
+/// 注册用户并建立推荐关系
+pub fn register_user(
+    mut deps: DepsMut,
+    env: &Env,
+    user: &Addr,
+    referrer: Option<Addr>,
+) -> Result<Response, ContractError> {
+    let config = CONFIG.load(deps.storage)?;
+    
+    // 检查系统是否启用
+    if !config.enabled {
+        return Err(ContractError::SystemPaused);
+    }
+    
+    // 检查用户是否已注册
+    if USER_MAP.has(deps.storage, user) {
+        return Err(ContractError::UserAlreadyRegistered {
+            user: user.to_string(),
+        });
+    }
+    
+    // 如果有推荐人，建立推荐关系（需在保存用户前完成校验）
+    let mut hook_msgs = Vec::new();
+    let mut events = Vec::new();
+    if let Some(ref ref_addr) = referrer {
+        // 检查推荐人是否存在
+        if !USER_MAP.has(deps.storage, ref_addr) {
+            return Err(ContractError::InvalidReferrer {
+                referrer: ref_addr.to_string(),
+            });
+        }
+        
+        let referral_response = crate::referral::establish_referral_relation(deps.branch(), env, ref_addr, user)?;
+        hook_msgs.extend(referral_response.messages);
+        events.extend(referral_response.events);
+    }
+    
+    // 保存用户信息
+    let user_info = create_user_info(user, referrer.clone(), env);
+    USER_MAP.save(deps.storage, user, &user_info)?;
+    crate::stats::record_user_added(deps.storage, &user_info)?;
+    
+    // 通知订阅合约
+    hook_msgs.extend(crate::hooks::prepare_hooks(deps.storage, &crate::hooks::RegistryHookMsg::UserRegistered {
+        user: user.to_string(),
+        referrer: referrer.as_ref().map(|addr| addr.to_string()),
+        registered_at: env.block.time.seconds(),
+    })?);
+    
+    events.insert(0, RegistryEvent::UserRegistered {
+        user: user.clone(),
+        referrer: referrer.clone(),
+        registered_at: env.block.time.seconds(),
+    }.into());
+    
+    Ok(Response::new()
+        .add_submessages(hook_msgs)
+        .add_events(events)
+        .add_attribute("action", "user_registered")
+        .add_attribute("user", user.to_string())
+        .add_attribute("referrer", referrer.map(|addr| addr.to_string()).unwrap_or_default())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
+/// 批量导入用户，推荐人须已存在或在同一批次中
+pub fn import_users(
+    mut deps: DepsMut,
+    env: &Env,
+    users: Vec<ImportedUser>,
+) -> Result<Response, ContractError> {
+    if users.len() > MAX_IMPORT_BATCH {
+        return Err(ContractError::LimitExceeded {
+            limit_type: "import_batch".to_string(),
+            limit_value: MAX_IMPORT_BATCH as u32,
+            actual_value: users.len() as u32,
+        });
+    }
+    
+    // 校验地址并排除重复
+    let mut batch = Vec::with_capacity(users.len());
+    let mut batch_addrs = std::collections::HashSet::new();
+    for imported in users {
+        let user = deps.api.addr_validate(&imported.address)?;
+        if USER_MAP.has(deps.storage, &user) || !batch_addrs.insert(user.clone()) {
+            return Err(ContractError::UserAlreadyRegistered {
+                user: user.to_string(),
+            });
+        }
+        let referrer = match imported.referrer {
+            Some(ref referrer) => Some(deps.api.addr_validate(referrer)?),
+            None => None,
+        };
+        batch.push((user, referrer, imported));
+    }
+    
+    // 推荐人必须已导入或在本批次中
+    for (user, referrer, _) in &batch {
+        if let Some(referrer) = referrer {
+            if referrer == user || (!batch_addrs.contains(referrer) && !USER_MAP.has(deps.storage, referrer)) {
+                return Err(ContractError::InvalidReferrer {
+                    referrer: referrer.to_string(),
+                });
+            }
+        }
+    }
+    
+    // 先写入用户，再建立推荐关系
+    let mut events = Vec::new();
+    for (user, referrer, imported) in &batch {
+        let mut user_info = create_user_info(user, referrer.clone(), env);
+        user_info.registered_at = imported.registered_at;
+        user_info.last_active_at = imported.registered_at;
+        USER_MAP.save(deps.storage, user, &user_info)?;
+        crate::stats::record_user_added(deps.storage, &user_info)?;
+        events.push(RegistryEvent::UserRegistered {
+            user: user.clone(),
+            referrer: referrer.clone(),
+            registered_at: imported.registered_at,
+        });
+    }
+    
+    for (user, referrer, imported) in &batch {
+        if let Some(referrer) = referrer {
+            let mut import_env = env.clone();
+            import_env.block.time = Timestamp::from_seconds(imported.registered_at);
+            add_referral_relation(deps.branch(), &import_env, referrer, user)?;
+            events.push(RegistryEvent::ReferralEstablished {
+                referrer: referrer.clone(),
+                referee: user.clone(),
+                timestamp: imported.registered_at,
+            });
+        }
+    }
+    
+    // 全部关系写入后检查循环
+    for (user, referrer, _) in &batch {
+        if let Some(referrer) = referrer {
+            crate::referral::check_circular_referral(deps.as_ref(), referrer, user)?;
+        }
+    }
+    
+    // 导入积分，原等级作为下限保留
+    let mut hook_msgs = Vec::new();
+    let mut response_events = Vec::new();
+    for (user, _, imported) in &batch {
+        if !imported.points.is_zero() {
+            let allocation_response = crate::points::credit_points_exact(
+                &mut deps,
+                env,
+                user,
+                imported.points,
+                PointsReason::ManualAdjustment,
+                None,
+                Some("import".to_string()),
+            )?;
+            hook_msgs.extend(allocation_response.messages);
+            response_events.extend(allocation_response.events);
+        }
+        
+        LEVEL_FLOORS.save(deps.storage, user, &imported.level)?;
+        let referral_count = USER_MAP.load(deps.storage, user)?.referral_stats.total_referrals;
+        let level_response = update_user_level(&mut deps, user, referral_count)?;
+        response_events.extend(level_response.events);
+    }
+    
+    Ok(Response::new()
+        .add_submessages(hook_msgs)
+        .add_events(events)
+        .add_events(response_events)
+        .add_attribute("action", "import_users")
+        .add_attribute("count", batch.len().to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
 /// 更新用户等级
 pub fn update_user_level(
     deps: &mut DepsMut,
@@ -36,14 +215,22 @@ pub fn update_user_level(
     let mut user_info = USER_MAP.load(deps.storage, user)?;
     let old_level = user_info.user_level.clone();
     
-    // 计算新等级
-    let new_level = UserLevel::from_referral_count(referral_count);
+    // 计算新等级，不低于导入时保留的等级
+    let floor = LEVEL_FLOORS.may_load(deps.storage, user)?.unwrap_or(UserLevel::Bronze);
+    let new_level = UserLevel::from_referral_count(referral_count).max(floor);
     
     if new_level != old_level {
+        crate::stats::record_level_change(deps.storage, &old_level, &new_level)?;
         user_info.user_level = new_level.clone();
         USER_MAP.save(deps.storage, user, &user_info)?;
+        crate::leaderboard::sync_member(deps.storage, user, &user_info)?;
         
         Ok(Response::new()
+            .add_event(RegistryEvent::LevelChanged {
+                user: user.clone(),
+                old_level: old_level.clone(),
+                new_level: new_level.clone(),
+            })
             .add_attribute("action", "level_up")
             .add_attribute("user", user.to_string())
             .add_attribute("old_level", format!("{:?}", old_level))
@@ -68,13 +255,165 @@ pub fn add_referral_relation(
     referrer_info.referral_stats.last_referral_time = Some(env.block.time.seconds());
     
     USER_MAP.save(deps.storage, referrer, &referrer_info)?;
+    update_referral_ranking(deps.storage, referrer, &referrer_info, true)?;
     
     // 建立推荐链映射
     REFERRAL_CHAIN.save(deps.storage, referee, referrer)?;
+    crate::stats::record_referral(deps.storage, true)?;
+    
+    // 更新各上级的团队数据
+    crate::team::update_team_membership(deps.storage, referrer, referee, true)?;
     
     Ok(())
 }
 
+/// 推荐人数变化后更新推荐排行榜
+fn update_referral_ranking(
+    storage: &mut dyn Storage,
+    referrer: &Addr,
+    referrer_info: &UserInfo,
+    added: bool,
+) -> Result<(), ContractError> {
+    let new_count = referrer_info.referral_stats.total_referrals as u128;
+    let old_count = if added { new_count - 1 } else { new_count + 1 };
+    crate::leaderboard::update_ranking(
+        storage,
+        crate::leaderboard::Ranking::Referrals,
+        referrer,
+        old_count,
+        new_count,
+        &crate::leaderboard::ranking_member(referrer_info),
+    )?;
+    Ok(())
+}
+
+/// 移除推荐关系
+pub fn remove_referral_relation(
+    deps: DepsMut,
+    referrer: &Addr,
+    referee: &Addr,
+) -> Result<(), ContractError> {
+    // 从推荐人的直接推荐列表中移除
+    let mut referrer_info = USER_MAP.load(deps.storage, referrer)?;
+    referrer_info.direct_referrals.retain(|addr| addr != referee);
+    referrer_info.referral_stats.total_referrals = referrer_info.referral_stats.total_referrals.saturating_sub(1);
+    referrer_info.referral_stats.active_referrals = referrer_info.referral_stats.active_referrals.saturating_sub(1);
+    
+    USER_MAP.save(deps.storage, referrer, &referrer_info)?;
+    update_referral_ranking(deps.storage, referrer, &referrer_info, false)?;
+    
+    // 更新各上级的团队数据
+    crate::team::update_team_membership(deps.storage, referrer, referee, false)?;
+    
+    // 删除推荐链映射
+    REFERRAL_CHAIN.remove(deps.storage, referee);
+    crate::stats::record_referral(deps.storage, false)?;
+    
+    Ok(())
+}
+
+/// 注销用户：结算或作废积分，并按配置处理直接下级
+pub fn deactivate_user(
+    mut deps: DepsMut,
+    env: &Env,
+    user: &Addr,
+) -> Result<Response, ContractError> {
+    let user_info = get_user_info(deps.as_ref(), user)?;
+    if user_info.status == UserStatus::Closed {
+        return Err(ContractError::InvalidUserStatus {
+            user: user.to_string(),
+            status: format!("{:?}", user_info.status),
+        });
+    }
+    
+    let deactivation_config = DEACTIVATION_CONFIG.may_load(deps.storage)?.unwrap_or_default();
+    let mut response = Response::new();
+    
+    // 处理剩余积分
+    let remaining_points = user_info.reward_points;
+    if !remaining_points.is_zero() {
+        let points_response = match deactivation_config.points_settlement {
+            PointsSettlement::Settle => {
+                crate::points::withdraw_points(deps.branch(), env, user, remaining_points)?
+            }
+            PointsSettlement::Forfeit => crate::points::deduct_points_from_user(
+                &mut deps,
+                env,
+                user,
+                remaining_points,
+                PointsReason::Penalty,
+                None,
+                Some("deactivation".to_string()),
+            )?,
+        };
+        response = response
+            .add_submessages(points_response.messages)
+            .add_events(points_response.events);
+    }
+    
+    let parent = REFERRAL_CHAIN.may_load(deps.storage, user)?;
+    match deactivation_config.downline_policy {
+        DownlinePolicy::ReparentToGrandparent => {
+            // 下级改挂到上级名下，注销节点从推荐树中摘除
+            for child in &user_info.direct_referrals {
+                remove_referral_relation(deps.branch(), user, child)?;
+                let mut child_info = USER_MAP.load(deps.storage, child)?;
+                if let Some(ref grandparent) = parent {
+                    // 改挂不应触发上级的推荐冷却
+                    let last_referral_time = USER_MAP.load(deps.storage, grandparent)?.referral_stats.last_referral_time;
+                    add_referral_relation(deps.branch(), env, grandparent, child)?;
+                    let mut grandparent_info = USER_MAP.load(deps.storage, grandparent)?;
+                    grandparent_info.referral_stats.last_referral_time = last_referral_time;
+                    USER_MAP.save(deps.storage, grandparent, &grandparent_info)?;
+                    response = response.add_event(RegistryEvent::ReferralEstablished {
+                        referrer: grandparent.clone(),
+                        referee: child.clone(),
+                        timestamp: env.block.time.seconds(),
+                    });
+                }
+                child_info.recommender = parent.clone();
+                USER_MAP.save(deps.storage, child, &child_info)?;
+            }
+            if let Some(ref parent) = parent {
+                remove_referral_relation(deps.branch(), parent, user)?;
+            }
+        }
+        DownlinePolicy::KeepTombstoned => {
+            // 保留节点，仅不再计为上级的活跃推荐
+            if let Some(ref parent) = parent {
+                let mut parent_info = USER_MAP.load(deps.storage, parent)?;
+                parent_info.referral_stats.active_referrals = parent_info.referral_stats.active_referrals.saturating_sub(1);
+                USER_MAP.save(deps.storage, parent, &parent_info)?;
+            }
+        }
+    }
+    
+    // 上级按推荐人数重新计算等级
+    if let Some(ref parent) = parent {
+        let total_referrals = USER_MAP.load(deps.storage, parent)?.referral_stats.total_referrals;
+        let level_response = update_user_level(&mut deps, parent, total_referrals)?;
+        response = response
+            .add_events(level_response.events)
+            .add_attributes(level_response.attributes);
+    }
+    
+    crate::security::set_user_status(deps.storage, user, UserStatus::Closed)?;
+    let mut user_info = USER_MAP.load(deps.storage, user)?;
+    if deactivation_config.downline_policy == DownlinePolicy::ReparentToGrandparent {
+        user_info.direct_referrals.clear();
+        user_info.recommender = None;
+    }
+    USER_MAP.save(deps.storage, user, &user_info)?;
+    
+    Ok(response
+        .add_attribute("action", "user_deactivated")
+        .add_attribute("user", user.to_string())
+        .add_attribute("points_settlement", format!("{:?}", deactivation_config.points_settlement))
+        .add_attribute("downline_policy", format!("{:?}", deactivation_config.downline_policy))
+        .add_attribute("remaining_points", remaining_points.to_string())
+        .add_attribute("timestamp", env.block.time.seconds().to_string()))
+}
+
 /// 检查用户是否存在
 pub fn user_exists(deps: Deps, user: &Addr) -> bool {
     USER_MAP.has(deps.storage, user)
@@ -0,0 +1,159 @@
+use cosmwasm_schema::cw_serde;
+use cosmwasm_std::{to_json_vec, Addr, Binary, Deps, DepsMut, Env, Response, Uint128};
+use crate::error::ContractError;
+use crate::state::{PointsReason, USED_VOUCHERS, USER_MAP, VOUCHER_KEYS};
+
+/// ed25519 公钥长度
+pub const ED25519_PUBKEY_LENGTH: usize = 32;
+
+/// 推荐人离线签发的邀请凭证
+#[cw_serde]
+pub struct InvitationVoucher {
+    pub referrer: String,
+    pub invitee: String,
+    pub expires_at: u64,
+    pub nonce: u64,
+    /// 由推荐人积分支付给被邀请人的奖励
+    pub bonus: Option<Uint128>,
+}
+
+/// 实际签名的内容，绑定链 ID 与合约地址以防跨部署重放
+#[cw_serde]
+pub struct VoucherSignDoc {
+    pub chain_id: String,
+    pub contract: String,
+    pub voucher: InvitationVoucher,
+}
+
+/// 生成凭证的签名原文
+pub fn voucher_sign_bytes(env: &Env, voucher: &InvitationVoucher) -> Result<Vec<u8>, ContractError> {
+    let doc = VoucherSignDoc {
+        chain_id: env.block.chain_id.clone(),
+        contract: env.contract.address.to_string(),
+        voucher: voucher.clone(),
+    };
+    Ok(to_json_vec(&doc)?)
+}
+
+/// 登记推荐人的凭证公钥
+pub fn register_voucher_key(
+    deps: DepsMut,
+    referrer: &Addr,
+    public_key: Binary,
+) -> Result<(), ContractError> {
+    if !USER_MAP.has(deps.storage, referrer) {
+        return Err(ContractError::UserNotRegistered {
+            user: referrer.to_string(),
+        });
+    }
+    
+    if public_key.len() != ED25519_PUBKEY_LENGTH {
+        return Err(ContractError::InvalidParameter {
+            parameter: "public_key".to_string(),
+            value: format!("length {}", public_key.len()),
+        });
+    }
+    
+    VOUCHER_KEYS.save(deps.storage, referrer, &public_key)?;
+    Ok(())
+}
+
+/// 校验凭证并返回推荐人地址
+pub fn verify_voucher(
+    deps: Deps,
+    env: &Env,
+    invitee: &Addr,
+    voucher: &InvitationVoucher,
+    signature: &Binary,
+) -> Result<Addr, ContractError> {
+    let referrer = deps.api.addr_validate(&voucher.referrer)?;
+    
+    // 凭证只能由被邀请人本人使用
+    if deps.api.addr_validate(&voucher.invitee)? != *invitee {
+        return Err(ContractError::Unauthorized {
+            message: "Voucher issued to another invitee".to_string(),
+        });
+    }
+    
+    if env.block.time.seconds() > voucher.expires_at {
+        return Err(ContractError::VoucherExpired {
+            expires_at: voucher.expires_at,
+        });
+    }
+    
+    if USED_VOUCHERS.has(deps.storage, (&referrer, voucher.nonce)) {
+        return Err(ContractError::VoucherAlreadyUsed {
+            referrer: referrer.to_string(),
+            nonce: voucher.nonce,
+        });
+    }
+    
+    let public_key = VOUCHER_KEYS
+        .may_load(deps.storage, &referrer)?
+        .ok_or_else(|| ContractError::InvalidReferrer {
+            referrer: referrer.to_string(),
+        })?;
+    
+    let message = voucher_sign_bytes(env, voucher)?;
+    let valid = deps
+        .api
+        .ed25519_verify(&message, signature, &public_key)
+        .map_err(|_| ContractError::InvalidSignature)?;
+    if !valid {
+        return Err(ContractError::InvalidSignature);
+    }
+    
+    Ok(referrer)
+}
+
+/// 使用邀请凭证注册
+pub fn register_with_voucher(
+    mut deps: DepsMut,
+    env: &Env,
+    invitee: &Addr,
+    voucher: InvitationVoucher,
+    signature: Binary,
+) -> Result<Response, ContractError> {
+    let referrer = verify_voucher(deps.as_ref(), env, invitee, &voucher, &signature)?;
+    
+    // 凭证一次性使用
+    USED_VOUCHERS.save(deps.storage, (&referrer, voucher.nonce), &true)?;
+    
+    let register_response = crate::user::register_user(deps.branch(), env, invitee, Some(referrer.clone()))?;
+    let mut response = Response::new()
+        .add_submessages(register_response.messages)
+        .add_events(register_response.events)
+        .add_attributes(register_response.attributes)
+        .add_attribute("voucher_nonce", voucher.nonce.to_string());
+    
+    // 奖励从推荐人积分中转给被邀请人
+    if let Some(bonus) = voucher.bonus.filter(|bonus| !bonus.is_zero()) {
+        let event_id = Some(format!("voucher:{}:{}", referrer, voucher.nonce));
+        let debit_response = crate::points::deduct_points_from_user(
+            &mut deps,
+            env,
+            &referrer,
+            bonus,
+            PointsReason::TransferOut,
+            Some(invitee.clone()),
+            event_id.clone(),
+        )?;
+        let credit_response = crate::points::allocate_points_to_user(
+            &mut deps,
+            env,
+            invitee,
+            bonus,
+            PointsReason::TransferIn,
+            Some(referrer.clone()),
+            event_id,
+        )?;
+        response = response
+            .add_submessages(debit_response.messages)
+            .add_submessages(credit_response.messages)
+            .add_events(debit_response.events)
+            .add_events(credit_response.events)
+            .add_attribute("voucher_bonus", bonus.to_string());
+    }
+    
+    Ok(response)
+}
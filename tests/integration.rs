@@ -1,19 +1,26 @@
 use cosmwasm_std::testing::{mock_dependencies, mock_env, message_info};
-use cosmwasm_std::{coins, from_json, Addr, Uint128, Decimal};
+use cosmwasm_std::{
+    coins, from_json, to_json_binary, Addr, Binary, Decimal, Deps, DepsMut, Empty, Env,
+    MessageInfo, Response, StdResult, Uint128, WasmMsg,
+};
+use cw_multi_test::{App, Contract, ContractWrapper, Executor};
+use cw_storage_plus::Item;
 use std::str::FromStr;
-use dd_registry_cw::contract::{instantiate, execute, query};
-use dd_registry_cw::msg::{InstantiateMsg, ExecuteMsg, QueryMsg};
-use dd_registry_cw::state::{SystemConfig, PointsRules, PointsReason};
+use dd_registry_cw::contract::{instantiate, execute, query, reply};
+use dd_registry_cw::hooks::{RegistryHookExecuteMsg, RegistryHookMsg};
+use dd_registry_cw::msg::{InstantiateMsg, ExecuteMsg, QueryMsg, HooksResponse, UserInfoResponse};
+use dd_registry_cw::ContractError;
+use dd_registry_cw::state::{SystemConfig, PointsRules, PointsReason, HookEventType};
 
 #[test]
 fn test_user_registration() {
     let mut deps = mock_dependencies();
     let env = mock_env();
-    let info = message_info(&Addr::unchecked("cosmwasm1jpev2csrppg792t22rn8z8uew8h3sjcpglcd0qv9g8gj8ky922tscp8avs"), &coins(1000, "uluna"));
+    let info = message_info(&deps.api.addr_make("user1"), &coins(1000, "uluna"));
     
     // 初始化合约
     let init_msg = InstantiateMsg {
-        admin: "cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp".to_string(),
+        admin: deps.api.addr_make("admin").to_string(),
         config: SystemConfig {
             enabled: true,
             max_referral_depth: 3,
@@ -22,7 +29,7 @@ fn test_user_registration() {
             points_decay_period: 30,
             points_decay_rate: Decimal::from_str("0.01").unwrap(),
             min_withdrawal_amount: Uint128::from(1000u128),
-            admin: Addr::unchecked("cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp"),
+            admin: deps.api.addr_make("admin"),
             emergency_paused: false,
         },
         points_rules: PointsRules {
@@ -35,12 +42,13 @@ fn test_user_registration() {
         },
     };
     
-    let admin_info = message_info(&Addr::unchecked("cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp"), &coins(1000, "uluna"));
+    let admin_info = message_info(&deps.api.addr_make("admin"), &coins(1000, "uluna"));
     instantiate(deps.as_mut(), env.clone(), admin_info, init_msg).unwrap();
     
     // 测试用户注册
     let msg = ExecuteMsg::Register {
         referrer: None,
+        referral_code: None,
     };
     
     let res = execute(deps.as_mut(), env, info, msg).unwrap();
@@ -56,7 +64,7 @@ fn test_referral_relation() {
     
     // 初始化合约
     let init_msg = InstantiateMsg {
-        admin: "cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp".to_string(),
+        admin: deps.api.addr_make("admin").to_string(),
         config: SystemConfig {
             enabled: true,
             max_referral_depth: 3,
@@ -65,7 +73,7 @@ fn test_referral_relation() {
             points_decay_period: 30,
             points_decay_rate: Decimal::from_str("0.01").unwrap(),
             min_withdrawal_amount: Uint128::from(1000u128),
-            admin: Addr::unchecked("cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp"),
+            admin: deps.api.addr_make("admin"),
             emergency_paused: false,
         },
         points_rules: PointsRules {
@@ -78,18 +86,19 @@ fn test_referral_relation() {
         },
     };
     
-    let admin_info = message_info(&Addr::unchecked("cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp"), &coins(1000, "uluna"));
+    let admin_info = message_info(&deps.api.addr_make("admin"), &coins(1000, "uluna"));
     instantiate(deps.as_mut(), env.clone(), admin_info, init_msg).unwrap();
     
     // 注册推荐人
-    let referrer_info = message_info(&Addr::unchecked("cosmwasm1referrer2"), &coins(1000, "uluna"));
-    let register_referrer = ExecuteMsg::Register { referrer: None };
+    let referrer_info = message_info(&deps.api.addr_make("referrer2"), &coins(1000, "uluna"));
+    let register_referrer = ExecuteMsg::Register { referrer: None, referral_code: None };
     execute(deps.as_mut(), env.clone(), referrer_info, register_referrer).unwrap();
     
     // 注册被推荐人
-    let referee_info = message_info(&Addr::unchecked("cosmwasm1referee2"), &coins(1000, "uluna"));
+    let referee_info = message_info(&deps.api.addr_make("referee2"), &coins(1000, "uluna"));
     let register_referee = ExecuteMsg::Register {
-        referrer: Some("cosmwasm1referrer2".to_string()),
+        referrer: Some(deps.api.addr_make("referrer2").to_string()),
+        referral_code: None,
     };
     
     let res = execute(deps.as_mut(), env.clone(), referee_info, register_referee).unwrap();
@@ -103,7 +112,7 @@ fn test_circular_referral_prevention() {
     
     // 初始化合约
     let init_msg = InstantiateMsg {
-        admin: "cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp".to_string(),
+        admin: deps.api.addr_make("admin").to_string(),
         config: SystemConfig {
             enabled: true,
             max_referral_depth: 3,
@@ -112,7 +121,7 @@ fn test_circular_referral_prevention() {
             points_decay_period: 30,
             points_decay_rate: Decimal::from_str("0.01").unwrap(),
             min_withdrawal_amount: Uint128::from(1000u128),
-            admin: Addr::unchecked("cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp"),
+            admin: deps.api.addr_make("admin"),
             emergency_paused: false,
         },
         points_rules: PointsRules {
@@ -125,28 +134,30 @@ fn test_circular_referral_prevention() {
         },
     };
     
-    let admin_info = message_info(&Addr::unchecked("cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp"), &coins(1000, "uluna"));
+    let admin_info = message_info(&deps.api.addr_make("admin"), &coins(1000, "uluna"));
     instantiate(deps.as_mut(), env.clone(), admin_info, init_msg).unwrap();
     
     // 注册用户A
-    let user_a_info = message_info(&Addr::unchecked("cosmwasm1user_a2"), &coins(1000, "uluna"));
-    let register_a = ExecuteMsg::Register { referrer: None };
+    let user_a_info = message_info(&deps.api.addr_make("user_a2"), &coins(1000, "uluna"));
+    let register_a = ExecuteMsg::Register { referrer: None, referral_code: None };
     execute(deps.as_mut(), env.clone(), user_a_info, register_a).unwrap();
     
     // 注册用户B，推荐人为A
-    let user_b_info = message_info(&Addr::unchecked("cosmwasm1user_b2"), &coins(1000, "uluna"));
+    let user_b_info = message_info(&deps.api.addr_make("user_b2"), &coins(1000, "uluna"));
     let register_b = ExecuteMsg::Register {
-        referrer: Some("cosmwasm1user_a2".to_string()),
+        referrer: Some(deps.api.addr_make("user_a2").to_string()),
+        referral_code: None,
     };
     execute(deps.as_mut(), env.clone(), user_b_info, register_b).unwrap();
     
-    // 尝试让C推荐A（应该失败，因为这会形成循环推荐）
-    let user_c_info = message_info(&Addr::unchecked("cosmwasm1user_c2"), &coins(1000, "uluna"));
+    // 尝试让A以B为推荐人再次注册（应该失败）
+    let user_a_info = message_info(&deps.api.addr_make("user_a2"), &coins(1000, "uluna"));
     let register_circular = ExecuteMsg::Register {
-        referrer: Some("cosmwasm1user_a2".to_string()),
+        referrer: Some(deps.api.addr_make("user_b2").to_string()),
+        referral_code: None,
     };
     
-    let res = execute(deps.as_mut(), env, user_c_info, register_circular);
+    let res = execute(deps.as_mut(), env, user_a_info, register_circular);
     assert!(res.is_err());
 }
 
@@ -157,7 +168,7 @@ fn test_points_allocation() {
     
     // 初始化合约
     let init_msg = InstantiateMsg {
-        admin: "cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp".to_string(),
+        admin: deps.api.addr_make("admin").to_string(),
         config: SystemConfig {
             enabled: true,
             max_referral_depth: 3,
@@ -166,7 +177,7 @@ fn test_points_allocation() {
             points_decay_period: 30,
             points_decay_rate: Decimal::from_str("0.01").unwrap(),
             min_withdrawal_amount: Uint128::from(1000u128),
-            admin: Addr::unchecked("cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp"),
+            admin: deps.api.addr_make("admin"),
             emergency_paused: false,
         },
         points_rules: PointsRules {
@@ -179,18 +190,18 @@ fn test_points_allocation() {
         },
     };
     
-    let admin_info = message_info(&Addr::unchecked("cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp"), &coins(1000, "uluna"));
+    let admin_info = message_info(&deps.api.addr_make("admin"), &coins(1000, "uluna"));
     instantiate(deps.as_mut(), env.clone(), admin_info, init_msg).unwrap();
     
     // 注册用户
-    let user_info = message_info(&Addr::unchecked("cosmwasm1user"), &coins(1000, "uluna"));
-    let register_user = ExecuteMsg::Register { referrer: None };
+    let user_info = message_info(&deps.api.addr_make("user"), &coins(1000, "uluna"));
+    let register_user = ExecuteMsg::Register { referrer: None, referral_code: None };
     execute(deps.as_mut(), env.clone(), user_info, register_user).unwrap();
     
     // 分配积分
-    let admin_info = message_info(&Addr::unchecked("cosmwasm1h34lmpywh4upnjdg90cjf4j70aee6z8qqfspugamjp42e4q28kqs8s7vcp"), &coins(1000, "uluna"));
+    let admin_info = message_info(&deps.api.addr_make("admin"), &coins(1000, "uluna"));
     let allocate_msg = ExecuteMsg::AllocateRewards {
-        user: "user".to_string(),
+        user: deps.api.addr_make("user").to_string(),
         points: Uint128::from(100u128),
         reason: PointsReason::ActivityBonus,
         related_user: None,
@@ -202,10 +213,206 @@ fn test_points_allocation() {
     
     // 查询用户积分
     let query_msg = QueryMsg::GetUserPoints {
-        user: "user".to_string(),
+        user: deps.api.addr_make("user").to_string(),
     };
     let res = query(deps.as_ref(), env, query_msg).unwrap();
     let points_res: dd_registry_cw::msg::UserPointsResponse = from_json(&res).unwrap();
     
     assert_eq!(points_res.points, Uint128::from(100u128));
 }
+
+const RECEIVED_HOOKS: Item<Vec<RegistryHookMsg>> = Item::new("received_hooks");
+
+fn receiver_execute(
+    deps: DepsMut,
+    _env: Env,
+    _info: MessageInfo,
+    msg: RegistryHookExecuteMsg,
+) -> StdResult<Response> {
+    let RegistryHookExecuteMsg::RegistryHook(hook) = msg;
+    let mut received = RECEIVED_HOOKS.may_load(deps.storage)?.unwrap_or_default();
+    received.push(hook);
+    RECEIVED_HOOKS.save(deps.storage, &received)?;
+    Ok(Response::new())
+}
+
+// 收到钩子后尝试重入注册合约
+fn reentrant_execute(
+    _deps: DepsMut,
+    _env: Env,
+    info: MessageInfo,
+    _msg: RegistryHookExecuteMsg,
+) -> StdResult<Response> {
+    let msg = WasmMsg::Execute {
+        contract_addr: info.sender.to_string(),
+        msg: to_json_binary(&ExecuteMsg::Register { referrer: None, referral_code: None })?,
+        funds: vec![],
+    };
+    Ok(Response::new().add_message(msg))
+}
+
+fn receiver_instantiate(_deps: DepsMut, _env: Env, _info: MessageInfo, _msg: Empty) -> StdResult<Response> {
+    Ok(Response::new())
+}
+
+fn receiver_query(deps: Deps, _env: Env, _msg: Empty) -> StdResult<Binary> {
+    to_json_binary(&RECEIVED_HOOKS.may_load(deps.storage)?.unwrap_or_default())
+}
+
+fn registry_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(execute, instantiate, query).with_reply(reply))
+}
+
+fn receiver_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(receiver_execute, receiver_instantiate, receiver_query))
+}
+
+fn reentrant_contract() -> Box<dyn Contract<Empty>> {
+    Box::new(ContractWrapper::new(reentrant_execute, receiver_instantiate, receiver_query))
+}
+
+fn instantiate_registry(app: &mut App) -> Addr {
+    let admin = app.api().addr_make("admin");
+    let code_id = app.store_code(registry_contract());
+    let init_msg = InstantiateMsg {
+        admin: admin.to_string(),
+        config: SystemConfig {
+            enabled: true,
+            max_referral_depth: 3,
+            referral_cooldown: 3600,
+            max_daily_referrals: 10,
+            points_decay_period: 30,
+            points_decay_rate: Decimal::from_str("0.01").unwrap(),
+            min_withdrawal_amount: Uint128::from(1000u128),
+            admin: admin.clone(),
+            emergency_paused: false,
+        },
+        points_rules: PointsRules {
+            direct_referral_rate: Decimal::from_str("0.5").unwrap(),
+            level_2_rate: Decimal::from_str("0.2").unwrap(),
+            level_3_rate: Decimal::from_str("0.1").unwrap(),
+            base_points: Uint128::from(100u128),
+            level_multipliers: std::collections::HashMap::new(),
+            activity_rules: Vec::new(),
+        },
+    };
+    app.instantiate_contract(code_id, admin, &init_msg, &[], "dd-registry", None)
+        .unwrap()
+}
+
+#[test]
+fn test_hooks_notify_subscribers() {
+    let mut app = App::default();
+    let admin = app.api().addr_make("admin");
+    let alice = app.api().addr_make("alice");
+    let bob = app.api().addr_make("bob");
+    let registry = instantiate_registry(&mut app);
+
+    let receiver_code = app.store_code(receiver_contract());
+    let receiver = app
+        .instantiate_contract(receiver_code, admin.clone(), &Empty {}, &[], "receiver", None)
+        .unwrap();
+
+    // 只订阅注册与推荐事件
+    app.execute_contract(
+        admin.clone(),
+        registry.clone(),
+        &ExecuteMsg::AddHook {
+            addr: receiver.to_string(),
+            events: vec![HookEventType::UserRegistered, HookEventType::ReferralEstablished],
+        },
+        &[],
+    )
+    .unwrap();
+
+    let hooks: HooksResponse = app
+        .wrap()
+        .query_wasm_smart(&registry, &QueryMsg::ListHooks {})
+        .unwrap();
+    assert_eq!(hooks.hooks.len(), 1);
+
+    app.execute_contract(alice.clone(), registry.clone(), &ExecuteMsg::Register { referrer: None, referral_code: None }, &[])
+        .unwrap();
+    app.execute_contract(
+        bob.clone(),
+        registry.clone(),
+        &ExecuteMsg::Register { referrer: Some(alice.to_string()), referral_code: None },
+        &[],
+    )
+    .unwrap();
+
+    // 未订阅积分变动事件
+    app.execute_contract(
+        admin,
+        registry,
+        &ExecuteMsg::AllocateRewards {
+            user: alice.to_string(),
+            points: Uint128::from(100u128),
+            reason: PointsReason::ActivityBonus,
+            related_user: None,
+            event_id: None,
+        },
+        &[],
+    )
+    .unwrap();
+
+    let received: Vec<RegistryHookMsg> = app.wrap().query_wasm_smart(&receiver, &Empty {}).unwrap();
+    assert_eq!(received.len(), 3);
+    assert!(matches!(received[0], RegistryHookMsg::UserRegistered { .. }));
+    assert_eq!(
+        received[1],
+        RegistryHookMsg::ReferralEstablished {
+            referrer: alice.to_string(),
+            referee: bob.to_string(),
+            timestamp: app.block_info().time.seconds(),
+        }
+    );
+    assert!(matches!(received[2], RegistryHookMsg::UserRegistered { .. }));
+}
+
+#[test]
+fn test_hook_reentrancy_rejected() {
+    let mut app = App::default();
+    let admin = app.api().addr_make("admin");
+    let alice = app.api().addr_make("alice");
+    let registry = instantiate_registry(&mut app);
+
+    let reentrant_code = app.store_code(reentrant_contract());
+    let reentrant = app
+        .instantiate_contract(reentrant_code, admin.clone(), &Empty {}, &[], "reentrant", None)
+        .unwrap();
+
+    app.execute_contract(
+        admin,
+        registry.clone(),
+        &ExecuteMsg::AddHook {
+            addr: reentrant.to_string(),
+            events: vec![HookEventType::UserRegistered],
+        },
+        &[],
+    )
+    .unwrap();
+
+    // 钩子重入被拒绝，失败记录在钩子名下，注册本身不回滚
+    app.execute_contract(alice.clone(), registry.clone(), &ExecuteMsg::Register { referrer: None, referral_code: None }, &[])
+        .unwrap();
+
+    let res: StdResult<UserInfoResponse> = app
+        .wrap()
+        .query_wasm_smart(&registry, &QueryMsg::GetUserInfo { user: alice.to_string() });
+    assert!(res.is_ok());
+    let res: StdResult<UserInfoResponse> = app
+        .wrap()
+        .query_wasm_smart(&registry, &QueryMsg::GetUserInfo { user: reentrant.to_string() });
+    assert!(res.is_err());
+
+    let hooks: HooksResponse = app.wrap().query_wasm_smart(&registry, &QueryMsg::ListHooks {}).unwrap();
+    let failure = hooks.hooks[0].failure.clone().unwrap();
+    assert_eq!(failure.count, 1);
+    assert!(failure.last_error.contains(&ContractError::ReentrancyDetected.to_string()));
+
+    // 锁已释放，后续操作不受影响
+    let bob = app.api().addr_make("bob");
+    app.execute_contract(bob, registry, &ExecuteMsg::Register { referrer: None, referral_code: None }, &[])
+        .unwrap();
+}
@@ -1,19 +1,38 @@
-use cosmwasm_std::testing::{mock_dependencies, mock_env, message_info};
-use cosmwasm_std::{coins, from_json, Addr, Uint128, Decimal};
+use cosmwasm_std::testing::{mock_dependencies, mock_env, message_info, MockApi, MockQuerier, MockStorage};
+use cosmwasm_std::{coins, from_json, BankMsg, Binary, Decimal, OwnedDeps, Reply, ReplyOn, SubMsgResult, Uint128};
 use std::str::FromStr;
-use dd_registry_cw::contract::{instantiate, execute, query};
-use dd_registry_cw::msg::{InstantiateMsg, ExecuteMsg, QueryMsg};
-use dd_registry_cw::state::{SystemConfig, PointsRules, PointsReason};
+use dd_registry_cw::contract::{instantiate, execute, migrate, query, reply, sudo};
+use dd_registry_cw::guard::guard_sub_msg;
+use dd_registry_cw::voucher::{voucher_sign_bytes, InvitationVoucher};
+use dd_registry_cw::distribution::distribution_leaf;
+use sha2::{Digest, Sha256};
+use ed25519_zebra::{SigningKey, VerificationKey};
+use dd_registry_cw::msg::{
+    InstantiateMsg, ExecuteMsg, QueryMsg, UserInfoResponse, UsersSummaryResponse, ReferralCodeResponse,
+    ReferralCodesResponse, SudoMsg, ImportedUser, ReferralChainResponse, LeaderboardResponse,
+    DownlineCursor, DownlineResponse, TeamSummaryResponse, SimulateRegisterResponse, ValidationResponse,
+    SimulateAllocationResponse, BudgetStatusResponse, CampaignResponse,
+    SeasonLeaderboardResponse, UserPointsAtResponse, TotalPointsAtResponse, DistributionResponse,
+    LeaderboardCursor, LeaderboardFilter, ReferralLeaderboardResponse, SupplyResponse, PointsHistoryFilter, SortOrder,
+    PointsHistoryResponse, RewardAllocation, MigrateMsg,
+};
+use dd_registry_cw::state::{
+    SystemConfig, PointsRules, PointsReason, TransferConfig, UserInfo, UserLevel, Compensation,
+    ReferralRewardAction, BindReferrerConfig, UserStatus, PointsRecord,
+    EarningCapsConfig, ReasonCap, CapPolicy, RateOverrides, TOTAL_POINTS, GLOBAL_STATS, LEVEL_STATS,
+    STATUS_STATS,
+};
+use dd_registry_cw::ContractError;
 
 #[test]
 fn test_user_registration() {
     let mut deps = mock_dependencies();
     let env = mock_env();
-    let info = message_info(&Addr::unchecked("user1"), &coins(1000, "uluna"));
+    let info = message_info(&deps.api.addr_make("user1"), &coins(1000, "uluna"));
     
     // 初始化合约
     let init_msg = InstantiateMsg {
-        admin: "admin".to_string(),
+        admin: deps.api.addr_make("admin").to_string(),
         config: SystemConfig {
             enabled: true,
             max_referral_depth: 3,
@@ -22,7 +41,7 @@ fn test_user_registration() {
             points_decay_period: 30,
             points_decay_rate: Decimal::from_str("0.01").unwrap(),
             min_withdrawal_amount: Uint128::from(1000u128),
-            admin: Addr::unchecked("admin"),
+            admin: deps.api.addr_make("admin"),
             emergency_paused: false,
         },
         points_rules: PointsRules {
@@ -35,12 +54,13 @@ fn test_user_registration() {
         },
     };
     
-    let admin_info = message_info(&Addr::unchecked("admin"), &coins(1000, "uluna"));
+    let admin_info = message_info(&deps.api.addr_make("admin"), &coins(1000, "uluna"));
     instantiate(deps.as_mut(), env.clone(), admin_info, init_msg).unwrap();
     
     // 测试用户注册
     let msg = ExecuteMsg::Register {
         referrer: None,
+        referral_code: None,
     };
     
     let res = execute(deps.as_mut(), env, info, msg).unwrap();
@@ -56,7 +76,7 @@ fn test_referral_relation() {
     
     // 初始化合约
     let init_msg = InstantiateMsg {
-        admin: "admin".to_string(),
+        admin: deps.api.addr_make("admin").to_string(),
         config: SystemConfig {
             enabled: true,
             max_referral_depth: 3,
@@ -65,7 +85,7 @@ fn test_referral_relation() {
             points_decay_period: 30,
             points_decay_rate: Decimal::from_str("0.01").unwrap(),
             min_withdrawal_amount: Uint128::from(1000u128),
-            admin: Addr::unchecked("admin"),
+            admin: deps.api.addr_make("admin"),
             emergency_paused: false,
         },
         points_rules: PointsRules {
@@ -78,18 +98,19 @@ fn test_referral_relation() {
         },
     };
     
-    let admin_info = message_info(&Addr::unchecked("admin"), &coins(1000, "uluna"));
+    let admin_info = message_info(&deps.api.addr_make("admin"), &coins(1000, "uluna"));
     instantiate(deps.as_mut(), env.clone(), admin_info, init_msg).unwrap();
     
     // 注册推荐人
-    let referrer_info = message_info(&Addr::unchecked("referrer"), &coins(1000, "uluna"));
-    let register_referrer = ExecuteMsg::Register { referrer: None };
+    let referrer_info = message_info(&deps.api.addr_make("referrer"), &coins(1000, "uluna"));
+    let register_referrer = ExecuteMsg::Register { referrer: None, referral_code: None };
     execute(deps.as_mut(), env.clone(), referrer_info, register_referrer).unwrap();
     
     // 注册被推荐人
-    let referee_info = message_info(&Addr::unchecked("referee"), &coins(1000, "uluna"));
+    let referee_info = message_info(&deps.api.addr_make("referee"), &coins(1000, "uluna"));
     let register_referee = ExecuteMsg::Register {
-        referrer: Some("referrer".to_string()),
+        referrer: Some(deps.api.addr_make("referrer").to_string()),
+        referral_code: None,
     };
     
     let res = execute(deps.as_mut(), env, referee_info, register_referee).unwrap();
@@ -103,7 +124,7 @@ fn test_circular_referral_prevention() {
     
     // 初始化合约
     let init_msg = InstantiateMsg {
-        admin: "admin".to_string(),
+        admin: deps.api.addr_make("admin").to_string(),
         config: SystemConfig {
             enabled: true,
             max_referral_depth: 3,
@@ -112,7 +133,7 @@ fn test_circular_referral_prevention() {
             points_decay_period: 30,
             points_decay_rate: Decimal::from_str("0.01").unwrap(),
             min_withdrawal_amount: Uint128::from(1000u128),
-            admin: Addr::unchecked("admin"),
+            admin: deps.api.addr_make("admin"),
             emergency_paused: false,
         },
         points_rules: PointsRules {
@@ -125,25 +146,27 @@ fn test_circular_referral_prevention() {
         },
     };
     
-    let admin_info = message_info(&Addr::unchecked("admin"), &coins(1000, "uluna"));
+    let admin_info = message_info(&deps.api.addr_make("admin"), &coins(1000, "uluna"));
     instantiate(deps.as_mut(), env.clone(), admin_info, init_msg).unwrap();
     
     // 注册用户A
-    let user_a_info = message_info(&Addr::unchecked("user_a"), &coins(1000, "uluna"));
-    let register_a = ExecuteMsg::Register { referrer: None };
+    let user_a_info = message_info(&deps.api.addr_make("user_a"), &coins(1000, "uluna"));
+    let register_a = ExecuteMsg::Register { referrer: None, referral_code: None };
     execute(deps.as_mut(), env.clone(), user_a_info, register_a).unwrap();
     
     // 注册用户B，推荐人为A
-    let user_b_info = message_info(&Addr::unchecked("user_b"), &coins(1000, "uluna"));
+    let user_b_info = message_info(&deps.api.addr_make("user_b"), &coins(1000, "uluna"));
     let register_b = ExecuteMsg::Register {
-        referrer: Some("user_a".to_string()),
+        referrer: Some(deps.api.addr_make("user_a").to_string()),
+        referral_code: None,
     };
     execute(deps.as_mut(), env.clone(), user_b_info, register_b).unwrap();
     
     // 尝试让A推荐B（应该失败）
-    let user_a_info = message_info(&Addr::unchecked("user_a"), &coins(1000, "uluna"));
+    let user_a_info = message_info(&deps.api.addr_make("user_a"), &coins(1000, "uluna"));
     let register_circular = ExecuteMsg::Register {
-        referrer: Some("user_b".to_string()),
+        referrer: Some(deps.api.addr_make("user_b").to_string()),
+        referral_code: None,
     };
     
     let res = execute(deps.as_mut(), env, user_a_info, register_circular);
@@ -157,7 +180,7 @@ fn test_points_allocation() {
     
     // 初始化合约
     let init_msg = InstantiateMsg {
-        admin: "admin".to_string(),
+        admin: deps.api.addr_make("admin").to_string(),
         config: SystemConfig {
             enabled: true,
             max_referral_depth: 3,
@@ -166,7 +189,7 @@ fn test_points_allocation() {
             points_decay_period: 30,
             points_decay_rate: Decimal::from_str("0.01").unwrap(),
             min_withdrawal_amount: Uint128::from(1000u128),
-            admin: Addr::unchecked("admin"),
+            admin: deps.api.addr_make("admin"),
             emergency_paused: false,
         },
         points_rules: PointsRules {
@@ -179,18 +202,18 @@ fn test_points_allocation() {
         },
     };
     
-    let admin_info = message_info(&Addr::unchecked("admin"), &coins(1000, "uluna"));
+    let admin_info = message_info(&deps.api.addr_make("admin"), &coins(1000, "uluna"));
     instantiate(deps.as_mut(), env.clone(), admin_info, init_msg).unwrap();
     
     // 注册用户
-    let user_info = message_info(&Addr::unchecked("user"), &coins(1000, "uluna"));
-    let register_user = ExecuteMsg::Register { referrer: None };
+    let user_info = message_info(&deps.api.addr_make("user"), &coins(1000, "uluna"));
+    let register_user = ExecuteMsg::Register { referrer: None, referral_code: None };
     execute(deps.as_mut(), env.clone(), user_info, register_user).unwrap();
     
     // 分配积分
-    let admin_info = message_info(&Addr::unchecked("admin"), &coins(1000, "uluna"));
+    let admin_info = message_info(&deps.api.addr_make("admin"), &coins(1000, "uluna"));
     let allocate_msg = ExecuteMsg::AllocateRewards {
-        user: "user".to_string(),
+        user: deps.api.addr_make("user").to_string(),
         points: Uint128::from(100u128),
         reason: PointsReason::ActivityBonus,
         related_user: None,
@@ -202,10 +225,1843 @@ fn test_points_allocation() {
     
     // 查询用户积分
     let query_msg = QueryMsg::GetUserPoints {
-        user: "user".to_string(),
+        user: deps.api.addr_make("user").to_string(),
     };
     let res = query(deps.as_ref(), env, query_msg).unwrap();
     let points_res: dd_registry_cw::msg::UserPointsResponse = from_json(&res).unwrap();
     
     assert_eq!(points_res.points, Uint128::from(100u128));
 }
+
+fn setup_contract() -> OwnedDeps<MockStorage, MockApi, MockQuerier> {
+    let mut deps = mock_dependencies();
+    let admin = deps.api.addr_make("admin");
+    
+    let init_msg = InstantiateMsg {
+        admin: admin.to_string(),
+        config: SystemConfig {
+            enabled: true,
+            max_referral_depth: 3,
+            referral_cooldown: 3600,
+            max_daily_referrals: 10,
+            points_decay_period: 30,
+            points_decay_rate: Decimal::from_str("0.01").unwrap(),
+            min_withdrawal_amount: Uint128::from(1000u128),
+            admin: admin.clone(),
+            emergency_paused: false,
+        },
+        points_rules: PointsRules {
+            direct_referral_rate: Decimal::from_str("0.5").unwrap(),
+            level_2_rate: Decimal::from_str("0.2").unwrap(),
+            level_3_rate: Decimal::from_str("0.1").unwrap(),
+            base_points: Uint128::from(100u128),
+            level_multipliers: std::collections::HashMap::new(),
+            activity_rules: Vec::new(),
+        },
+    };
+    
+    instantiate(deps.as_mut(), mock_env(), message_info(&admin, &[]), init_msg).unwrap();
+    deps
+}
+
+fn register(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, user: &str, referrer: Option<&str>) {
+    let sender = deps.api.addr_make(user);
+    let msg = ExecuteMsg::Register {
+        referrer: referrer.map(|r| deps.api.addr_make(r).to_string()),
+        referral_code: None,
+    };
+    execute(deps.as_mut(), mock_env(), message_info(&sender, &[]), msg).unwrap();
+}
+
+fn allocate(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, user: &str, points: u128) {
+    let admin = deps.api.addr_make("admin");
+    let msg = ExecuteMsg::AllocateRewards {
+        user: deps.api.addr_make(user).to_string(),
+        points: Uint128::from(points),
+        reason: PointsReason::ActivityBonus,
+        related_user: None,
+        event_id: None,
+    };
+    execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), msg).unwrap();
+}
+
+/// 通过管理员导入快速搭建推荐树，避开推荐冷却
+fn import_tree(deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, users: &[(&str, Option<&str>, u128)]) {
+    let admin = deps.api.addr_make("admin");
+    let users = users
+        .iter()
+        .map(|(user, referrer, points)| ImportedUser {
+            address: deps.api.addr_make(user).to_string(),
+            referrer: referrer.map(|r| deps.api.addr_make(r).to_string()),
+            registered_at: 1_600_000_000,
+            points: Uint128::from(*points),
+            level: UserLevel::Bronze,
+        })
+        .collect();
+    execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), ExecuteMsg::ImportUsers { users }).unwrap();
+}
+
+fn user_info(deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, user: &str) -> UserInfo {
+    let msg = QueryMsg::GetUserInfo { user: deps.api.addr_make(user).to_string() };
+    let res: UserInfoResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    res.user_info
+}
+
+fn last_record(deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, user: &str) -> PointsRecord {
+    let msg = QueryMsg::GetPointsHistory {
+        user: deps.api.addr_make(user).to_string(),
+        limit: Some(1),
+        start_after: None,
+        filter: None,
+        order: Some(SortOrder::Descending),
+        cursor: None,
+    };
+    let res: PointsHistoryResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    res.records.into_iter().next().unwrap()
+}
+
+#[test]
+fn test_points_transfer() {
+    let mut deps = setup_contract();
+    let admin = deps.api.addr_make("admin");
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", None);
+    register(&mut deps, "treasury", None);
+    allocate(&mut deps, "alice", 1000);
+    
+    let transfer = ExecuteMsg::TransferPoints {
+        to: deps.api.addr_make("bob").to_string(),
+        amount: Uint128::from(200u128),
+        memo: Some("gift".to_string()),
+    };
+    
+    // 默认关闭
+    let alice = deps.api.addr_make("alice");
+    let err = execute(deps.as_mut(), mock_env(), message_info(&alice, &[]), transfer.clone()).unwrap_err();
+    assert_eq!(err, ContractError::FeatureDisabled { feature: "transfer".to_string() });
+    
+    let config = TransferConfig {
+        enabled: true,
+        fee_rate: Decimal::percent(10),
+        fee_recipient: Some(deps.api.addr_make("treasury")),
+        max_daily_amount: None,
+        max_daily_count: Some(1),
+    };
+    execute(
+        deps.as_mut(),
+        mock_env(),
+        message_info(&admin, &[]),
+        ExecuteMsg::UpdateTransferConfig { config },
+    )
+    .unwrap();
+    
+    execute(deps.as_mut(), mock_env(), message_info(&alice, &[]), transfer.clone()).unwrap();
+    
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(800u128));
+    assert_eq!(user_info(&deps, "bob").reward_points, Uint128::from(180u128));
+    assert_eq!(user_info(&deps, "treasury").reward_points, Uint128::from(20u128));
+    
+    // 双方历史记录互相关联
+    let sent = last_record(&deps, "alice");
+    assert_eq!(sent.reason, PointsReason::TransferOut);
+    assert_eq!(sent.related_user, Some(deps.api.addr_make("bob")));
+    let received = last_record(&deps, "bob");
+    assert_eq!(received.reason, PointsReason::TransferIn);
+    assert_eq!(received.related_user, Some(alice.clone()));
+    
+    // 超出每日次数限制
+    let err = execute(deps.as_mut(), mock_env(), message_info(&alice, &[]), transfer).unwrap_err();
+    assert!(matches!(err, ContractError::LimitExceeded { .. }));
+}
+
+#[test]
+fn test_users_summary() {
+    let mut deps = setup_contract();
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", Some("alice"));
+    allocate(&mut deps, "bob", 300);
+    
+    let msg = QueryMsg::GetUsersSummary {
+        users: vec![
+            deps.api.addr_make("alice").to_string(),
+            deps.api.addr_make("bob").to_string(),
+            deps.api.addr_make("carol").to_string(),
+        ],
+    };
+    let res: UsersSummaryResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    
+    assert_eq!(res.users.len(), 3);
+    assert!(res.users[0].registered);
+    assert_eq!(res.users[1].points, Uint128::from(300u128));
+    assert_eq!(res.users[1].referrer, Some(deps.api.addr_make("alice")));
+    assert!(!res.users[2].registered);
+    assert_eq!(res.users[2].level, None);
+}
+
+#[test]
+fn test_guarded_call_compensation() {
+    let mut deps = setup_contract();
+    register(&mut deps, "alice", None);
+    let alice = deps.api.addr_make("alice");
+
+    let payout = BankMsg::Send {
+        to_address: alice.to_string(),
+        amount: coins(100, "uluna"),
+    };
+    let compensation = Compensation {
+        user: alice.clone(),
+        points: Uint128::from(100u128),
+        reason: PointsReason::ManualAdjustment,
+    };
+    let sub_msg = guard_sub_msg(deps.as_mut().storage, payout.into(), Some(compensation)).unwrap();
+    assert_eq!(sub_msg.reply_on, ReplyOn::Always);
+
+    // 持锁期间拒绝执行
+    let bob = deps.api.addr_make("bob");
+    let err = execute(deps.as_mut(), mock_env(), message_info(&bob, &[]), ExecuteMsg::Register { referrer: None, referral_code: None })
+        .unwrap_err();
+    assert_eq!(err, ContractError::ReentrancyDetected);
+
+    // 外部调用失败，积分退还并释放锁
+    let failed = Reply {
+        id: sub_msg.id,
+        payload: Binary::default(),
+        gas_used: 0,
+        result: SubMsgResult::Err("payout failed".to_string()),
+    };
+    reply(deps.as_mut(), mock_env(), failed).unwrap();
+
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(100u128));
+    register(&mut deps, "bob", None);
+}
+
+#[test]
+fn test_register_with_voucher() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    register(&mut deps, "alice", None);
+    allocate(&mut deps, "alice", 500);
+    let alice = deps.api.addr_make("alice");
+    let bob = deps.api.addr_make("bob");
+
+    let signing_key = SigningKey::from([7u8; 32]);
+    let public_key: [u8; 32] = VerificationKey::from(&signing_key).into();
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        message_info(&alice, &[]),
+        ExecuteMsg::RegisterVoucherKey { public_key: Binary::from(public_key) },
+    )
+    .unwrap();
+
+    let voucher = InvitationVoucher {
+        referrer: alice.to_string(),
+        invitee: bob.to_string(),
+        expires_at: env.block.time.seconds() + 3600,
+        nonce: 1,
+        bonus: Some(Uint128::from(50u128)),
+    };
+    let sign_bytes = voucher_sign_bytes(&env, &voucher).unwrap();
+    let signature: [u8; 64] = signing_key.sign(&sign_bytes).into();
+    let msg = ExecuteMsg::RegisterWithVoucher {
+        voucher: voucher.clone(),
+        signature: Binary::from(signature),
+    };
+
+    // 篡改奖励后签名无效
+    let mut tampered = voucher.clone();
+    tampered.bonus = Some(Uint128::from(500u128));
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        message_info(&bob, &[]),
+        ExecuteMsg::RegisterWithVoucher { voucher: tampered, signature: Binary::from(signature) },
+    )
+    .unwrap_err();
+    assert_eq!(err, ContractError::InvalidSignature);
+
+    execute(deps.as_mut(), env.clone(), message_info(&bob, &[]), msg.clone()).unwrap();
+
+    let bob_info = user_info(&deps, "bob");
+    assert_eq!(bob_info.recommender, Some(alice.clone()));
+    assert_eq!(bob_info.reward_points, Uint128::from(50u128));
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(450u128));
+
+    // 凭证只能使用一次
+    let err = execute(deps.as_mut(), env, message_info(&bob, &[]), msg).unwrap_err();
+    assert_eq!(err, ContractError::VoucherAlreadyUsed { referrer: alice.to_string(), nonce: 1 });
+}
+
+#[test]
+fn test_referral_codes() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    register(&mut deps, "alice", None);
+    let alice = deps.api.addr_make("alice");
+
+    let create = |code: &str| ExecuteMsg::CreateReferralCode {
+        code: code.to_string(),
+        max_uses: Some(1),
+        expires_at: None,
+    };
+
+    // 保留词与敏感词
+    let err = execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), create("Admin")).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidReferralCode { .. }));
+    let err = execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), create("sh-it-happens")).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidReferralCode { .. }));
+
+    execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), create("Alice2024")).unwrap();
+
+    let bob = deps.api.addr_make("bob");
+    let msg = ExecuteMsg::Register {
+        referrer: None,
+        referral_code: Some("ALICE2024".to_string()),
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&bob, &[]), msg).unwrap();
+    assert_eq!(user_info(&deps, "bob").recommender, Some(alice.clone()));
+
+    let res: ReferralCodeResponse = from_json(
+        query(deps.as_ref(), env.clone(), QueryMsg::ResolveReferralCode { code: "alice2024".to_string() }).unwrap(),
+    )
+    .unwrap();
+    let code = res.code.unwrap();
+    assert_eq!(code.owner, alice);
+    assert_eq!(code.uses, 1);
+    assert!(!res.usable);
+
+    // 使用次数已满
+    let carol = deps.api.addr_make("carol");
+    let msg = ExecuteMsg::Register {
+        referrer: None,
+        referral_code: Some("alice2024".to_string()),
+    };
+    let err = execute(deps.as_mut(), env.clone(), message_info(&carol, &[]), msg).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidReferralCode { .. }));
+
+    let res: ReferralCodesResponse = from_json(
+        query(
+            deps.as_ref(),
+            env,
+            QueryMsg::ListMyCodes { user: alice.to_string(), start_after: None, limit: None },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(res.codes.len(), 1);
+}
+
+#[test]
+fn test_sudo_emergency_controls() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    register(&mut deps, "alice", None);
+    allocate(&mut deps, "alice", 2000);
+    let alice = deps.api.addr_make("alice");
+    let new_admin = deps.api.addr_make("new_admin");
+
+    // 冻结用户后不可提取
+    sudo(
+        deps.as_mut(),
+        env.clone(),
+        SudoMsg::FreezeUsers { users: vec![alice.to_string()], frozen: true },
+    )
+    .unwrap();
+    let withdraw = ExecuteMsg::WithdrawPoints { amount: Uint128::from(1000u128) };
+    let err = execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), withdraw.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidUserStatus { .. }));
+
+    sudo(
+        deps.as_mut(),
+        env.clone(),
+        SudoMsg::FreezeUsers { users: vec![alice.to_string()], frozen: false },
+    )
+    .unwrap();
+
+    // 强制暂停
+    sudo(deps.as_mut(), env.clone(), SudoMsg::ForcePause { paused: true }).unwrap();
+    let err = execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), withdraw.clone()).unwrap_err();
+    assert_eq!(err, ContractError::SystemPaused);
+
+    // 更换管理员后由新管理员恢复
+    sudo(deps.as_mut(), env.clone(), SudoMsg::ReplaceAdmin { admin: new_admin.to_string() }).unwrap();
+    let admin = deps.api.addr_make("admin");
+    let unpause = ExecuteMsg::EmergencyPause { paused: false };
+    let err = execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), unpause.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::Unauthorized { .. }));
+    execute(deps.as_mut(), env.clone(), message_info(&new_admin, &[]), unpause).unwrap();
+
+    execute(deps.as_mut(), env, message_info(&alice, &[]), withdraw).unwrap();
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(1000u128));
+}
+
+#[test]
+fn test_import_users() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    let admin = deps.api.addr_make("admin");
+    register(&mut deps, "alice", None);
+
+    let imported = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, user: &str, referrer: Option<&str>, points: u128| {
+        ImportedUser {
+            address: deps.api.addr_make(user).to_string(),
+            referrer: referrer.map(|r| deps.api.addr_make(r).to_string()),
+            registered_at: 1_600_000_000,
+            points: Uint128::from(points),
+            level: UserLevel::Silver,
+        }
+    };
+
+    // 批次内形成循环
+    let cyclic = vec![imported(&deps, "x", Some("y"), 0), imported(&deps, "y", Some("x"), 0)];
+    let err = execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), ExecuteMsg::ImportUsers { users: cyclic })
+        .unwrap_err();
+    assert!(matches!(err, ContractError::CircularReferral { .. }));
+
+    // 推荐人可以出现在同批次的后面
+    let users = vec![
+        imported(&deps, "carol", Some("bob"), 300),
+        imported(&deps, "bob", Some("alice"), 500),
+    ];
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), ExecuteMsg::ImportUsers { users }).unwrap();
+
+    let carol = user_info(&deps, "carol");
+    assert_eq!(carol.recommender, Some(deps.api.addr_make("bob")));
+    assert_eq!(carol.registered_at, 1_600_000_000);
+    assert_eq!(carol.reward_points, Uint128::from(300u128));
+    assert_eq!(carol.user_level, UserLevel::Silver);
+    assert_eq!(user_info(&deps, "bob").direct_referrals, vec![deps.api.addr_make("carol")]);
+
+    // 之后积分变动按推荐人数重算等级，不低于导入的等级
+    allocate(&mut deps, "carol", 100);
+    assert_eq!(user_info(&deps, "carol").user_level, UserLevel::Silver);
+    assert_eq!(user_info(&deps, "alice").referral_stats.total_referrals, 1);
+
+    let chain: ReferralChainResponse = from_json(
+        query(
+            deps.as_ref(),
+            env.clone(),
+            QueryMsg::GetReferralChain { user: deps.api.addr_make("carol").to_string(), max_depth: None },
+        )
+        .unwrap(),
+    )
+    .unwrap();
+    assert_eq!(chain.depth, 2);
+
+    let leaderboard: LeaderboardResponse = from_json(
+        query(deps.as_ref(), env, QueryMsg::GetPointsLeaderboard { limit: None, start_after: None, filter: None }).unwrap(),
+    )
+    .unwrap();
+    assert_eq!(leaderboard.entries[0].user, deps.api.addr_make("bob"));
+}
+
+#[test]
+fn test_reassign_referrer() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    let admin = deps.api.addr_make("admin");
+    register(&mut deps, "alice", None);
+    register(&mut deps, "carol", None);
+    register(&mut deps, "bob", Some("alice"));
+    let alice = deps.api.addr_make("alice");
+    let bob = deps.api.addr_make("bob");
+    let carol = deps.api.addr_make("carol");
+
+    let reward = ExecuteMsg::AllocateRewards {
+        user: alice.to_string(),
+        points: Uint128::from(50u128),
+        reason: PointsReason::ReferralReward,
+        related_user: Some(bob.to_string()),
+        event_id: None,
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), reward).unwrap();
+
+    // 不能改到自己的下级名下
+    let err = execute(
+        deps.as_mut(),
+        env.clone(),
+        message_info(&admin, &[]),
+        ExecuteMsg::ReassignReferrer {
+            user: alice.to_string(),
+            new_referrer: bob.to_string(),
+            reason: "ticket #1".to_string(),
+            reward_action: None,
+        },
+    )
+    .unwrap_err();
+    assert!(matches!(err, ContractError::CircularReferral { .. }));
+
+    execute(
+        deps.as_mut(),
+        env,
+        message_info(&admin, &[]),
+        ExecuteMsg::ReassignReferrer {
+            user: bob.to_string(),
+            new_referrer: carol.to_string(),
+            reason: "ticket #2".to_string(),
+            reward_action: Some(ReferralRewardAction::Transfer),
+        },
+    )
+    .unwrap();
+
+    let alice_info = user_info(&deps, "alice");
+    assert!(alice_info.direct_referrals.is_empty());
+    assert_eq!(alice_info.referral_stats.total_referrals, 0);
+    assert_eq!(alice_info.reward_points, Uint128::zero());
+
+    let carol_info = user_info(&deps, "carol");
+    assert_eq!(carol_info.direct_referrals, vec![bob.clone()]);
+    assert_eq!(carol_info.reward_points, Uint128::from(50u128));
+    assert_eq!(user_info(&deps, "bob").recommender, Some(carol));
+}
+
+#[test]
+fn test_bind_referrer() {
+    let mut deps = setup_contract();
+    let mut env = mock_env();
+    let admin = deps.api.addr_make("admin");
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", None);
+    register(&mut deps, "carol", None);
+    let alice = deps.api.addr_make("alice");
+    let bob = deps.api.addr_make("bob");
+    let carol = deps.api.addr_make("carol");
+    let bind = ExecuteMsg::BindReferrer { referrer: alice.to_string() };
+
+    // 默认关闭
+    let err = execute(deps.as_mut(), env.clone(), message_info(&bob, &[]), bind.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::FeatureDisabled { .. }));
+
+    let config = BindReferrerConfig { window: 86400, trigger_payout: true };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        message_info(&admin, &[]),
+        ExecuteMsg::UpdateBindReferrerConfig { config },
+    )
+    .unwrap();
+
+    execute(deps.as_mut(), env.clone(), message_info(&bob, &[]), bind.clone()).unwrap();
+    assert_eq!(user_info(&deps, "bob").recommender, Some(alice.clone()));
+    assert_eq!(user_info(&deps, "alice").direct_referrals, vec![bob.clone()]);
+    assert!(!user_info(&deps, "alice").reward_points.is_zero());
+
+    // 只能绑定一次
+    let err = execute(deps.as_mut(), env.clone(), message_info(&bob, &[]), bind.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidReferrer { .. }));
+
+    // 推荐人冷却期内
+    let err = execute(deps.as_mut(), env.clone(), message_info(&carol, &[]), bind.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::CooldownNotReached { .. }));
+
+    // 超过补绑窗口期
+    env.block.time = env.block.time.plus_seconds(86401);
+    let err = execute(deps.as_mut(), env, message_info(&carol, &[]), bind).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidParameter { .. }));
+}
+
+#[test]
+fn test_register_skips_referral_cooldown() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    let admin = deps.api.addr_make("admin");
+    register(&mut deps, "alice", None);
+    
+    // 注册不受推荐冷却期与每日上限约束
+    for name in ["bob", "carol", "dave"] {
+        register(&mut deps, name, Some("alice"));
+    }
+    assert_eq!(user_info(&deps, "alice").direct_referrals.len(), 3);
+    
+    // 补绑推荐人仍受冷却期约束
+    let config = BindReferrerConfig { window: 86400, trigger_payout: false };
+    execute(
+        deps.as_mut(),
+        env.clone(),
+        message_info(&admin, &[]),
+        ExecuteMsg::UpdateBindReferrerConfig { config },
+    )
+    .unwrap();
+    register(&mut deps, "erin", None);
+    let erin = deps.api.addr_make("erin");
+    let bind = ExecuteMsg::BindReferrer { referrer: deps.api.addr_make("alice").to_string() };
+    let err = execute(deps.as_mut(), env, message_info(&erin, &[]), bind).unwrap_err();
+    assert!(matches!(err, ContractError::CooldownNotReached { .. }));
+}
+
+#[test]
+fn test_deactivate_reparents_downline() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", Some("alice"));
+    register(&mut deps, "carol", Some("bob"));
+    allocate(&mut deps, "bob", 40);
+    let alice = deps.api.addr_make("alice");
+    let bob = deps.api.addr_make("bob");
+    let carol = deps.api.addr_make("carol");
+
+    execute(deps.as_mut(), env.clone(), message_info(&bob, &[]), ExecuteMsg::Deactivate {}).unwrap();
+
+    // 默认按提取结算剩余积分
+    let bob_info = user_info(&deps, "bob");
+    let last_record = last_record(&deps, "bob");
+    assert_eq!(last_record.event_id.as_deref(), Some("withdrawal"));
+    assert_eq!(bob_info.status, UserStatus::Closed);
+    assert_eq!(bob_info.reward_points, Uint128::zero());
+    assert!(bob_info.direct_referrals.is_empty());
+    assert_eq!(user_info(&deps, "carol").recommender, Some(alice.clone()));
+    assert_eq!(user_info(&deps, "alice").direct_referrals, vec![carol]);
+
+    // 已注销账户不能再注销，也不能作为推荐人
+    let err = execute(deps.as_mut(), env, message_info(&bob, &[]), ExecuteMsg::Deactivate {}).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidUserStatus { .. }));
+    let dave = deps.api.addr_make("dave");
+    let register_dave = ExecuteMsg::Register { referrer: Some(bob.to_string()), referral_code: None };
+    let err = execute(deps.as_mut(), mock_env(), message_info(&dave, &[]), register_dave).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidReferrer { .. }));
+}
+
+#[test]
+fn test_downline_and_team_summary() {
+    let mut deps = setup_contract();
+    import_tree(
+        &mut deps,
+        &[
+            ("alice", None, 0),
+            ("bob", Some("alice"), 10),
+            ("carol", Some("alice"), 20),
+            ("dave", Some("bob"), 30),
+            ("erin", Some("dave"), 40),
+            ("frank", Some("erin"), 50),
+        ],
+    );
+    let alice = deps.api.addr_make("alice").to_string();
+    let downline = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, user: &str, start_after: Option<DownlineCursor>, limit: Option<u32>| {
+        let msg = QueryMsg::GetDownline { user: deps.api.addr_make(user).to_string(), max_depth: Some(10), start_after, limit };
+        query(deps.as_ref(), mock_env(), msg).map(|res| from_json::<DownlineResponse>(res).unwrap())
+    };
+
+    let res = downline(&deps, "alice", None, Some(2)).unwrap();
+    let mut first: Vec<_> = res.members.iter().map(|member| (member.depth, member.user.clone())).collect();
+    first.sort();
+    let mut expected = vec![(1, deps.api.addr_make("bob")), (1, deps.api.addr_make("carol"))];
+    expected.sort();
+    assert_eq!(first, expected);
+    assert_eq!(res.next_cursor.as_ref().unwrap().depth, 1);
+
+    // 翻页，且深度不超过 max_referral_depth
+    let res = downline(&deps, "alice", res.next_cursor, None).unwrap();
+    let users: Vec<_> = res.members.iter().map(|member| member.user.clone()).collect();
+    assert_eq!(users, vec![deps.api.addr_make("dave"), deps.api.addr_make("erin")]);
+    assert_eq!(res.members[1].depth, 3);
+    assert_eq!(res.members[1].points, Uint128::from(40u128));
+    assert!(res.next_cursor.is_none());
+
+    // 游标不是该用户的下级时报错
+    let stranger = DownlineCursor { depth: 1, user: deps.api.addr_make("frank").to_string() };
+    assert!(downline(&deps, "alice", Some(stranger), None).is_err());
+
+    // 改挂推荐人后索引随子树移动
+    let reassign = ExecuteMsg::ReassignReferrer {
+        user: deps.api.addr_make("dave").to_string(),
+        new_referrer: deps.api.addr_make("carol").to_string(),
+        reason: "ticket #4".to_string(),
+        reward_action: None,
+    };
+    let admin = deps.api.addr_make("admin");
+    execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), reassign).unwrap();
+    assert!(downline(&deps, "bob", None, None).unwrap().members.is_empty());
+    let carol: Vec<_> = downline(&deps, "carol", None, None).unwrap().members.iter().map(|member| member.depth).collect();
+    assert_eq!(carol, vec![1, 2, 3]);
+
+    let summary = QueryMsg::GetTeamSummary { user: alice };
+    let res: TeamSummaryResponse = from_json(query(deps.as_ref(), mock_env(), summary).unwrap()).unwrap();
+    assert_eq!(res.depth_counts, vec![2, 1, 1]);
+    assert_eq!(res.total_members, 4);
+}
+
+#[test]
+fn test_team_aggregates() {
+    let mut deps = setup_contract();
+    let admin = deps.api.addr_make("admin");
+    import_tree(
+        &mut deps,
+        &[
+            ("alice", None, 0),
+            ("bob", Some("alice"), 10),
+            ("carol", Some("alice"), 20),
+            ("dave", Some("bob"), 30),
+            ("erin", Some("dave"), 40),
+            ("frank", Some("erin"), 50),
+        ],
+    );
+    let summary = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, user: &str| -> TeamSummaryResponse {
+        let msg = QueryMsg::GetTeamSummary { user: deps.api.addr_make(user).to_string() };
+        from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap()
+    };
+
+    // 超出 max_referral_depth 的成员不计入
+    let alice = summary(&deps, "alice");
+    assert_eq!(alice.depth_counts, vec![2, 1, 1]);
+    assert_eq!(alice.active_members, 4);
+    assert_eq!(alice.downline_points, Uint128::from(100u128));
+
+    let dave = deps.api.addr_make("dave");
+    sudo(deps.as_mut(), mock_env(), SudoMsg::FreezeUsers { users: vec![dave.to_string()], frozen: true }).unwrap();
+    assert_eq!(summary(&deps, "alice").active_members, 3);
+
+    // 改挂推荐人时整棵子树随之移动
+    let reassign = ExecuteMsg::ReassignReferrer {
+        user: dave.to_string(),
+        new_referrer: deps.api.addr_make("carol").to_string(),
+        reason: "ticket #3".to_string(),
+        reward_action: None,
+    };
+    execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), reassign).unwrap();
+    assert!(summary(&deps, "bob").depth_counts.is_empty());
+    let carol = summary(&deps, "carol");
+    assert_eq!(carol.depth_counts, vec![1, 1, 1]);
+    assert_eq!(carol.active_members, 2);
+    assert_eq!(summary(&deps, "alice").depth_counts, vec![2, 1, 1]);
+
+    // 成员新获得的积分累加到各上级
+    allocate(&mut deps, "erin", 5);
+    assert_eq!(summary(&deps, "carol").downline_points, Uint128::from(5u128));
+    assert_eq!(summary(&deps, "alice").downline_points, Uint128::from(105u128));
+}
+
+#[test]
+fn test_referral_tree_move_limits() {
+    let mut deps = setup_contract();
+    let admin = deps.api.addr_make("admin");
+    import_tree(&mut deps, &[("alice", None, 0), ("carol", None, 0), ("bob", Some("alice"), 0)]);
+    
+    // 推荐层级上限在已有用户后不可修改
+    let config = dd_registry_cw::state::CONFIG.load(deps.as_ref().storage).unwrap();
+    let deeper = SystemConfig { max_referral_depth: config.max_referral_depth + 1, ..config.clone() };
+    let err = execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), ExecuteMsg::UpdateConfig { config: deeper }).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidParameter { .. }));
+    let cooldown = SystemConfig { referral_cooldown: 60, ..config };
+    execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), ExecuteMsg::UpdateConfig { config: cooldown }).unwrap();
+    
+    // 下级过多的用户不能改挂
+    let names: Vec<String> = (0..=dd_registry_cw::team::MAX_MOVED_SUBTREE).map(|i| format!("member{}", i)).collect();
+    let members: Vec<(&str, Option<&str>, u128)> = names.iter().map(|name| (name.as_str(), Some("bob"), 0)).collect();
+    for chunk in members.chunks(200) {
+        import_tree(&mut deps, chunk);
+    }
+    let reassign = ExecuteMsg::ReassignReferrer {
+        user: deps.api.addr_make("bob").to_string(),
+        new_referrer: deps.api.addr_make("carol").to_string(),
+        reason: "ticket #5".to_string(),
+        reward_action: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), reassign).unwrap_err();
+    assert!(matches!(err, ContractError::LimitExceeded { .. }));
+}
+
+#[test]
+fn test_simulate_register() {
+    let mut deps = setup_contract();
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", Some("alice"));
+    let dave = deps.api.addr_make("dave").to_string();
+    let bob = deps.api.addr_make("bob").to_string();
+
+    let simulate = QueryMsg::SimulateRegister { user: dave.clone(), referrer: Some(bob.clone()) };
+    let res: SimulateRegisterResponse = from_json(query(deps.as_ref(), mock_env(), simulate).unwrap()).unwrap();
+    assert!(res.success);
+    assert_eq!(res.chain_depth, 2);
+    // 注册本身不向上级发放推荐奖励
+    assert!(res.upline_payouts.is_empty());
+    assert_eq!(res.referral_limits.unwrap().cooldown_remaining, 0);
+
+    // 模拟不写入存储
+    let info = QueryMsg::GetUserInfo { user: dave.clone() };
+    assert!(query(deps.as_ref(), mock_env(), info).is_err());
+    assert!(user_info(&deps, "bob").direct_referrals.is_empty());
+
+    let validate = QueryMsg::ValidateReferral { referrer: bob, referee: dave.clone() };
+    let res: ValidationResponse = from_json(query(deps.as_ref(), mock_env(), validate).unwrap()).unwrap();
+    assert_eq!(res.depth, Some(2));
+
+    // 推荐人冷却期内仍可注册，冷却状态仅供参考
+    let alice = deps.api.addr_make("alice").to_string();
+    let simulate = QueryMsg::SimulateRegister { user: dave, referrer: Some(alice) };
+    let res: SimulateRegisterResponse = from_json(query(deps.as_ref(), mock_env(), simulate).unwrap()).unwrap();
+    assert!(res.success);
+    let limits = res.referral_limits.unwrap();
+    assert_eq!(limits.cooldown_remaining, 3600);
+    assert_eq!(limits.daily_referrals, 0);
+}
+
+#[test]
+fn test_simulation_storage_range() {
+    use cosmwasm_std::{Order, Storage};
+    use dd_registry_cw::simulation::SimulationStorage;
+    
+    let mut base = MockStorage::new();
+    for key in [b"a", b"c", b"e"] {
+        base.set(key, b"base");
+    }
+    let mut storage = SimulationStorage::new(&base);
+    storage.set(b"b", b"overlay");
+    storage.set(b"c", b"overlay");
+    storage.remove(b"e");
+    
+    // 缓冲层覆盖与删除逐条合并到底层记录中
+    let keys = |order| -> Vec<(Vec<u8>, Vec<u8>)> { storage.range(None, Some(b"z"), order).collect() };
+    let expected = vec![
+        (b"a".to_vec(), b"base".to_vec()),
+        (b"b".to_vec(), b"overlay".to_vec()),
+        (b"c".to_vec(), b"overlay".to_vec()),
+    ];
+    assert_eq!(keys(Order::Ascending), expected);
+    assert_eq!(keys(Order::Descending), expected.into_iter().rev().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_simulate_allocation() {
+    let mut deps = setup_contract();
+    import_tree(
+        &mut deps,
+        &[
+            ("alice", None, 0),
+            ("bob", Some("alice"), 0),
+            ("carol", Some("bob"), 0),
+            ("dave", Some("carol"), 0),
+            ("erin", Some("dave"), 0),
+        ],
+    );
+
+    let simulate = QueryMsg::SimulateAllocation {
+        user: deps.api.addr_make("erin").to_string(),
+        base_points: Uint128::from(1000u128),
+        reason: PointsReason::ReferralReward,
+    };
+    let res: SimulateAllocationResponse = from_json(query(deps.as_ref(), mock_env(), simulate).unwrap()).unwrap();
+
+    // 只计算到 max_referral_depth 层
+    let breakdown: Vec<_> = res.shares.iter().map(|share| (share.user.clone(), share.depth, share.points.u128())).collect();
+    assert_eq!(
+        breakdown,
+        vec![
+            (deps.api.addr_make("dave"), 1, 500),
+            (deps.api.addr_make("carol"), 2, 200),
+            (deps.api.addr_make("bob"), 3, 100),
+        ]
+    );
+    assert_eq!(res.shares[0].rate, Decimal::from_str("0.5").unwrap());
+    assert_eq!(res.shares[0].multiplier, Decimal::one());
+    assert_eq!(res.total_points, Uint128::from(800u128));
+
+    // 模拟不改变积分
+    assert_eq!(user_info(&deps, "dave").reward_points, Uint128::zero());
+}
+
+#[test]
+fn test_earning_caps_and_budget() {
+    let mut deps = setup_contract();
+    let admin = deps.api.addr_make("admin");
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", None);
+    let mut config = EarningCapsConfig {
+        policy: CapPolicy::Truncate,
+        period: 30 * 86400,
+        global_budget: Some(Uint128::from(1000u128)),
+        caps: vec![ReasonCap {
+            reason: PointsReason::ActivityBonus,
+            user_daily: Some(Uint128::from(100u128)),
+            user_lifetime: Some(Uint128::from(150u128)),
+            global_per_period: None,
+        }],
+    };
+    let update = ExecuteMsg::UpdateEarningCaps { config: config.clone() };
+    execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), update).unwrap();
+
+    // 超出当日上限的部分被截断
+    allocate(&mut deps, "alice", 80);
+    allocate(&mut deps, "alice", 50);
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(100u128));
+
+    // 次日只剩终身额度
+    let mut next_day = mock_env();
+    next_day.block.time = next_day.block.time.plus_seconds(86400);
+    let reward = ExecuteMsg::AllocateRewards {
+        user: deps.api.addr_make("alice").to_string(),
+        points: Uint128::from(100u128),
+        reason: PointsReason::ActivityBonus,
+        related_user: None,
+        event_id: None,
+    };
+    execute(deps.as_mut(), next_day.clone(), message_info(&admin, &[]), reward).unwrap();
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(150u128));
+
+    let status = QueryMsg::GetBudgetStatus { user: Some(deps.api.addr_make("alice").to_string()) };
+    let res: BudgetStatusResponse = from_json(query(deps.as_ref(), next_day.clone(), status).unwrap()).unwrap();
+    assert_eq!(res.global.unwrap().remaining, Uint128::from(850u128));
+    assert_eq!(res.reasons[0].user_daily.as_ref().unwrap().remaining, Uint128::from(50u128));
+    assert_eq!(res.reasons[0].user_lifetime.as_ref().unwrap().remaining, Uint128::zero());
+
+    // 拒绝策略下整笔失败
+    config.policy = CapPolicy::Reject;
+    let update = ExecuteMsg::UpdateEarningCaps { config };
+    execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), update).unwrap();
+    let reward = ExecuteMsg::AllocateRewards {
+        user: deps.api.addr_make("bob").to_string(),
+        points: Uint128::from(120u128),
+        reason: PointsReason::ActivityBonus,
+        related_user: None,
+        event_id: None,
+    };
+    let err = execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), reward).unwrap_err();
+    assert!(matches!(err, ContractError::AmountLimitExceeded { .. }));
+    assert_eq!(user_info(&deps, "bob").reward_points, Uint128::zero());
+}
+
+#[test]
+fn test_campaigns() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    let admin = deps.api.addr_make("admin");
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", Some("alice"));
+    let now = env.block.time.seconds();
+
+    let double_bonus = ExecuteMsg::CreateCampaign {
+        name: "double activity week".to_string(),
+        start_time: now,
+        end_time: now + 7 * 86400,
+        budget: Some(Uint128::from(300u128)),
+        multiplier: Some(Decimal::from_str("2").unwrap()),
+        rate_overrides: None,
+        eligible_reasons: vec![PointsReason::ActivityBonus],
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), double_bonus).unwrap();
+
+    allocate(&mut deps, "alice", 100);
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(200u128));
+    assert_eq!(last_record(&deps, "alice").campaign_id, Some(1));
+
+    // 剩余预算不足时按原值发放
+    allocate(&mut deps, "alice", 100);
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(300u128));
+    assert_eq!(last_record(&deps, "alice").campaign_id, None);
+
+    // 预算用尽后活动停止
+    allocate(&mut deps, "alice", 50);
+    allocate(&mut deps, "alice", 10);
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(410u128));
+    let res: CampaignResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetCampaign { id: 1 }).unwrap()).unwrap();
+    assert_eq!(res.campaign.spent, Uint128::from(300u128));
+    assert!(!res.running);
+
+    // 费率覆盖作用于多层级奖励
+    let referral_boost = ExecuteMsg::CreateCampaign {
+        name: "referral boost".to_string(),
+        start_time: now,
+        end_time: now + 86400,
+        budget: None,
+        multiplier: None,
+        rate_overrides: Some(RateOverrides {
+            direct_referral_rate: Some(Decimal::one()),
+            level_2_rate: None,
+            level_3_rate: None,
+        }),
+        eligible_reasons: vec![PointsReason::ReferralReward],
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), referral_boost).unwrap();
+    let simulate = QueryMsg::SimulateAllocation {
+        user: deps.api.addr_make("bob").to_string(),
+        base_points: Uint128::from(100u128),
+        reason: PointsReason::ReferralReward,
+    };
+    let res: SimulateAllocationResponse = from_json(query(deps.as_ref(), env, simulate).unwrap()).unwrap();
+    assert_eq!(res.shares[0].points, Uint128::from(100u128));
+    assert_eq!(res.shares[0].campaign_id, Some(2));
+}
+
+#[test]
+fn test_seasons() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    let admin = deps.api.addr_make("admin");
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", None);
+    register(&mut deps, "carol", None);
+
+    // 赛季开始前的积分不计入
+    allocate(&mut deps, "carol", 500);
+    let start = ExecuteMsg::StartSeason { name: "2026 Q1".to_string() };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), start).unwrap();
+    allocate(&mut deps, "alice", 100);
+    allocate(&mut deps, "bob", 300);
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), ExecuteMsg::CloseSeason {}).unwrap();
+
+    let start = ExecuteMsg::StartSeason { name: "2026 Q2".to_string() };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), start).unwrap();
+    allocate(&mut deps, "alice", 1000);
+
+    // 往届排行榜保持归档结果
+    let leaderboard = QueryMsg::GetSeasonLeaderboard { season_id: 1, limit: None };
+    let res: SeasonLeaderboardResponse = from_json(query(deps.as_ref(), env.clone(), leaderboard).unwrap()).unwrap();
+    assert!(res.finalized);
+    assert_eq!(res.season.participants, 2);
+    let ranking: Vec<_> = res.entries.iter().map(|entry| (entry.rank, entry.user.clone(), entry.points.u128())).collect();
+    assert_eq!(
+        ranking,
+        vec![(1, deps.api.addr_make("bob"), 300), (2, deps.api.addr_make("alice"), 100)]
+    );
+
+    let leaderboard = QueryMsg::GetSeasonLeaderboard { season_id: 2, limit: Some(10) };
+    let res: SeasonLeaderboardResponse = from_json(query(deps.as_ref(), env.clone(), leaderboard.clone()).unwrap()).unwrap();
+    assert!(!res.finalized);
+    assert_eq!(res.entries.len(), 1);
+    assert_eq!(res.entries[0].points, Uint128::from(1000u128));
+
+    // 同分并列，与主排行榜规则一致
+    allocate(&mut deps, "bob", 1000);
+    allocate(&mut deps, "carol", 200);
+    let res: SeasonLeaderboardResponse = from_json(query(deps.as_ref(), env, leaderboard).unwrap()).unwrap();
+    let ranks: Vec<_> = res.entries.iter().map(|entry| (entry.rank, entry.points.u128())).collect();
+    assert_eq!(ranks, vec![(1, 1000), (1, 1000), (3, 200)]);
+
+    // 终身积分不受赛季影响
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(1100u128));
+}
+
+#[test]
+fn test_points_snapshots_by_height() {
+    let mut deps = setup_contract();
+    let admin = deps.api.addr_make("admin");
+    let alice = deps.api.addr_make("alice");
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", None);
+
+    let at_height = |height: u64| {
+        let mut env = mock_env();
+        env.block.height = height;
+        env
+    };
+    let reward = |user: &str, points: u128| ExecuteMsg::AllocateRewards {
+        user: deps.api.addr_make(user).to_string(),
+        points: Uint128::from(points),
+        reason: PointsReason::ActivityBonus,
+        related_user: None,
+        event_id: None,
+    };
+    let (alice_reward, bob_reward) = (reward("alice", 100), reward("bob", 50));
+    execute(deps.as_mut(), at_height(100), message_info(&admin, &[]), alice_reward).unwrap();
+    execute(deps.as_mut(), at_height(105), message_info(&admin, &[]), bob_reward).unwrap();
+    let withdraw = ExecuteMsg::WithdrawPoints { amount: Uint128::from(30u128) };
+    execute(deps.as_mut(), at_height(110), message_info(&alice, &[]), withdraw).unwrap();
+
+    // 查询结果为该高度区块执行前的余额
+    let points_at = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, height: u64| {
+        let msg = QueryMsg::GetUserPointsAt { user: alice.to_string(), height };
+        let res: UserPointsAtResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        res.points.u128()
+    };
+    assert_eq!(points_at(&deps, 100), 0);
+    assert_eq!(points_at(&deps, 101), 100);
+    assert_eq!(points_at(&deps, 110), 100);
+    assert_eq!(points_at(&deps, 111), 70);
+
+    let total_at = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, height: u64| {
+        let msg = QueryMsg::GetTotalPointsAt { height };
+        let res: TotalPointsAtResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        res.total_points.u128()
+    };
+    assert_eq!(total_at(&deps, 99), 0);
+    assert_eq!(total_at(&deps, 103), 100);
+    assert_eq!(total_at(&deps, 106), 150);
+    assert_eq!(total_at(&deps, 200), 120);
+}
+
+#[test]
+fn test_migrate_seeds_points_total() {
+    // 模拟升级前的合约：余额已存在但没有积分总量
+    let legacy = || {
+        let mut deps = setup_contract();
+        register(&mut deps, "alice", None);
+        allocate(&mut deps, "alice", 100);
+        TOTAL_POINTS.remove(deps.as_mut().storage, mock_env().block.height).unwrap();
+        deps
+    };
+    let withdraw = ExecuteMsg::WithdrawPoints { amount: Uint128::from(30u128) };
+    
+    // 未迁移时扣减报错而不是下溢
+    let mut deps = legacy();
+    let alice = deps.api.addr_make("alice");
+    let err = execute(deps.as_mut(), mock_env(), message_info(&alice, &[]), withdraw.clone()).unwrap_err();
+    assert!(matches!(err, ContractError::SystemError { .. }));
+    
+    let mut deps = legacy();
+    let balance = user_info(&deps, "alice").reward_points;
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert!(res.attributes.iter().any(|attr| attr.key == "total_points" && attr.value == balance.to_string()));
+    execute(deps.as_mut(), mock_env(), message_info(&alice, &[]), withdraw).unwrap();
+    
+    let msg = QueryMsg::GetTotalPointsAt { height: mock_env().block.height + 1 };
+    let res: TotalPointsAtResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.total_points, balance - Uint128::from(30u128));
+}
+
+fn hash_pair(a: [u8; 32], b: [u8; 32]) -> [u8; 32] {
+    let (left, right) = if a <= b { (a, b) } else { (b, a) };
+    Sha256::digest([left, right].concat()).into()
+}
+
+#[test]
+fn test_merkle_distribution() {
+    let mut deps = setup_contract();
+    let admin = deps.api.addr_make("admin");
+    let (alice, bob, carol) = (deps.api.addr_make("alice"), deps.api.addr_make("bob"), deps.api.addr_make("carol"));
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", None);
+    register(&mut deps, "treasury", None);
+
+    // 三个叶子：root = H(H(alice, bob), carol)
+    let leaves = [
+        distribution_leaf(&alice, Uint128::from(100u128)),
+        distribution_leaf(&bob, Uint128::from(200u128)),
+        distribution_leaf(&carol, Uint128::from(300u128)),
+    ];
+    let alice_bob = hash_pair(leaves[0], leaves[1]);
+    let root = hash_pair(alice_bob, leaves[2]);
+
+    let env = mock_env();
+    let expires_at = env.block.time.seconds() + 86400;
+    let create = ExecuteMsg::CreateDistribution {
+        merkle_root: Binary::from(root.to_vec()),
+        total: Uint128::from(600u128),
+        expires_at,
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), create).unwrap();
+
+    let claim = |amount: u128, proof: Vec<[u8; 32]>| ExecuteMsg::ClaimDistribution {
+        id: 1,
+        amount: Uint128::from(amount),
+        proof: proof.into_iter().map(|node| Binary::from(node.to_vec())).collect(),
+    };
+
+    // 数量与证明不符
+    let err = execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), claim(150, vec![leaves[1], leaves[2]])).unwrap_err();
+    assert_eq!(err, ContractError::InvalidMerkleProof);
+
+    execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), claim(100, vec![leaves[1], leaves[2]])).unwrap();
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(100u128));
+
+    let err = execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), claim(100, vec![leaves[1], leaves[2]])).unwrap_err();
+    assert!(matches!(err, ContractError::DistributionAlreadyClaimed { id: 1, .. }));
+
+    // 他人的证明不能被冒用
+    let err = execute(deps.as_mut(), env.clone(), message_info(&bob, &[]), claim(100, vec![leaves[1], leaves[2]])).unwrap_err();
+    assert_eq!(err, ContractError::InvalidMerkleProof);
+
+    // 冻结的用户既不能领取，也不能获得管理员发放
+    let freeze = |frozen: bool| SudoMsg::FreezeUsers { users: vec![bob.to_string()], frozen };
+    sudo(deps.as_mut(), env.clone(), freeze(true)).unwrap();
+    let err = execute(deps.as_mut(), env.clone(), message_info(&bob, &[]), claim(200, vec![leaves[0], leaves[2]])).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidUserStatus { .. }));
+    let reward = ExecuteMsg::AllocateRewards {
+        user: bob.to_string(),
+        points: Uint128::from(10u128),
+        reason: PointsReason::ActivityBonus,
+        related_user: None,
+        event_id: None,
+    };
+    let err = execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), reward).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidUserStatus { .. }));
+    sudo(deps.as_mut(), env.clone(), freeze(false)).unwrap();
+
+    // 到期前不能回收
+    let reclaim = ExecuteMsg::ReclaimDistribution {
+        id: 1,
+        recipient: Some(deps.api.addr_make("treasury").to_string()),
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), reclaim.clone()).unwrap_err();
+
+    let mut expired_env = env.clone();
+    expired_env.block.time = expired_env.block.time.plus_seconds(86401);
+    let err = execute(deps.as_mut(), expired_env.clone(), message_info(&bob, &[]), claim(200, vec![leaves[0], leaves[2]])).unwrap_err();
+    assert!(matches!(err, ContractError::DistributionExpired { id: 1, .. }));
+
+    execute(deps.as_mut(), expired_env.clone(), message_info(&admin, &[]), reclaim).unwrap();
+    assert_eq!(user_info(&deps, "treasury").reward_points, Uint128::from(500u128));
+
+    let msg = QueryMsg::GetDistribution { id: 1 };
+    let res: DistributionResponse = from_json(query(deps.as_ref(), expired_env, msg).unwrap()).unwrap();
+    assert!(res.expired);
+    assert_eq!(res.distribution.claimed, Uint128::from(100u128));
+    assert_eq!(res.distribution.reclaimed, Some(Uint128::from(500u128)));
+}
+
+#[test]
+fn test_leaderboard_pagination_and_filters() {
+    let mut deps = setup_contract();
+    import_tree(&mut deps, &[
+        ("alice", None, 500),
+        ("bob", Some("alice"), 300),
+        ("carol", Some("alice"), 300),
+        ("dave", Some("bob"), 100),
+        ("erin", None, 0),
+    ]);
+
+    let points_page = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, start_after: Option<LeaderboardCursor>, filter: Option<LeaderboardFilter>| {
+        let msg = QueryMsg::GetPointsLeaderboard { limit: Some(2), start_after, filter };
+        let res: LeaderboardResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        res
+    };
+
+    // 翻页后名次连续，同分并列，总数为全部上榜人数
+    let first = points_page(&deps, None, None);
+    assert_eq!(first.total, 4);
+    let ranks: Vec<_> = first.entries.iter().map(|entry| (entry.rank, entry.points.u128())).collect();
+    assert_eq!(ranks, vec![(1, 500), (2, 300)]);
+
+    let second = points_page(&deps, first.next_cursor.clone(), None);
+    let ranks: Vec<_> = second.entries.iter().map(|entry| (entry.rank, entry.points.u128())).collect();
+    assert_eq!(ranks, vec![(2, 300), (4, 100)]);
+    assert_ne!(second.entries[0].user, first.entries[1].user);
+
+    let third = points_page(&deps, second.next_cursor.clone(), None);
+    assert!(third.entries.is_empty());
+    assert!(third.next_cursor.is_none());
+
+    // 游标与存储中的分值不符时报错
+    let mut stale = first.next_cursor.clone().unwrap();
+    stale.score += Uint128::one();
+    let msg = QueryMsg::GetPointsLeaderboard { limit: Some(2), start_after: Some(stale), filter: None };
+    assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+
+    // 积分变化后旧条目被替换
+    allocate(&mut deps, "dave", 900);
+    let first = points_page(&deps, None, None);
+    assert_eq!(first.total, 4);
+    assert_eq!(first.entries[0].user, deps.api.addr_make("dave"));
+    assert_eq!(first.entries[0].points, Uint128::from(1000u128));
+
+    // 按状态筛选
+    let freeze = SudoMsg::FreezeUsers { users: vec![deps.api.addr_make("dave").to_string()], frozen: true };
+    sudo(deps.as_mut(), mock_env(), freeze).unwrap();
+    let active = LeaderboardFilter { status: Some(UserStatus::Active), ..Default::default() };
+    let first = points_page(&deps, None, Some(active.clone()));
+    assert_eq!(first.total, 3);
+    assert_eq!((first.entries[0].user.clone(), first.entries[0].rank), (deps.api.addr_make("alice"), 1));
+    
+    // 筛选后翻页，游标处的并列名次由合约重新计算
+    let second = points_page(&deps, first.next_cursor.clone(), Some(active.clone()));
+    let ranks: Vec<_> = second.entries.iter().map(|entry| (entry.rank, entry.points.u128())).collect();
+    assert_eq!(ranks, vec![(2, 300)]);
+    assert!(second.next_cursor.is_none());
+    
+    // 不符合筛选条件的游标报错
+    let frozen = LeaderboardCursor { user: deps.api.addr_make("dave").to_string(), score: Uint128::from(1000u128) };
+    let msg = QueryMsg::GetPointsLeaderboard { limit: Some(2), start_after: Some(frozen), filter: Some(active.clone()) };
+    assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+    
+    // 迁移时按用户数据重建排行榜
+    let all = points_page(&deps, None, None);
+    dd_registry_cw::state::POINTS_RANKING.clear(deps.as_mut().storage);
+    dd_registry_cw::state::RANKING_GROUPS.clear(deps.as_mut().storage);
+    dd_registry_cw::state::GROUP_RANKING.clear(deps.as_mut().storage);
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(points_page(&deps, None, None), all);
+    assert_eq!(points_page(&deps, first.next_cursor.clone(), Some(active)), second);
+
+    // 推荐人数排行榜
+    let msg = QueryMsg::GetReferralLeaderboard { limit: None, start_after: None, filter: None };
+    let res: ReferralLeaderboardResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!(res.total, 2);
+    let ranking: Vec<_> = res.entries.iter().map(|entry| (entry.user.clone(), entry.referrals, entry.rank)).collect();
+    assert_eq!(
+        ranking,
+        vec![(deps.api.addr_make("alice"), 2, 1), (deps.api.addr_make("bob"), 1, 2)]
+    );
+}
+
+#[test]
+fn test_level_stats_counters() {
+    let mut deps = setup_contract();
+    let admin = deps.api.addr_make("admin");
+    import_tree(&mut deps, &[
+        ("alice", None, 0),
+        ("bob", Some("alice"), 0),
+        ("carol", Some("bob"), 0),
+        ("dave", None, 0),
+    ]);
+    let gold = ImportedUser {
+        address: deps.api.addr_make("erin").to_string(),
+        referrer: None,
+        registered_at: 1_600_000_000,
+        points: Uint128::zero(),
+        level: UserLevel::Gold,
+    };
+    execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), ExecuteMsg::ImportUsers { users: vec![gold] }).unwrap();
+
+    let freeze = SudoMsg::FreezeUsers { users: vec![deps.api.addr_make("dave").to_string()], frozen: true };
+    sudo(deps.as_mut(), mock_env(), freeze).unwrap();
+
+    // bob 注销后 carol 改挂到 alice
+    let bob = deps.api.addr_make("bob");
+    execute(deps.as_mut(), mock_env(), message_info(&bob, &[]), ExecuteMsg::Deactivate {}).unwrap();
+
+    // 响应以枚举为 map 键，serde-json-wasm 无法反序列化，直接调用查询函数
+    let stats = dd_registry_cw::query::query_level_stats(deps.as_ref()).unwrap();
+    query(deps.as_ref(), mock_env(), QueryMsg::GetLevelStats {}).unwrap();
+    assert_eq!(stats.total_users, 5);
+    assert_eq!(stats.total_referrals, 1);
+    assert_eq!(stats.stats.get(&UserLevel::Bronze), Some(&4));
+    assert_eq!(stats.stats.get(&UserLevel::Gold), Some(&1));
+    assert_eq!(stats.status_stats.get(&UserStatus::Active), Some(&3));
+    assert_eq!(stats.status_stats.get(&UserStatus::Suspended), Some(&1));
+    assert_eq!(stats.status_stats.get(&UserStatus::Closed), Some(&1));
+    
+    // 升级前的合约没有计数器，迁移时按用户数据重建
+    let clear = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>| {
+        GLOBAL_STATS.remove(deps.as_mut().storage);
+        LEVEL_STATS.clear(deps.as_mut().storage);
+        STATUS_STATS.clear(deps.as_mut().storage);
+    };
+    clear(&mut deps);
+    migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert_eq!(dd_registry_cw::query::query_level_stats(deps.as_ref()).unwrap(), stats);
+    
+    // 计数器与数据不一致时报错，而不是静默归零
+    clear(&mut deps);
+    let unfreeze = SudoMsg::FreezeUsers { users: vec![deps.api.addr_make("dave").to_string()], frozen: false };
+    assert!(sudo(deps.as_mut(), mock_env(), unfreeze).is_err());
+}
+
+#[test]
+fn test_supply_accounting() {
+    let mut deps = setup_contract();
+    let admin = deps.api.addr_make("admin");
+    let alice = deps.api.addr_make("alice");
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", None);
+    register(&mut deps, "treasury", None);
+    allocate(&mut deps, "alice", 1000);
+    allocate(&mut deps, "bob", 500);
+
+    let config = TransferConfig {
+        enabled: true,
+        fee_rate: Decimal::percent(10),
+        fee_recipient: Some(deps.api.addr_make("treasury")),
+        max_daily_amount: None,
+        max_daily_count: None,
+    };
+    execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), ExecuteMsg::UpdateTransferConfig { config }).unwrap();
+    let transfer = ExecuteMsg::TransferPoints {
+        to: deps.api.addr_make("bob").to_string(),
+        amount: Uint128::from(200u128),
+        memo: None,
+    };
+    execute(deps.as_mut(), mock_env(), message_info(&alice, &[]), transfer).unwrap();
+    let withdraw = ExecuteMsg::WithdrawPoints { amount: Uint128::from(100u128) };
+    execute(deps.as_mut(), mock_env(), message_info(&alice, &[]), withdraw).unwrap();
+
+    let supply: SupplyResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetSupply {}).unwrap()).unwrap();
+    assert_eq!(supply.issued, Uint128::from(1500u128));
+    assert_eq!(supply.deducted, Uint128::zero());
+    assert_eq!(supply.withdrawn, Uint128::from(100u128));
+    assert_eq!(supply.transferred, Uint128::from(200u128));
+    assert_eq!(supply.burned, Uint128::zero());
+    assert_eq!((supply.decayed, supply.expired), (Uint128::zero(), Uint128::zero()));
+    assert!(supply.balanced);
+
+    // 转账不计入发放与扣除
+    let issued: Vec<_> = supply.issued_by_reason.iter().map(|entry| (entry.reason.clone(), entry.amount.u128())).collect();
+    assert_eq!(issued, vec![(PointsReason::ActivityBonus, 1500)]);
+    assert!(supply.deducted_by_reason.is_empty());
+
+    // 未配置金库时手续费销毁
+    let config = TransferConfig {
+        enabled: true,
+        fee_rate: Decimal::percent(10),
+        fee_recipient: None,
+        max_daily_amount: None,
+        max_daily_count: None,
+    };
+    execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), ExecuteMsg::UpdateTransferConfig { config }).unwrap();
+    let transfer = ExecuteMsg::TransferPoints {
+        to: deps.api.addr_make("bob").to_string(),
+        amount: Uint128::from(100u128),
+        memo: None,
+    };
+    execute(deps.as_mut(), mock_env(), message_info(&alice, &[]), transfer).unwrap();
+    let supply: SupplyResponse = from_json(query(deps.as_ref(), mock_env(), QueryMsg::GetSupply {}).unwrap()).unwrap();
+    assert_eq!(supply.transferred, Uint128::from(300u128));
+    assert_eq!(supply.burned, Uint128::from(10u128));
+    assert!(supply.balanced);
+
+    // 余额总和与供应一致
+    let balances: u128 = ["alice", "bob", "treasury"].iter().map(|user| user_info(&deps, user).reward_points.u128()).sum();
+    assert_eq!(supply.total_supply.u128(), balances);
+}
+
+#[test]
+fn test_points_history_filters() {
+    let mut deps = setup_contract();
+    let admin = deps.api.addr_make("admin");
+    let (alice, bob, carol) = (deps.api.addr_make("alice"), deps.api.addr_make("bob"), deps.api.addr_make("carol"));
+    register(&mut deps, "alice", None);
+
+    let start = mock_env().block.time.seconds();
+    let rewards = [
+        (0, 10, PointsReason::ActivityBonus, None, Some("e1")),
+        (100, 20, PointsReason::ReferralReward, Some(&bob), Some("e1")),
+        (200, 30, PointsReason::ReferralReward, Some(&carol), None),
+        (300, 40, PointsReason::ReferralReward, Some(&bob), Some("e2")),
+    ];
+    for (offset, points, reason, related_user, event_id) in rewards {
+        let mut env = mock_env();
+        env.block.time = env.block.time.plus_seconds(offset);
+        let msg = ExecuteMsg::AllocateRewards {
+            user: alice.to_string(),
+            points: Uint128::from(points as u128),
+            reason,
+            related_user: related_user.map(|user| user.to_string()),
+            event_id: event_id.map(|event| event.to_string()),
+        };
+        execute(deps.as_mut(), env, message_info(&admin, &[]), msg).unwrap();
+    }
+
+    let history = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, limit: Option<u32>, start_after: Option<u64>, filter: Option<PointsHistoryFilter>, order: Option<SortOrder>, cursor: Option<u64>| {
+        let msg = QueryMsg::GetPointsHistory { user: alice.to_string(), limit, start_after, filter, order, cursor };
+        let res: PointsHistoryResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        let points: Vec<u128> = res.records.iter().map(|record| record.points_change.u128()).collect();
+        (points, res.total, res.next_cursor)
+    };
+
+    let by_bob = PointsHistoryFilter { related_user: Some(bob.to_string()), ..Default::default() };
+    assert_eq!(history(&deps, None, None, Some(by_bob), None, None), (vec![20, 40], 2, None));
+
+    // 多个条件组合
+    let event_rewards = PointsHistoryFilter {
+        event_id: Some("e1".to_string()),
+        reason: Some(PointsReason::ReferralReward),
+        ..Default::default()
+    };
+    assert_eq!(history(&deps, None, None, Some(event_rewards), None, None), (vec![20], 1, None));
+
+    let window = PointsHistoryFilter { from_time: Some(start + 100), to_time: Some(start + 200), ..Default::default() };
+    assert_eq!(history(&deps, None, None, Some(window.clone()), None, None), (vec![20, 30], 2, None));
+    
+    // 三个条件组合同样直接命中索引
+    let bob_rewards = PointsHistoryFilter {
+        related_user: Some(bob.to_string()),
+        event_id: Some("e2".to_string()),
+        reason: Some(PointsReason::ReferralReward),
+        ..Default::default()
+    };
+    assert_eq!(history(&deps, None, None, Some(bob_rewards), None, None), (vec![40], 1, None));
+    
+    // 游标不在时间范围内时报错
+    for order in [SortOrder::Ascending, SortOrder::Descending] {
+        let msg = QueryMsg::GetPointsHistory {
+            user: alice.to_string(),
+            limit: None,
+            start_after: None,
+            filter: Some(window.clone()),
+            order: Some(order),
+            cursor: Some(3),
+        };
+        assert!(query(deps.as_ref(), mock_env(), msg).is_err());
+    }
+
+    // 倒序按顺序号翻页
+    let (page, total, cursor) = history(&deps, Some(2), None, None, Some(SortOrder::Descending), None);
+    assert_eq!((page, total), (vec![40, 30], 4));
+    let (page, _, cursor) = history(&deps, Some(2), None, None, Some(SortOrder::Descending), cursor);
+    assert_eq!(page, vec![20, 10]);
+    let (page, _, cursor) = history(&deps, Some(2), None, None, Some(SortOrder::Descending), cursor);
+    assert!(page.is_empty() && cursor.is_none());
+
+    // 兼容按时间戳的 start_after
+    assert_eq!(history(&deps, None, Some(start), None, None, None), (vec![20, 30, 40], 3, None));
+}
+
+#[test]
+fn test_typed_registry_events() {
+    let mut deps = setup_contract();
+    let admin = deps.api.addr_make("admin");
+    let (alice, bob) = (deps.api.addr_make("alice"), deps.api.addr_make("bob"));
+    register(&mut deps, "alice", None);
+
+    let msg = ExecuteMsg::Register { referrer: Some(alice.to_string()), referral_code: None };
+    let res = execute(deps.as_mut(), mock_env(), message_info(&bob, &[]), msg).unwrap();
+    let types: Vec<&str> = res.events.iter().map(|event| event.ty.as_str()).collect();
+    assert_eq!(types, vec!["dd_registry.user_registered", "dd_registry.referral_established"]);
+
+    let rewards = [(&alice, 100u128), (&bob, 50)]
+        .iter()
+        .map(|(user, points)| RewardAllocation {
+            user: user.to_string(),
+            points: Uint128::from(*points),
+            reason: PointsReason::ActivityBonus,
+            related_user: None,
+            event_id: Some("batch".to_string()),
+        })
+        .collect();
+    let msg = ExecuteMsg::BatchAllocateRewards { rewards };
+    let res = execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), msg).unwrap();
+
+    // 每个用户一个事件，属性不再合并到响应上
+    let points_events: Vec<_> = res.events.iter().filter(|event| event.ty == "dd_registry.points_changed").collect();
+    assert_eq!(points_events.len(), 2);
+    assert_eq!(res.attributes.len(), 2);
+    for (event, (user, amount)) in points_events.iter().zip([(&alice, "100"), (&bob, "50")]) {
+        let attr = |key: &str| event.attributes.iter().find(|attr| attr.key == key).unwrap().value.clone();
+        assert_eq!(attr("version"), "1");
+        assert_eq!(attr("user"), user.to_string());
+        assert_eq!(attr("direction"), "credit");
+        assert_eq!(attr("amount"), amount);
+        assert_eq!(attr("new_points"), amount);
+        assert_eq!(attr("event_id"), "batch");
+    }
+}
+
+#[test]
+fn test_distribution_claim_ignores_campaigns_and_caps() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    let admin = deps.api.addr_make("admin");
+    let alice = deps.api.addr_make("alice");
+    register(&mut deps, "alice", None);
+    let now = env.block.time.seconds();
+
+    let campaign = ExecuteMsg::CreateCampaign {
+        name: "distribution boost".to_string(),
+        start_time: now,
+        end_time: now + 86400,
+        budget: Some(Uint128::from(1000u128)),
+        multiplier: Some(Decimal::from_str("2").unwrap()),
+        rate_overrides: None,
+        eligible_reasons: vec![PointsReason::Distribution],
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), campaign).unwrap();
+    let caps = EarningCapsConfig {
+        policy: CapPolicy::Truncate,
+        period: 86400,
+        global_budget: None,
+        caps: vec![ReasonCap {
+            reason: PointsReason::Distribution,
+            user_daily: Some(Uint128::from(10u128)),
+            user_lifetime: None,
+            global_per_period: None,
+        }],
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), ExecuteMsg::UpdateEarningCaps { config: caps }).unwrap();
+
+    // 单叶子树的根即叶子本身
+    let leaf = distribution_leaf(&alice, Uint128::from(50u128));
+    let create = ExecuteMsg::CreateDistribution {
+        merkle_root: Binary::from(leaf.to_vec()),
+        total: Uint128::from(50u128),
+        expires_at: now + 86400,
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), create).unwrap();
+    let claim = ExecuteMsg::ClaimDistribution { id: 1, amount: Uint128::from(50u128), proof: vec![] };
+    execute(deps.as_mut(), env.clone(), message_info(&alice, &[]), claim).unwrap();
+
+    // 按原额记入，不加成、不截断，也不占用活动预算与上限用量
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(50u128));
+    let res: CampaignResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetCampaign { id: 1 }).unwrap()).unwrap();
+    assert_eq!(res.campaign.spent, Uint128::zero());
+    let status = QueryMsg::GetBudgetStatus { user: Some(alice.to_string()) };
+    let res: BudgetStatusResponse = from_json(query(deps.as_ref(), env, status).unwrap()).unwrap();
+    assert_eq!(res.reasons[0].user_daily.as_ref().unwrap().used, Uint128::zero());
+}
+
+#[test]
+fn test_internal_credits_skip_caps() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    let admin = deps.api.addr_make("admin");
+    register(&mut deps, "alice", None);
+    register(&mut deps, "carol", None);
+    register(&mut deps, "bob", Some("alice"));
+    let (alice, bob, carol) = (deps.api.addr_make("alice"), deps.api.addr_make("bob"), deps.api.addr_make("carol"));
+
+    let reward = ExecuteMsg::AllocateRewards {
+        user: alice.to_string(),
+        points: Uint128::from(50u128),
+        reason: PointsReason::ReferralReward,
+        related_user: Some(bob.to_string()),
+        event_id: None,
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), reward).unwrap();
+
+    let cap = |reason: PointsReason| ReasonCap {
+        reason,
+        user_daily: Some(Uint128::from(10u128)),
+        user_lifetime: None,
+        global_per_period: None,
+    };
+    let mut config = EarningCapsConfig {
+        policy: CapPolicy::Reject,
+        period: 86400,
+        global_budget: None,
+        caps: vec![cap(PointsReason::ManualAdjustment), cap(PointsReason::ReferralReward)],
+    };
+    let update = ExecuteMsg::UpdateEarningCaps { config: config.clone() };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), update).unwrap();
+
+    // 导入的余额按原额记入
+    let users = vec![ImportedUser {
+        address: deps.api.addr_make("dave").to_string(),
+        referrer: None,
+        registered_at: 1_600_000_000,
+        points: Uint128::from(100u128),
+        level: UserLevel::Bronze,
+    }];
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), ExecuteMsg::ImportUsers { users }).unwrap();
+    assert_eq!(user_info(&deps, "dave").reward_points, Uint128::from(100u128));
+
+    // 外部调用失败的退款不受上限影响
+    let compensation = Compensation {
+        user: carol.clone(),
+        points: Uint128::from(100u128),
+        reason: PointsReason::ManualAdjustment,
+    };
+    let sub_msg = guard_sub_msg(deps.as_mut().storage, BankMsg::Burn { amount: coins(1, "uluna") }.into(), Some(compensation)).unwrap();
+    let failed = Reply {
+        id: sub_msg.id,
+        payload: Binary::default(),
+        gas_used: 0,
+        result: SubMsgResult::Err("payout failed".to_string()),
+    };
+    reply(deps.as_mut(), env.clone(), failed).unwrap();
+    assert_eq!(user_info(&deps, "carol").reward_points, Uint128::from(100u128));
+
+    // 推荐奖励随改挂转移
+    let reassign = ExecuteMsg::ReassignReferrer {
+        user: bob.to_string(),
+        new_referrer: carol.to_string(),
+        reason: "ticket #3".to_string(),
+        reward_action: Some(ReferralRewardAction::Transfer),
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), reassign).unwrap();
+    assert_eq!(user_info(&deps, "carol").reward_points, Uint128::from(150u128));
+
+    // 内部记入不占用上限额度
+    let status = QueryMsg::GetBudgetStatus { user: Some(carol.to_string()) };
+    let res: BudgetStatusResponse = from_json(query(deps.as_ref(), env.clone(), status).unwrap()).unwrap();
+    assert!(res.reasons.iter().all(|reason| reason.user_daily.as_ref().unwrap().used.is_zero()));
+
+    // 模拟分配与实际发放使用相同的截断
+    config.policy = CapPolicy::Truncate;
+    let update = ExecuteMsg::UpdateEarningCaps { config };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), update).unwrap();
+    let simulate = QueryMsg::SimulateAllocation {
+        user: bob.to_string(),
+        base_points: Uint128::from(1000u128),
+        reason: PointsReason::ReferralReward,
+    };
+    let res: SimulateAllocationResponse = from_json(query(deps.as_ref(), env, simulate).unwrap()).unwrap();
+    assert_eq!(res.shares[0].user, carol);
+    assert_eq!(res.shares[0].points, Uint128::from(10u128));
+    assert_eq!(res.total_points, Uint128::from(10u128));
+}
+
+#[test]
+fn test_campaigns_skip_internal_credits() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    let admin = deps.api.addr_make("admin");
+    let alice = deps.api.addr_make("alice");
+    register(&mut deps, "alice", None);
+    let now = env.block.time.seconds();
+
+    let campaign = ExecuteMsg::CreateCampaign {
+        name: "adjustment boost".to_string(),
+        start_time: now,
+        end_time: now + 86400,
+        budget: Some(Uint128::from(1000u128)),
+        multiplier: Some(Decimal::from_str("2").unwrap()),
+        rate_overrides: None,
+        eligible_reasons: vec![PointsReason::ManualAdjustment],
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), campaign).unwrap();
+
+    // 迁移余额与退款不加成，也不占用活动预算
+    let users = vec![ImportedUser {
+        address: deps.api.addr_make("bob").to_string(),
+        referrer: None,
+        registered_at: 1_600_000_000,
+        points: Uint128::from(100u128),
+        level: UserLevel::Bronze,
+    }];
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), ExecuteMsg::ImportUsers { users }).unwrap();
+    assert_eq!(user_info(&deps, "bob").reward_points, Uint128::from(100u128));
+
+    let compensation = Compensation {
+        user: alice.clone(),
+        points: Uint128::from(100u128),
+        reason: PointsReason::ManualAdjustment,
+    };
+    let sub_msg = guard_sub_msg(deps.as_mut().storage, BankMsg::Burn { amount: coins(1, "uluna") }.into(), Some(compensation)).unwrap();
+    let failed = Reply {
+        id: sub_msg.id,
+        payload: Binary::default(),
+        gas_used: 0,
+        result: SubMsgResult::Err("payout failed".to_string()),
+    };
+    reply(deps.as_mut(), env.clone(), failed).unwrap();
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(100u128));
+
+    let res: CampaignResponse = from_json(query(deps.as_ref(), env.clone(), QueryMsg::GetCampaign { id: 1 }).unwrap()).unwrap();
+    assert_eq!(res.campaign.spent, Uint128::zero());
+
+    // 管理员发放仍按活动加成
+    let reward = ExecuteMsg::AllocateRewards {
+        user: alice.to_string(),
+        points: Uint128::from(10u128),
+        reason: PointsReason::ManualAdjustment,
+        related_user: None,
+        event_id: None,
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), reward).unwrap();
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(120u128));
+}
+
+#[test]
+fn test_freeze_users_keeps_banned_and_closed() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    register(&mut deps, "alice", None);
+    register(&mut deps, "bob", None);
+    register(&mut deps, "carol", None);
+    let (alice, bob, carol) = (deps.api.addr_make("alice"), deps.api.addr_make("bob"), deps.api.addr_make("carol"));
+    dd_registry_cw::security::set_user_status(deps.as_mut().storage, &bob, UserStatus::Banned).unwrap();
+    execute(deps.as_mut(), env.clone(), message_info(&carol, &[]), ExecuteMsg::Deactivate {}).unwrap();
+
+    let freeze = |frozen: bool| SudoMsg::FreezeUsers {
+        users: vec![alice.to_string(), bob.to_string(), carol.to_string()],
+        frozen,
+    };
+    let res = sudo(deps.as_mut(), env.clone(), freeze(true)).unwrap();
+    assert!(res.attributes.iter().any(|attr| attr.key == "skipped" && attr.value == "2"));
+    assert_eq!(user_info(&deps, "alice").status, UserStatus::Suspended);
+    assert_eq!(user_info(&deps, "bob").status, UserStatus::Banned);
+    assert_eq!(user_info(&deps, "carol").status, UserStatus::Closed);
+
+    // 解冻不会恢复封禁或已注销的账户
+    sudo(deps.as_mut(), env, freeze(false)).unwrap();
+    assert_eq!(user_info(&deps, "alice").status, UserStatus::Active);
+    assert_eq!(user_info(&deps, "bob").status, UserStatus::Banned);
+    assert_eq!(user_info(&deps, "carol").status, UserStatus::Closed);
+}
+
+#[test]
+fn test_reassign_referrer_claws_back_once() {
+    let mut deps = setup_contract();
+    let env = mock_env();
+    let admin = deps.api.addr_make("admin");
+    register(&mut deps, "alice", None);
+    register(&mut deps, "carol", None);
+    register(&mut deps, "dave", None);
+    register(&mut deps, "bob", Some("alice"));
+    let (alice, bob) = (deps.api.addr_make("alice"), deps.api.addr_make("bob"));
+    allocate(&mut deps, "alice", 100);
+    let reward = ExecuteMsg::AllocateRewards {
+        user: alice.to_string(),
+        points: Uint128::from(50u128),
+        reason: PointsReason::ReferralReward,
+        related_user: Some(bob.to_string()),
+        event_id: None,
+    };
+    execute(deps.as_mut(), env.clone(), message_info(&admin, &[]), reward).unwrap();
+
+    let reassign = |deps: &mut OwnedDeps<MockStorage, MockApi, MockQuerier>, new_referrer: &str, action: ReferralRewardAction| {
+        let msg = ExecuteMsg::ReassignReferrer {
+            user: deps.api.addr_make("bob").to_string(),
+            new_referrer: deps.api.addr_make(new_referrer).to_string(),
+            reason: "correction".to_string(),
+            reward_action: Some(action),
+        };
+        let admin = deps.api.addr_make("admin");
+        execute(deps.as_mut(), mock_env(), message_info(&admin, &[]), msg)
+    };
+
+    reassign(&mut deps, "carol", ReferralRewardAction::ClawBack).unwrap();
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(100u128));
+    // 更正不会触发新推荐人的推荐冷却
+    assert_eq!(user_info(&deps, "carol").referral_stats.last_referral_time, None);
+
+    // 改回原推荐人后再次更正，已追回的奖励不再重复追回
+    reassign(&mut deps, "alice", ReferralRewardAction::Keep).unwrap();
+    reassign(&mut deps, "dave", ReferralRewardAction::ClawBack).unwrap();
+    assert_eq!(user_info(&deps, "alice").reward_points, Uint128::from(100u128));
+
+    // 已注销的用户不能更正推荐人
+    execute(deps.as_mut(), env, message_info(&bob, &[]), ExecuteMsg::Deactivate {}).unwrap();
+    let err = reassign(&mut deps, "carol", ReferralRewardAction::Keep).unwrap_err();
+    assert!(matches!(err, ContractError::InvalidUserStatus { .. }));
+}
+
+#[test]
+fn test_points_history_legacy_records() {
+    let mut deps = setup_contract();
+    register(&mut deps, "alice", None);
+    let alice = deps.api.addr_make("alice");
+
+    // 旧版本只把流水保存在用户信息中
+    let now = mock_env().block.time.seconds();
+    let mut info = user_info(&deps, "alice");
+    info.points_history = [(100, 10u128), (50, 20), (20, 30)]
+        .into_iter()
+        .map(|(age, points)| PointsRecord {
+            points_change: Uint128::from(points),
+            reason: PointsReason::ActivityBonus,
+            timestamp: now - age,
+            related_user: None,
+            event_id: None,
+            campaign_id: None,
+        })
+        .collect();
+    dd_registry_cw::state::USER_MAP.save(deps.as_mut().storage, &alice, &info).unwrap();
+
+    let history = |deps: &OwnedDeps<MockStorage, MockApi, MockQuerier>, cursor: Option<u64>| {
+        let msg = QueryMsg::GetPointsHistory {
+            user: alice.to_string(),
+            limit: Some(2),
+            start_after: None,
+            filter: None,
+            order: None,
+            cursor,
+        };
+        let res: PointsHistoryResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+        let points: Vec<u128> = res.records.iter().map(|record| record.points_change.u128()).collect();
+        (points, res.total, res.next_cursor)
+    };
+    let (page, total, cursor) = history(&deps, None);
+    assert_eq!((page, total), (vec![10, 20], 3));
+
+    // 下次积分变动时迁入索引，迁移前的游标继续有效
+    allocate(&mut deps, "alice", 40);
+    assert!(user_info(&deps, "alice").points_history.is_empty());
+    assert_eq!(history(&deps, cursor), (vec![30, 40], 4, Some(3)));
+    
+    // 迁移时一次迁入所有用户的旧流水
+    register(&mut deps, "bob", None);
+    let bob = deps.api.addr_make("bob");
+    let legacy = UserInfo { points_history: info.points_history.clone(), ..user_info(&deps, "bob") };
+    dd_registry_cw::state::USER_MAP.save(deps.as_mut().storage, &bob, &legacy).unwrap();
+    let res = migrate(deps.as_mut(), mock_env(), MigrateMsg {}).unwrap();
+    assert!(res.attributes.iter().any(|attr| attr.key == "history_users" && attr.value == "1"));
+    assert!(user_info(&deps, "bob").points_history.is_empty());
+    let msg = QueryMsg::GetPointsHistory {
+        user: bob.to_string(),
+        limit: None,
+        start_after: None,
+        filter: None,
+        order: Some(SortOrder::Descending),
+        cursor: None,
+    };
+    let res: PointsHistoryResponse = from_json(query(deps.as_ref(), mock_env(), msg).unwrap()).unwrap();
+    assert_eq!((res.records, res.total), (info.points_history.into_iter().rev().collect(), 3));
+}